@@ -0,0 +1,509 @@
+use chrono::{Local, TimeZone};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{self, SystemTime};
+use tracing::{error, warn};
+
+use crate::cache::cached_endpoint;
+use crate::config::{instance_display_name, AlertRule, ConfigFields, RuleKind};
+use crate::database::{
+    connect_db, get_alerts, get_ping_data, update_alerts, Alert, Ping, ServiceState,
+};
+use crate::maintenance::{global_maintenance, services_in_maintenance};
+use crate::notifier::{notify, AlertStatus, Notification};
+use crate::services::dockwatch::DockwatchStatsResponse;
+use crate::services::gluetun::GluetunV1VPNStatus;
+use crate::services::proxmox::ProxmoxV2Data;
+use crate::services::registry::find_service;
+
+// A rule condition that holds right now
+struct Condition {
+    service: String,
+    subject: String,
+    message: String,
+    // When it started, if the source knows, otherwise when it was first seen
+    since: Option<i64>,
+}
+
+// Service whose instances a rule watches, None for rules on pinged services
+pub fn rule_service(kind: RuleKind) -> Option<&'static str> {
+    match kind {
+        RuleKind::ServiceDown => None,
+        RuleKind::VpnDown => Some("gluetun"),
+        RuleKind::ContainerUnhealthy => Some("dockwatch"),
+        RuleKind::StorageUsage => Some("proxmox"),
+    }
+}
+
+fn watches(rule: &AlertRule, service: &str) -> bool {
+    rule.services.is_empty() || rule.services.iter().any(|watched| watched == service)
+}
+
+fn format_time(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .earliest()
+        .map(|time| time.format("%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+// Conditions of a rule that hold, plus the services that couldn't be checked
+async fn conditions(
+    config: &Arc<ConfigFields>,
+    rule: &AlertRule,
+    pings: &[Ping],
+) -> (Vec<Condition>, Vec<String>) {
+    let mut conditions = Vec::new();
+    let mut unchecked = Vec::new();
+
+    let Some(service) = rule_service(rule.kind).and_then(find_service) else {
+        for ping in pings {
+            if ping.state == ServiceState::Down && watches(rule, &ping.name) {
+                conditions.push(Condition {
+                    service: ping.name.clone(),
+                    subject: ping.name.clone(),
+                    message: format!(
+                        "{} is down since {}: {}",
+                        ping.name,
+                        format_time(ping.state_since),
+                        ping.message.as_deref().unwrap_or("check failed")
+                    ),
+                    since: Some(ping.state_since),
+                });
+            }
+        }
+        return (conditions, unchecked);
+    };
+
+    // Owned names, instances aren't Sync and can't be held across awaits
+    let instances: Vec<String> = service
+        .instances(config)
+        .iter()
+        .filter(|instance| instance.enabled())
+        .map(|instance| String::from(instance.name()))
+        .collect();
+
+    for instance in instances {
+        let name = instance_display_name(service.name(), &instance);
+        if !watches(rule, &name) {
+            continue;
+        }
+
+        let mut condition = |subject: String, message: String| {
+            conditions.push(Condition {
+                service: name.clone(),
+                subject,
+                message,
+                since: None,
+            })
+        };
+
+        match rule.kind {
+            // An unreachable Gluetun usually means the VPN is gone too
+            RuleKind::VpnDown => {
+                match cached_endpoint::<GluetunV1VPNStatus>(
+                    config,
+                    service,
+                    &instance,
+                    "vpn_status",
+                )
+                .await
+                {
+                    Ok(vpn) if vpn.status == "running" => (),
+                    Ok(vpn) => condition(name.clone(), format!("VPN is {}", vpn.status)),
+                    Err(e) => condition(name.clone(), format!("VPN status unavailable: {}", e)),
+                }
+            }
+            RuleKind::ContainerUnhealthy => {
+                let stats = cached_endpoint::<DockwatchStatsResponse>(
+                    config, service, &instance, "overview",
+                )
+                .await;
+                match stats {
+                    Ok(stats) if stats.response.health.unhealthy as f64 > rule.threshold() => {
+                        condition(
+                            name.clone(),
+                            format!("{} unhealthy containers", stats.response.health.unhealthy),
+                        )
+                    }
+                    Ok(_) => (),
+                    // Unreachable instances are a job for service_down rules
+                    Err(e) => {
+                        warn!("Failed to check {} for alerts: {}", name, e);
+                        unchecked.push(name.clone());
+                    }
+                }
+            }
+            RuleKind::StorageUsage => {
+                match cached_endpoint::<Vec<ProxmoxV2Data>>(config, service, &instance, "node")
+                    .await
+                {
+                    Ok(nodes) => {
+                        for node in nodes {
+                            for storage in node.storage.data.iter().filter(|s| s.total > 0) {
+                                let usage = storage.used as f64 * 100.0 / storage.total as f64;
+                                if usage > rule.threshold() {
+                                    condition(
+                                        format!("{} {}/{}", name, node.node, storage.storage),
+                                        format!(
+                                            "{} on {} is {:.0}% full",
+                                            storage.storage, node.node, usage
+                                        ),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Failed to check {} for alerts: {}", name, e);
+                        unchecked.push(name.clone());
+                    }
+                }
+            }
+            RuleKind::ServiceDown => (),
+        }
+    }
+
+    (conditions, unchecked)
+}
+
+// Sends to the rule's channels, true if at least one got it
+async fn deliver(config: &ConfigFields, rule: &AlertRule, notification: &Notification) -> bool {
+    let mut delivered = false;
+
+    for channel in config
+        .alerts
+        .channels
+        .iter()
+        .filter(|channel| rule.channels.is_empty() || rule.channels.contains(&channel.name))
+    {
+        match notify(channel, notification).await {
+            Ok(()) => delivered = true,
+            Err(e) => warn!("Failed to send alert to {}: {}", channel.name, e),
+        }
+    }
+
+    delivered
+}
+
+fn notification(alert: &Alert, status: AlertStatus, now: i64) -> Notification {
+    Notification {
+        rule: alert.rule.clone(),
+        service: alert.service.clone(),
+        subject: alert.subject.clone(),
+        status,
+        message: alert.message.clone(),
+        since: alert.since,
+        timestamp: now,
+    }
+}
+
+// Evaluates every rule, sends new alerts once they held for the rule's duration and recoveries for sent ones
+// Services in maintenance keep their alerts as they are, so they neither fire nor recover
+pub async fn evaluate_alerts(config: Arc<ConfigFields>) {
+    if let Err(e) = run(&config).await {
+        error!("Failed to evaluate alerts: {}", e);
+    }
+}
+
+async fn run(config: &Arc<ConfigFields>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let now = SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)?
+        .as_secs() as i64;
+
+    let (pings, maintenance, global, existing) = connect_db()
+        .and_then(|conn| {
+            let pings = get_ping_data(&conn, 0)?;
+            let maintenance = services_in_maintenance(&conn, now)?;
+            let global = global_maintenance(&conn, now)?;
+            let existing = get_alerts(&conn)?;
+            conn.close().map_err(|(_, e)| e)?;
+            Ok((pings, maintenance, global, existing))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let in_maintenance = |service: &str| global.is_some() || maintenance.contains_key(service);
+    let (active, resolved) = evaluate(config, &pings, in_maintenance, existing, now).await;
+
+    let mut conn = connect_db().map_err(|e| e.to_string())?;
+    update_alerts(&mut conn, &active, &resolved).map_err(|e| e.to_string())?;
+    conn.close().map_err(|(_, e)| e)?;
+
+    Ok(())
+}
+
+// Alerts to keep and alerts that are resolved, given the stored ones
+async fn evaluate(
+    config: &Arc<ConfigFields>,
+    pings: &[Ping],
+    in_maintenance: impl Fn(&str) -> bool,
+    existing: Vec<Alert>,
+    now: i64,
+) -> (Vec<Alert>, Vec<Alert>) {
+    let mut existing: HashMap<(String, String), Alert> = existing
+        .into_iter()
+        .map(|alert| ((alert.rule.clone(), alert.subject.clone()), alert))
+        .collect();
+    let mut active = Vec::new();
+    let mut resolved = Vec::new();
+    // (rule, service) pairs that couldn't be checked keep their alerts
+    let mut unknown: Vec<(String, String)> = Vec::new();
+
+    for rule in &config.alerts.rules {
+        let (conditions, unchecked) = conditions(config, rule, pings).await;
+        unknown.extend(
+            unchecked
+                .into_iter()
+                .map(|service| (rule.name.clone(), service)),
+        );
+
+        for condition in conditions {
+            if in_maintenance(&condition.service) {
+                continue;
+            }
+
+            let previous = existing.remove(&(rule.name.clone(), condition.subject.clone()));
+            let mut alert = Alert {
+                rule: rule.name.clone(),
+                service: condition.service,
+                subject: condition.subject,
+                message: condition.message,
+                since: previous
+                    .as_ref()
+                    .map_or(condition.since.unwrap_or(now), |previous| previous.since),
+                notified: previous.is_some_and(|previous| previous.notified),
+            };
+
+            if !alert.notified && now - alert.since >= rule.duration as i64 {
+                alert.notified = deliver(
+                    config,
+                    rule,
+                    &notification(&alert, AlertStatus::Firing, now),
+                )
+                .await;
+            }
+            active.push(alert);
+        }
+    }
+
+    // Whatever is left no longer holds, or its rule was removed
+    for (_, alert) in existing {
+        let Some(rule) = config
+            .alerts
+            .rules
+            .iter()
+            .find(|rule| rule.name == alert.rule)
+        else {
+            resolved.push(alert);
+            continue;
+        };
+
+        if in_maintenance(&alert.service)
+            || unknown.contains(&(alert.rule.clone(), alert.service.clone()))
+        {
+            active.push(alert);
+            continue;
+        }
+
+        // A failed recovery is retried on the next evaluation
+        if !alert.notified
+            || deliver(
+                config,
+                rule,
+                &notification(&alert, AlertStatus::Resolved, now),
+            )
+            .await
+        {
+            resolved.push(alert);
+        } else {
+            active.push(alert);
+        }
+    }
+
+    (active, resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_default_config;
+    use crate::notifier::tests::{channel, listen, Received};
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    const NOW: i64 = 1_700_000_000;
+
+    // A config with one service_down rule sending to a local webhook
+    async fn setup(duration: u64, status: u16) -> (Arc<ConfigFields>, UnboundedReceiver<Received>) {
+        let (url, requests) = listen(status).await;
+
+        let mut config = create_default_config();
+        config.alerts.channels = vec![channel(&format!(
+            "name = \"hook\"\ntype = \"webhook\"\nurl = \"{}\"",
+            url
+        ))];
+        config.alerts.rules = vec![toml::from_str(&format!(
+            "name = \"Service down\"\ntype = \"service_down\"\nfor = {}",
+            duration
+        ))
+        .unwrap()];
+
+        (Arc::new(config), requests)
+    }
+
+    fn ping(name: &str, state: ServiceState) -> Ping {
+        Ping {
+            id: Some(1),
+            name: String::from(name),
+            status: 0,
+            up: state == ServiceState::Up,
+            message: Some(String::from("connection refused")),
+            state,
+            state_since: NOW - 600,
+            cert_issuer: None,
+            cert_expires: None,
+            cert_expiring: false,
+            uptime_24h: None,
+            uptime_7d: None,
+            uptime_30d: None,
+            avg_latency: None,
+            latency: None,
+            last_check: NOW as u32,
+            url: String::from("http://nas.lan"),
+            app_order: 0,
+            is_favorite: false,
+        }
+    }
+
+    fn status(request: Option<Received>) -> serde_json::Value {
+        request.unwrap().json()["status"].clone()
+    }
+
+    #[tokio::test]
+    async fn fires_once_and_recovers() {
+        let (config, mut requests) = setup(0, 200).await;
+        let down = [ping("nas", ServiceState::Down)];
+
+        let (active, resolved) = evaluate(&config, &down, |_| false, Vec::new(), NOW).await;
+        assert_eq!(active.len(), 1);
+        assert!(resolved.is_empty());
+        assert!(active[0].notified);
+        assert_eq!(active[0].since, NOW - 600);
+        assert_eq!(status(requests.recv().await), "firing");
+
+        // Already sent, nothing new
+        let (active, _) = evaluate(&config, &down, |_| false, active, NOW + 60).await;
+        assert_eq!(active.len(), 1);
+        assert!(requests.try_recv().is_err());
+
+        let up = [ping("nas", ServiceState::Up)];
+        let (active, resolved) = evaluate(&config, &up, |_| false, active, NOW + 120).await;
+        assert!(active.is_empty());
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(status(requests.recv().await), "resolved");
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_rule_duration() {
+        let (config, mut requests) = setup(900, 200).await;
+        let down = [ping("nas", ServiceState::Down)];
+
+        let (active, _) = evaluate(&config, &down, |_| false, Vec::new(), NOW).await;
+        assert!(!active[0].notified);
+        assert!(requests.try_recv().is_err());
+
+        let (active, _) = evaluate(&config, &down, |_| false, active, NOW + 300).await;
+        assert!(active[0].notified);
+        assert_eq!(status(requests.recv().await), "firing");
+    }
+
+    #[tokio::test]
+    async fn resolves_unsent_alerts_quietly() {
+        let (config, mut requests) = setup(900, 200).await;
+
+        let (active, _) = evaluate(
+            &config,
+            &[ping("nas", ServiceState::Down)],
+            |_| false,
+            Vec::new(),
+            NOW,
+        )
+        .await;
+        let (active, resolved) = evaluate(
+            &config,
+            &[ping("nas", ServiceState::Up)],
+            |_| false,
+            active,
+            NOW + 60,
+        )
+        .await;
+        assert!(active.is_empty());
+        assert_eq!(resolved.len(), 1);
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn retries_failed_deliveries() {
+        let (config, mut requests) = setup(0, 500).await;
+        let down = [ping("nas", ServiceState::Down)];
+
+        let (active, _) = evaluate(&config, &down, |_| false, Vec::new(), NOW).await;
+        assert!(!active[0].notified);
+        let (active, _) = evaluate(&config, &down, |_| false, active, NOW + 60).await;
+        assert!(!active[0].notified);
+        assert_eq!(status(requests.recv().await), "firing");
+        assert_eq!(status(requests.recv().await), "firing");
+
+        // A recovery that couldn't be sent keeps the alert
+        let mut sent = active;
+        sent[0].notified = true;
+        let (active, resolved) = evaluate(
+            &config,
+            &[ping("nas", ServiceState::Up)],
+            |_| false,
+            sent,
+            NOW + 120,
+        )
+        .await;
+        assert_eq!(active.len(), 1);
+        assert!(resolved.is_empty());
+        assert_eq!(status(requests.recv().await), "resolved");
+    }
+
+    #[tokio::test]
+    async fn holds_alerts_in_maintenance() {
+        let (config, mut requests) = setup(0, 200).await;
+        let down = [ping("nas", ServiceState::Down)];
+
+        let (active, _) = evaluate(&config, &down, |_| true, Vec::new(), NOW).await;
+        assert!(active.is_empty());
+
+        let (active, _) = evaluate(&config, &down, |_| false, active, NOW).await;
+        assert_eq!(status(requests.recv().await), "firing");
+
+        // Neither recovers while the window lasts
+        let up = [ping("nas", ServiceState::Up)];
+        let (active, resolved) = evaluate(&config, &up, |_| true, active, NOW + 60).await;
+        assert_eq!(active.len(), 1);
+        assert!(resolved.is_empty());
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn drops_alerts_of_removed_rules() {
+        let (config, mut requests) = setup(0, 200).await;
+        let stale = Alert {
+            rule: String::from("Removed"),
+            service: String::from("nas"),
+            subject: String::from("nas"),
+            message: String::from("nas is down"),
+            since: NOW - 600,
+            notified: true,
+        };
+
+        let (active, resolved) = evaluate(&config, &[], |_| false, vec![stale], NOW).await;
+        assert!(active.is_empty());
+        assert_eq!(resolved.len(), 1);
+        assert!(requests.try_recv().is_err());
+    }
+}
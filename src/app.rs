@@ -1,9 +1,14 @@
 use std::process;
 use std::time::Instant;
+use tracing::error;
 
 use homedash_rs::{
-    config::get_config,
+    config::{read_config, store_config, watch_config, LoggingConfig},
     database::{connect_db, create_schemas, populate_tables},
+    logging::init_logging,
+    poller::start_poller,
+    proxy_listener::start_proxy_listener,
+    validation::validate_config,
 };
 
 #[derive(Clone)]
@@ -19,13 +24,25 @@ pub struct ApplicationState {
 pub fn main() -> ApplicationState {
     let start_time = Instant::now();
 
-    let config = get_config()
+    let config = read_config()
         .map_err(|e| {
-            println!("Config Error: {}", e);
+            init_logging(&LoggingConfig::default());
+            error!("Config Error: {}", e);
             process::exit(1);
         })
         .unwrap();
 
+    init_logging(&config.logging);
+
+    let issues = validate_config(&config);
+    if !issues.is_empty() {
+        error!("Config Error: {} problem(s) found", issues.len());
+        for issue in issues {
+            error!("  {}", issue);
+        }
+        process::exit(1);
+    }
+
     let conn = connect_db().unwrap();
     create_schemas(&conn).unwrap();
     populate_tables(&conn, &config.clone()).unwrap();
@@ -33,6 +50,26 @@ pub fn main() -> ApplicationState {
     // Close db connection
     conn.close().unwrap();
 
+    store_config(config.clone());
+
+    // Keep the cache warm and the ping status current
+    start_poller();
+
+    // Where the reverse proxy connects when it logs users in
+    if config.auth.enabled {
+        start_proxy_listener(&config.auth.proxy);
+    }
+
+    // Keep the services table in sync when config.toml changes
+    watch_config(|config| match connect_db() {
+        Ok(conn) => {
+            if let Err(e) = populate_tables(&conn, config) {
+                error!("Failed to update services: {}", e);
+            }
+        }
+        Err(e) => error!("Failed to update services: {}", e),
+    });
+
     return ApplicationState {
         timestamp: start_time,
         username: config.clone().username,
@@ -0,0 +1,428 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::error::Error;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
+use tracing::{error, warn};
+use tuono_lib::axum::http::header::{AUTHORIZATION, COOKIE};
+use tuono_lib::axum::http::{HeaderMap, StatusCode};
+
+use crate::config::{config_snapshot, AuthConfig, AuthMode, ProxyAuthConfig};
+use crate::database::{
+    connect_db, delete_session, get_service_name, get_session_user, get_user_credentials,
+    insert_session, User,
+};
+use crate::proxy_listener::{proxy_token, TOKEN_HEADER};
+
+pub const SESSION_COOKIE: &str = "homedash_session";
+
+// Verified instead of a real hash for unknown users, so they take as long as wrong passwords
+static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+// What a route does, in "mutating" mode only Write needs a login
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+// Someone who logged in, locally or at the reverse proxy
+#[derive(Debug, Clone)]
+pub struct Login {
+    pub username: String,
+    // Services table names they see, None for all
+    pub services: Option<Vec<String>>,
+    // Whether they may change things
+    pub control: bool,
+    // Logged in at the reverse proxy, logging out happens there
+    pub proxied: bool,
+}
+
+impl Login {
+    fn local(user: User) -> Self {
+        Login {
+            username: user.username,
+            services: None,
+            control: true,
+            proxied: false,
+        }
+    }
+
+    pub fn can_see(&self, service: &str) -> bool {
+        self.services
+            .as_ref()
+            .is_none_or(|services| services.iter().any(|visible| visible == service))
+    }
+}
+
+pub fn hash_password(password: &str) -> Result<String, Box<dyn Error>> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| e.to_string())?;
+
+    Ok(hash.to_string())
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64)
+}
+
+// The user if the password matches
+fn check_credentials(username: &str, password: &str) -> Result<Option<User>, Box<dyn Error>> {
+    let conn = connect_db()?;
+    let credentials = get_user_credentials(&conn, username)?;
+
+    // Close db connection
+    conn.close().map_err(|(_, e)| e)?;
+
+    match credentials {
+        Some((user, hash)) => Ok(verify_password(password, &hash).then_some(user)),
+        None => {
+            let dummy = DUMMY_HASH.get_or_init(|| hash_password("homedash").unwrap_or_default());
+            verify_password(password, dummy);
+            Ok(None)
+        }
+    }
+}
+
+// Starts a session, the token goes into the session cookie
+pub fn start_session(
+    config: &AuthConfig,
+    username: &str,
+    password: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let Some(user) = check_credentials(username, password)? else {
+        warn!("Failed login for {}", username);
+        return Ok(None);
+    };
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    let conn = connect_db()?;
+    insert_session(
+        &conn,
+        &token,
+        user.id,
+        now() + config.session_days as i64 * 86400,
+    )?;
+
+    // Close db connection
+    conn.close().map_err(|(_, e)| e)?;
+
+    Ok(Some(token))
+}
+
+// Ends the session of the request's cookie, if there is one
+pub fn end_session(headers: &HeaderMap) -> Result<(), Box<dyn Error>> {
+    let Some(token) = session_token(headers) else {
+        return Ok(());
+    };
+
+    let conn = connect_db()?;
+    delete_session(&conn, &token)?;
+
+    // Close db connection
+    conn.close().map_err(|(_, e)| e)?;
+
+    Ok(())
+}
+
+// Set-Cookie value for a session, a max_age of 0 removes the cookie
+pub fn session_cookie(config: &AuthConfig, token: &str, max_age: u64) -> String {
+    format!(
+        "{}={}; Max-Age={}; Path=/; HttpOnly; SameSite=Lax{}",
+        SESSION_COOKIE,
+        token,
+        max_age,
+        if config.secure_cookie { "; Secure" } else { "" }
+    )
+}
+
+fn session_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, token)| String::from(token))
+}
+
+// "Authorization: Basic ..." for scripts and scrapers
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let encoded = headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+
+    Some((String::from(username), String::from(password)))
+}
+
+// Browsers say where a request came from, a cookie alone doesn't prove another site didn't send it
+pub fn is_cross_site(headers: &HeaderMap) -> bool {
+    headers
+        .get("sec-fetch-site")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|site| site != "same-origin" && site != "none")
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)?
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+// The user from the proxy's headers, if the proxy listener vouched for the request
+fn proxy_login(config: &ProxyAuthConfig, headers: &HeaderMap) -> Option<Login> {
+    if !config.enabled {
+        return None;
+    }
+    let username = header(headers, &config.user_header)?;
+
+    // Compared in constant time so response times don't give the token away
+    let token = header(headers, TOKEN_HEADER).unwrap_or_default();
+    if !bool::from(token.as_bytes().ct_eq(proxy_token().as_bytes())) {
+        warn!(
+            "Ignoring {} header that didn't come through the proxy listener",
+            config.user_header
+        );
+        return None;
+    }
+
+    let groups: Vec<&str> = header(headers, &config.groups_header)
+        .map(|groups| groups.split(',').map(str::trim).collect())
+        .unwrap_or_default();
+
+    let mut login = Login {
+        username: String::from(username),
+        services: None,
+        control: true,
+        proxied: true,
+    };
+    if config.groups.is_empty() {
+        return Some(login);
+    }
+
+    // Members of several groups get everything any of them allows
+    let matching: Vec<_> = config
+        .groups
+        .iter()
+        .filter(|group| groups.contains(&group.name.as_str()))
+        .collect();
+    login.control = matching.iter().any(|group| group.control);
+    if !matching.iter().any(|group| group.services.is_empty()) {
+        login.services = Some(
+            matching
+                .iter()
+                .flat_map(|group| group.services.iter().cloned())
+                .collect(),
+        );
+    }
+
+    Some(login)
+}
+
+// The logged in user from the proxy's headers, the session cookie or Basic credentials
+pub fn current_login(
+    config: &AuthConfig,
+    headers: &HeaderMap,
+    access: Access,
+) -> Result<Option<Login>, Box<dyn Error>> {
+    // Proxy sessions are cookies too, so writes from other sites don't count either
+    let cross_site = access == Access::Write && is_cross_site(headers);
+
+    if let Some(login) = proxy_login(&config.proxy, headers) {
+        return Ok((!cross_site).then_some(login));
+    }
+
+    if let Some(token) = session_token(headers) {
+        if cross_site {
+            return Ok(None);
+        }
+
+        let conn = connect_db()?;
+        let user = get_session_user(&conn, &token, now())?;
+
+        // Close db connection
+        conn.close().map_err(|(_, e)| e)?;
+
+        if let Some(user) = user {
+            return Ok(Some(Login::local(user)));
+        }
+    }
+
+    match basic_credentials(headers) {
+        Some((username, password)) => {
+            Ok(check_credentials(&username, &password)?.map(Login::local))
+        }
+        None => Ok(None),
+    }
+}
+
+// Checks a request against the auth config, the login is None when there is none and none is needed
+pub fn authenticate(headers: &HeaderMap, access: Access) -> Result<Option<Login>, StatusCode> {
+    let config = config_snapshot().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !config.auth.enabled {
+        return Ok(None);
+    }
+
+    let login = current_login(&config.auth, headers, access).map_err(|e| {
+        error!("Failed to check login: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    match login {
+        Some(login) if access == Access::Write && !login.control => Err(StatusCode::FORBIDDEN),
+        Some(login) => Ok(Some(login)),
+        None if access == Access::Read && config.auth.mode == AuthMode::Mutating => Ok(None),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+// Forbidden unless the login may see the service, without a login every service is visible
+pub fn check_visible(login: &Option<Login>, service: &str) -> Result<(), StatusCode> {
+    match login {
+        Some(login) if !login.can_see(service) => Err(StatusCode::FORBIDDEN),
+        _ => Ok(()),
+    }
+}
+
+// Same for a services table id, unknown ids are left to the route
+pub fn check_visible_id(login: &Option<Login>, id: u32) -> Result<(), StatusCode> {
+    if login.as_ref().is_none_or(|login| login.services.is_none()) {
+        return Ok(());
+    }
+
+    let name = connect_db()
+        .and_then(|conn| {
+            let name = get_service_name(&conn, id)?;
+
+            // Close db connection
+            conn.close().map_err(|(_, e)| e)?;
+            Ok(name)
+        })
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match name {
+        Some(name) => check_visible(login, &name),
+        None => Ok(()),
+    }
+}
+
+// For routes about every service at once
+pub fn check_sees_all(login: &Option<Login>) -> Result<(), StatusCode> {
+    match login {
+        Some(login) if login.services.is_some() => Err(StatusCode::FORBIDDEN),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(groups: &str) -> ProxyAuthConfig {
+        toml::from_str(&format!(
+            "enabled = true\ntrusted_proxies = [\"172.18.0.0/16\"]\n{}",
+            groups
+        ))
+        .unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn needs_the_listener_token() {
+        let config = config("");
+
+        let login = proxy_login(
+            &config,
+            &headers(&[("remote-user", "alice"), (TOKEN_HEADER, proxy_token())]),
+        )
+        .unwrap();
+        assert_eq!(login.username, "alice");
+        assert!(login.proxied && login.control && login.services.is_none());
+
+        let wrong = format!("{}0", proxy_token());
+        for token in ["", "s3cret", &proxy_token()[1..], &wrong] {
+            let headers = headers(&[("remote-user", "alice"), (TOKEN_HEADER, token)]);
+            assert!(proxy_login(&config, &headers).is_none(), "{}", token);
+        }
+
+        // Forwarded addresses don't count for anything
+        let forwarded = headers(&[
+            ("remote-user", "alice"),
+            ("x-forwarded-for", "172.18.0.2"),
+            ("x-real-ip", "172.18.0.2"),
+        ]);
+        assert!(proxy_login(&config, &forwarded).is_none());
+    }
+
+    #[test]
+    fn ignores_headers_when_disabled() {
+        let mut config = config("");
+        config.enabled = false;
+
+        let headers = headers(&[("remote-user", "alice"), (TOKEN_HEADER, proxy_token())]);
+        assert!(proxy_login(&config, &headers).is_none());
+    }
+
+    #[test]
+    fn combines_group_permissions() {
+        let config = config(concat!(
+            "[[groups]]\nname = \"family\"\nservices = [\"Plex\"]\n",
+            "[[groups]]\nname = \"media\"\nservices = [\"Overseerr\"]\ncontrol = true\n",
+        ));
+        let login = |groups: &'static str| {
+            proxy_login(
+                &config,
+                &headers(&[
+                    ("remote-user", "bob"),
+                    ("remote-groups", groups),
+                    (TOKEN_HEADER, proxy_token()),
+                ]),
+            )
+            .unwrap()
+        };
+
+        let family = login("family");
+        assert!(family.can_see("Plex") && !family.can_see("Overseerr"));
+        assert!(!family.control);
+
+        let both = login("family, media");
+        assert!(both.can_see("Plex") && both.can_see("Overseerr"));
+        assert!(both.control);
+
+        let guest = login("guests");
+        assert!(!guest.can_see("Plex") && !guest.control);
+    }
+}
@@ -0,0 +1,181 @@
+use std::env;
+use std::io::{self, BufRead};
+use std::process;
+
+use homedash_rs::auth::hash_password;
+use homedash_rs::config::{get_config_path, load_config, store_config};
+use homedash_rs::database::{
+    connect_db, create_schemas, delete_user, get_users, insert_user, set_password,
+};
+use homedash_rs::notifier::send_test;
+use homedash_rs::validation::check_config_file;
+
+fn usage() -> ! {
+    println!("Usage: homedash validate [path/to/config.toml]");
+    println!("       homedash notify <channel> [path/to/config.toml]");
+    println!("       homedash user add|passwd <username>");
+    println!("       homedash user remove <username>");
+    println!("       homedash user list");
+    process::exit(2);
+}
+
+fn validate(path: String) {
+    let report = check_config_file(&path);
+
+    if report.valid {
+        println!("{}: OK", path);
+        return;
+    }
+
+    println!("{}: {} problem(s) found", path, report.issues.len());
+    for issue in report.issues {
+        println!("  {}", issue);
+    }
+
+    process::exit(1);
+}
+
+fn notify(channel: String, path: String) {
+    // Stored so the HTTP client uses this file's [http_client] settings
+    let config = match load_config(&path) {
+        Ok(config) => store_config(config),
+        Err(e) => {
+            println!("{}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start runtime");
+    match runtime.block_on(send_test(&config, &channel)) {
+        Some(Ok(())) => println!("{}: sent", channel),
+        Some(Err(e)) => {
+            println!("{}: {}", channel, e);
+            process::exit(1);
+        }
+        None => {
+            println!("{}: no such channel in {}", channel, path);
+            process::exit(1);
+        }
+    }
+}
+
+fn exit_with(e: impl std::fmt::Display) -> ! {
+    println!("{}", e);
+    process::exit(1);
+}
+
+// Turns terminal echo off until dropped, a no-op when stdin isn't a terminal
+#[cfg(unix)]
+struct HiddenInput(Option<libc::termios>);
+
+#[cfg(unix)]
+impl HiddenInput {
+    fn new() -> Self {
+        let fd = libc::STDIN_FILENO;
+        let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+        if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+            return HiddenInput(None);
+        }
+
+        let mut hidden = termios;
+        hidden.c_lflag &= !libc::ECHO;
+        hidden.c_lflag |= libc::ECHONL;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &hidden) } != 0 {
+            return HiddenInput(None);
+        }
+        HiddenInput(Some(termios))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for HiddenInput {
+    fn drop(&mut self) {
+        if let Some(termios) = &self.0 {
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, termios) };
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct HiddenInput;
+
+#[cfg(not(unix))]
+impl HiddenInput {
+    fn new() -> Self {
+        HiddenInput
+    }
+}
+
+// One line from stdin, so passwords can be piped in or typed without ending up in the shell history
+fn read_password() -> String {
+    println!("Password:");
+
+    let mut password = String::new();
+    let result = {
+        let _hidden = HiddenInput::new();
+        io::stdin().lock().read_line(&mut password)
+    };
+    if let Err(e) = result {
+        exit_with(e);
+    }
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        exit_with("Password can't be empty");
+    }
+
+    String::from(password)
+}
+
+fn user(action: &str, username: Option<String>) {
+    let mut conn = connect_db().unwrap_or_else(|e| exit_with(e));
+    create_schemas(&conn).unwrap_or_else(|e| exit_with(e));
+
+    match (action, username) {
+        ("list", None) => {
+            for user in get_users(&conn).unwrap_or_else(|e| exit_with(e)) {
+                println!("{}", user.username);
+            }
+        }
+        ("add", Some(username)) => {
+            let hash = hash_password(&read_password()).unwrap_or_else(|e| exit_with(e));
+            if !insert_user(&conn, &username, &hash).unwrap_or_else(|e| exit_with(e)) {
+                exit_with(format!("{}: user already exists", username));
+            }
+            println!("{}: added", username);
+        }
+        ("passwd", Some(username)) => {
+            let hash = hash_password(&read_password()).unwrap_or_else(|e| exit_with(e));
+            if !set_password(&mut conn, &username, &hash).unwrap_or_else(|e| exit_with(e)) {
+                exit_with(format!("{}: no such user", username));
+            }
+            println!("{}: password changed, existing sessions ended", username);
+        }
+        ("remove", Some(username)) => {
+            if !delete_user(&conn, &username).unwrap_or_else(|e| exit_with(e)) {
+                exit_with(format!("{}: no such user", username));
+            }
+            println!("{}: removed", username);
+        }
+        _ => usage(),
+    }
+
+    // Close db connection
+    conn.close().unwrap_or_else(|(_, e)| exit_with(e));
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+
+    match args.next().as_deref() {
+        Some("validate") => validate(args.next().unwrap_or_else(get_config_path)),
+        Some("notify") => match args.next() {
+            Some(channel) => notify(channel, args.next().unwrap_or_else(get_config_path)),
+            None => usage(),
+        },
+        Some("user") => match args.next() {
+            Some(action) => user(&action, args.next()),
+            None => usage(),
+        },
+        _ => usage(),
+    }
+}
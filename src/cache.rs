@@ -0,0 +1,354 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{self, SystemTime};
+use tracing::warn;
+use tuono_lib::axum::http::header::{AGE, CACHE_CONTROL};
+use tuono_lib::axum::http::HeaderValue;
+use tuono_lib::axum::response::{IntoResponse, Response};
+use tuono_lib::axum::Json;
+
+use crate::config::{config_snapshot, instance_display_name, ConfigFields};
+use crate::database::{connect_db, get_cached_response, set_cached_response, CachedResponse};
+use crate::events::{publish, subscribers, Event};
+use crate::services::registry::{find_service, ServiceClient};
+use crate::services::ServiceError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheStatus {
+    // Fresh cached response
+    Hit,
+    // Expired but within stale_while_revalidate, a refresh runs in the background
+    Stale,
+    // Fetched from the service and cached
+    Miss,
+    // Not cacheable, the cache is disabled
+    Bypass,
+}
+
+impl CacheStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "HIT",
+            CacheStatus::Stale => "STALE",
+            CacheStatus::Miss => "MISS",
+            CacheStatus::Bypass => "BYPASS",
+        }
+    }
+}
+
+// An API response plus what the cache did with it, sets X-Cache, Age and Cache-Control
+pub struct Cached<T> {
+    pub data: T,
+    pub status: CacheStatus,
+    pub age: u64,
+    pub ttl: u64,
+    pub stale_while_revalidate: u64,
+    // Last failed refresh, the data is from before it
+    pub error: Option<String>,
+}
+
+// Endpoints being refreshed in the background, so concurrent stale hits only trigger one request
+static REFRESHING: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+// Url of an instance, cached responses of another url are from a different server
+fn instance_url(service: &str, instance: &str) -> String {
+    let Ok(config) = config_snapshot() else {
+        return String::new();
+    };
+
+    find_service(service)
+        .and_then(|client| client.find(&config, instance))
+        .map(|found| String::from(found.url()))
+        .unwrap_or_default()
+}
+
+// Name events and logs use, e.g. "Radarr (4k)"
+fn display_name(service: &str, instance: &str) -> String {
+    match find_service(service) {
+        Some(client) => instance_display_name(client.name(), instance),
+        None => instance_display_name(service, instance),
+    }
+}
+
+fn read_entry(service: &str, instance: &str, endpoint: &str) -> Option<CachedResponse> {
+    let conn = connect_db().ok()?;
+    let entry = get_cached_response(&conn, service, instance, endpoint)
+        .ok()
+        .flatten();
+    conn.close().ok()?;
+
+    let url = instance_url(service, instance);
+    entry
+        .filter(|(cached_url, _)| *cached_url == url)
+        .map(|(_, response)| response)
+}
+
+fn write_entry<T: Serialize>(service: &str, instance: &str, endpoint: &str, data: &T) {
+    let result = connect_db().and_then(|conn| {
+        set_cached_response(
+            &conn,
+            service,
+            instance,
+            endpoint,
+            &instance_url(service, instance),
+            &CachedResponse {
+                fetched_at: now(),
+                data: serde_json::to_value(data)?,
+                error: None,
+            },
+        )?;
+        conn.close().map_err(|(_, e)| e.into())
+    });
+
+    if let Err(e) = result {
+        warn!(
+            "Failed to cache {} {}: {}",
+            display_name(service, instance),
+            endpoint,
+            e
+        );
+    }
+}
+
+// Keeps the last good response and remembers why refreshing it failed
+fn write_error(service: &str, instance: &str, endpoint: &str, error: &ServiceError) {
+    let mut response = read_entry(service, instance, endpoint).unwrap_or(CachedResponse {
+        fetched_at: 0,
+        data: Value::Null,
+        error: None,
+    });
+    response.error = Some(error.to_string());
+
+    let result = connect_db().and_then(|conn| {
+        set_cached_response(
+            &conn,
+            service,
+            instance,
+            endpoint,
+            &instance_url(service, instance),
+            &response,
+        )?;
+        conn.close().map_err(|(_, e)| e.into())
+    });
+
+    if let Err(e) = result {
+        warn!(
+            "Failed to cache {} {}: {}",
+            display_name(service, instance),
+            endpoint,
+            e
+        );
+    }
+}
+
+fn start_refresh(key: &str) -> bool {
+    REFRESHING
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashSet::new)
+        .insert(String::from(key))
+}
+
+fn finish_refresh(key: &str) {
+    if let Some(refreshing) = REFRESHING.lock().unwrap().as_mut() {
+        refreshing.remove(key);
+    }
+}
+
+// Tells /api/events clients about data that differs from the cached response
+fn publish_changes<T: Serialize>(service: &str, instance: &str, endpoint: &str, data: &T) {
+    let previous = read_entry(service, instance, endpoint).map(|response| response.data);
+    let Ok(data) = serde_json::to_value(data) else {
+        return;
+    };
+
+    if previous.as_ref() != Some(&data) {
+        publish(Event::Data {
+            service: display_name(service, instance),
+            endpoint: String::from(endpoint),
+            data,
+        });
+    }
+}
+
+// Fetches an endpoint into the cache, skipped if the same endpoint is already being refreshed
+pub async fn refresh<T, Fut>(service: &str, instance: &str, endpoint: &str, fetch: Fut)
+where
+    T: Serialize,
+    Fut: Future<Output = Result<T, ServiceError>>,
+{
+    let key = format!("{}/{}/{}", service, instance, endpoint);
+    if !start_refresh(&key) {
+        return;
+    }
+
+    match fetch.await {
+        Ok(data) => {
+            if subscribers() > 0 {
+                publish_changes(service, instance, endpoint, &data);
+            }
+            write_entry(service, instance, endpoint, &data);
+        }
+        Err(e) => {
+            warn!(
+                "Failed to refresh {} {}: {}",
+                display_name(service, instance),
+                endpoint,
+                e
+            );
+            write_error(service, instance, endpoint, &e);
+        }
+    }
+
+    finish_refresh(&key);
+}
+
+// Serves an endpoint from the response cache, calling fetch on a miss or in the background when stale
+// service is the registry id and instance the instance name, e.g. "radarr" and "4k"
+pub async fn cached<T, F, Fut>(
+    service: &str,
+    instance: &str,
+    endpoint: &str,
+    fetch: F,
+) -> Result<Cached<T>, ServiceError>
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, ServiceError>> + Send + 'static,
+{
+    let (config, polled) = config_snapshot()
+        .map(|config| (config.cache.clone(), config.poller.enabled))
+        .unwrap_or_default();
+
+    if !config.enabled {
+        return Ok(Cached {
+            data: fetch().await?,
+            status: CacheStatus::Bypass,
+            age: 0,
+            ttl: 0,
+            stale_while_revalidate: 0,
+            error: None,
+        });
+    }
+    let ttl = config.ttl;
+
+    // Entries that never had a good response only hold the last error
+    let entry = read_entry(service, instance, endpoint).filter(|response| response.fetched_at > 0);
+    if let Some(response) = entry {
+        let age = (now() - response.fetched_at).max(0) as u64;
+
+        // The poller keeps polled snapshots up to date, otherwise expired entries refresh on request
+        if polled || age < ttl + config.stale_while_revalidate {
+            if let Ok(data) = serde_json::from_value::<T>(response.data) {
+                let status = if age < ttl {
+                    CacheStatus::Hit
+                } else {
+                    CacheStatus::Stale
+                };
+
+                if status == CacheStatus::Stale && !polled {
+                    let service = String::from(service);
+                    let instance = String::from(instance);
+                    let endpoint = String::from(endpoint);
+
+                    tokio::spawn(
+                        async move { refresh(&service, &instance, &endpoint, fetch()).await },
+                    );
+                }
+
+                return Ok(Cached {
+                    data,
+                    status,
+                    age,
+                    ttl,
+                    stale_while_revalidate: config.stale_while_revalidate,
+                    error: response.error,
+                });
+            }
+        }
+    }
+
+    let data = fetch().await?;
+    write_entry(service, instance, endpoint, &data);
+
+    Ok(Cached {
+        data,
+        status: CacheStatus::Miss,
+        age: 0,
+        ttl,
+        stale_while_revalidate: config.stale_while_revalidate,
+        error: None,
+    })
+}
+
+// Endpoint data of an instance through the cache, so polled endpoints don't cause extra requests
+pub async fn cached_endpoint<T: DeserializeOwned>(
+    config: &Arc<ConfigFields>,
+    service: &'static dyn ServiceClient,
+    instance: &str,
+    endpoint: &'static str,
+) -> Result<T, String> {
+    let (config, name) = (Arc::clone(config), String::from(instance));
+
+    let response = cached::<Value, _, _>(service.id(), instance, endpoint, move || {
+        service.fetch(&config, &name, endpoint)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    serde_json::from_value(response.data).map_err(|e| e.to_string())
+}
+
+impl<T: Serialize> IntoResponse for Cached<T> {
+    fn into_response(self) -> Response {
+        let cache_control = match self.status {
+            CacheStatus::Hit | CacheStatus::Miss => format!(
+                "private, max-age={}, stale-while-revalidate={}",
+                self.ttl.saturating_sub(self.age),
+                self.stale_while_revalidate
+            ),
+            CacheStatus::Stale => String::from("private, max-age=0"),
+            CacheStatus::Bypass => String::from("no-store"),
+        };
+
+        let mut response = (
+            [
+                ("X-Cache", String::from(self.status.as_str())),
+                (AGE.as_str(), self.age.to_string()),
+                (CACHE_CONTROL.as_str(), cache_control),
+            ],
+            Json(self.data),
+        )
+            .into_response();
+
+        // Header values only allow visible ASCII
+        let error = self.error.map(|error| {
+            error
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_graphic() || c == ' ' {
+                        c
+                    } else {
+                        '?'
+                    }
+                })
+                .collect::<String>()
+        });
+        if let Some(value) = error.and_then(|error| HeaderValue::from_str(&error).ok()) {
+            response.headers_mut().insert("X-Last-Error", value);
+        }
+
+        response
+    }
+}
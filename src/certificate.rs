@@ -0,0 +1,225 @@
+// What a ping keeps from a peer certificate
+#[derive(Debug, Clone)]
+pub struct CertificateInfo {
+    // Issuer organization and common name, e.g. "O=Let's Encrypt, CN=R11"
+    pub issuer: String,
+    // notAfter as a unix timestamp
+    pub expires: i64,
+}
+
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const OID: u8 = 0x06;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const EXPLICIT_VERSION: u8 = 0xA0;
+
+const OID_COMMON_NAME: [u8; 3] = [0x55, 0x04, 0x03];
+const OID_ORGANIZATION: [u8; 3] = [0x55, 0x04, 0x0A];
+
+// A DER element, content is the value without tag and length
+struct Element<'a> {
+    tag: u8,
+    content: &'a [u8],
+}
+
+// Splits the first element off data
+fn read_element(data: &[u8]) -> Option<(Element<'_>, &[u8])> {
+    let tag = *data.first()?;
+    let first = *data.get(1)? as usize;
+
+    let (length, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let bytes = first & 0x7F;
+        if bytes == 0 || bytes > 4 {
+            return None;
+        }
+        let length = data
+            .get(2..2 + bytes)?
+            .iter()
+            .fold(0usize, |length, byte| (length << 8) | *byte as usize);
+        (length, 2 + bytes)
+    };
+
+    let content = data.get(header..header + length)?;
+    Some((Element { tag, content }, &data[header + length..]))
+}
+
+fn elements(mut data: &[u8]) -> Vec<Element<'_>> {
+    let mut elements = Vec::new();
+    while let Some((element, rest)) = read_element(data) {
+        elements.push(element);
+        data = rest;
+    }
+
+    elements
+}
+
+// Organization and common name of an X.509 Name, in certificate order
+fn name(element: &Element) -> String {
+    let mut parts = Vec::new();
+
+    for set in elements(element.content)
+        .iter()
+        .filter(|set| set.tag == SET)
+    {
+        for attribute in elements(set.content) {
+            let attribute = elements(attribute.content);
+            let (Some(oid), Some(value)) = (attribute.first(), attribute.get(1)) else {
+                continue;
+            };
+            if oid.tag != OID {
+                continue;
+            }
+
+            let label = match oid.content {
+                content if content == OID_COMMON_NAME => "CN",
+                content if content == OID_ORGANIZATION => "O",
+                _ => continue,
+            };
+            parts.push(format!(
+                "{}={}",
+                label,
+                String::from_utf8_lossy(value.content)
+            ));
+        }
+    }
+
+    parts.join(", ")
+}
+
+// Days since 1970-01-01 for a civil date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+// UTCTime "YYMMDDHHMMSSZ" or GeneralizedTime "YYYYMMDDHHMMSSZ" as a unix timestamp
+fn time(element: &Element) -> Option<i64> {
+    let text = std::str::from_utf8(element.content).ok()?;
+    let text = text.strip_suffix('Z')?;
+
+    let (year, rest) = match element.tag {
+        UTC_TIME => {
+            let year: i64 = text.get(0..2)?.parse().ok()?;
+            (
+                if year >= 50 { 1900 + year } else { 2000 + year },
+                &text[2..],
+            )
+        }
+        GENERALIZED_TIME => (text.get(0..4)?.parse().ok()?, &text[4..]),
+        _ => return None,
+    };
+
+    let field = |index: usize| -> Option<i64> { rest.get(index * 2..index * 2 + 2)?.parse().ok() };
+    let (month, day, hour, minute, second) =
+        (field(0)?, field(1)?, field(2)?, field(3)?, field(4)?);
+
+    Some(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+// Reads issuer and notAfter from a DER encoded certificate
+pub fn parse_certificate(der: &[u8]) -> Option<CertificateInfo> {
+    let (certificate, _) = read_element(der)?;
+    let (tbs, _) = read_element(certificate.content)?;
+    if certificate.tag != SEQUENCE || tbs.tag != SEQUENCE {
+        return None;
+    }
+
+    // version (optional), serial number, signature algorithm, issuer, validity
+    let mut fields = elements(tbs.content).into_iter().peekable();
+    if fields.peek()?.tag == EXPLICIT_VERSION {
+        fields.next();
+    }
+    let issuer = fields.nth(2)?;
+    let validity = fields.next()?;
+
+    let validity = elements(validity.content);
+    let expires = time(validity.get(1)?)?;
+
+    Some(CertificateInfo {
+        issuer: name(&issuer),
+        expires,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    // Leaf for nas.lan signed by "/C=DE/O=Homedash Test/OU=Lab/CN=Lab CA+O=Second Org",
+    // notAfter 2029-12-31 23:59:59 as UTCTime
+    const LEAF: &str = "
+        MIIBmjCCAUCgAwIBAgIBAjAKBggqhkjOPQQDAjBXMQswCQYDVQQGEwJERTEWMBQG
+        A1UECgwNSG9tZWRhc2ggVGVzdDEMMAoGA1UECwwDTGFiMSIwDQYDVQQDDAZMYWIg
+        Q0EwEQYDVQQKDApTZWNvbmQgT3JnMB4XDTI0MDEwMTAwMDAwMFoXDTI5MTIzMTIz
+        NTk1OVowEjEQMA4GA1UEAwwHbmFzLmxhbjBZMBMGByqGSM49AgEGCCqGSM49AwEH
+        A0IABHueym0DKB93IKU9pTLeblfNwMj1WUI0VM0rXP05mOUWnFOVUYLAt9VJbkKx
+        z21DHXi2q9O4VP01weCYN56GhM2jQjBAMB0GA1UdDgQWBBTPUhktF3zy2wa2D/S7
+        1WlILvR0lDAfBgNVHSMEGDAWgBQ01KCV4tOKuTgm9FdmDoUdczF4XTAKBggqhkjO
+        PQQDAgNIADBFAiEAl/x2dePqX+SraHTUGGN19/N0Ns0lh1mfP8p/l7Ofy6gCIC/o
+        Ku5vWOS1roIa6SFy2PrQ9ESN/7npMGnNtOVv4sF1";
+
+    // Self-signed "/O=Far Future/CN=Future CA", notAfter 2055-03-01 08:00:00 as GeneralizedTime
+    const FUTURE: &str = "
+        MIIBqjCCAU+gAwIBAgIUbSbKcd984YybBjSQtaZZ0gKqEpEwCgYIKoZIzj0EAwIw
+        KTETMBEGA1UECgwKRmFyIEZ1dHVyZTESMBAGA1UEAwwJRnV0dXJlIENBMCAXDTI0
+        MDEwMTAwMDAwMFoYDzIwNTUwMzAxMDgwMDAwWjApMRMwEQYDVQQKDApGYXIgRnV0
+        dXJlMRIwEAYDVQQDDAlGdXR1cmUgQ0EwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNC
+        AAR7nsptAygfdyClPaUy3m5XzcDI9VlCNFTNK1z9OZjlFpxTlVGCwLfVSW5Csc9t
+        Qx14tqvTuFT9NcHgmDeehoTNo1MwUTAdBgNVHQ4EFgQUz1IZLRd88tsGtg/0u9Vp
+        SC70dJQwHwYDVR0jBBgwFoAUz1IZLRd88tsGtg/0u9VpSC70dJQwDwYDVR0TAQH/
+        BAUwAwEB/zAKBggqhkjOPQQDAgNJADBGAiEA9bL+GhBCAsswy3FZbsAIHvnL85kQ
+        4pKbrZFtG5Ud0UoCIQC3+pCbDlvctqY5ebzB0y+pWz+fEcndZYSTphsBF59wlA==";
+
+    fn der(base64: &str) -> Vec<u8> {
+        let base64: String = base64.split_whitespace().collect();
+        STANDARD.decode(base64).unwrap()
+    }
+
+    #[test]
+    fn reads_utc_time_and_multi_rdn_issuer() {
+        let info = parse_certificate(&der(LEAF)).unwrap();
+        // C and OU are skipped, the multivalued RDN keeps both values
+        assert_eq!(info.issuer, "O=Homedash Test, CN=Lab CA, O=Second Org");
+        assert_eq!(info.expires, 1893455999);
+    }
+
+    #[test]
+    fn reads_generalized_time() {
+        let info = parse_certificate(&der(FUTURE)).unwrap();
+        assert_eq!(info.issuer, "O=Far Future, CN=Future CA");
+        assert_eq!(info.expires, 2687500800);
+    }
+
+    #[test]
+    fn maps_utc_time_years() {
+        let utc = |text: &'static str| {
+            time(&Element {
+                tag: UTC_TIME,
+                content: text.as_bytes(),
+            })
+        };
+        assert_eq!(utc("991231235959Z"), Some(946684799));
+        assert_eq!(utc("491231235959Z"), Some(2524607999));
+        assert_eq!(utc("500101000000Z"), Some(-631152000));
+        assert_eq!(utc("4912312359Z"), None);
+        assert_eq!(utc("491231235959+0100"), None);
+    }
+
+    #[test]
+    fn rejects_truncated_certificates() {
+        let certificate = der(LEAF);
+        for length in [0, 1, 4, 40, 120] {
+            assert!(parse_certificate(&certificate[..length]).is_none());
+        }
+        assert!(parse_certificate(b"not a certificate").is_none());
+    }
+}
@@ -0,0 +1,113 @@
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Client, Proxy};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::{loaded_config, HttpClientConfig};
+use crate::services::ServiceError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientOptions {
+    verify_tls: bool,
+    ca_cert: String,
+    follow_redirects: bool,
+}
+
+struct ClientPool {
+    config: HttpClientConfig,
+    clients: HashMap<ClientOptions, Client>,
+}
+
+// One client per TLS and redirect setup so connections and TLS sessions are reused between requests
+// The pool is dropped whenever [http_client] changes in the config
+static CLIENTS: Mutex<Option<ClientPool>> = Mutex::new(None);
+
+fn build_client(
+    config: &HttpClientConfig,
+    options: &ClientOptions,
+) -> Result<Client, ServiceError> {
+    let redirect = if options.follow_redirects {
+        Policy::default()
+    } else {
+        Policy::none()
+    };
+
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout))
+        .read_timeout(Duration::from_secs(config.read_timeout))
+        .user_agent(&config.user_agent)
+        .danger_accept_invalid_certs(!options.verify_tls)
+        .redirect(redirect)
+        // Lets pings read the peer certificate
+        .tls_info(true);
+
+    if !config.proxy.is_empty() {
+        let proxy = Proxy::all(&config.proxy).map_err(|e| {
+            ServiceError::Internal(format!("invalid proxy \"{}\": {}", config.proxy, e))
+        })?;
+        builder = builder.proxy(proxy);
+    }
+
+    if !options.ca_cert.is_empty() {
+        let pem = std::fs::read(&options.ca_cert).map_err(|e| {
+            ServiceError::Internal(format!("failed to read CA cert {}: {}", options.ca_cert, e))
+        })?;
+        let certificate = Certificate::from_pem(&pem).map_err(|e| {
+            ServiceError::Internal(format!("invalid CA cert {}: {}", options.ca_cert, e))
+        })?;
+        builder = builder.add_root_certificate(certificate);
+    }
+
+    builder
+        .build()
+        .map_err(|e| ServiceError::Internal(format!("failed to build HTTP client: {}", e)))
+}
+
+// Returns the shared client for an instance's TLS settings, cloning a Client is cheap
+pub fn http_client(verify_tls: bool, ca_cert: &str) -> Result<Client, ServiceError> {
+    pooled_client(ClientOptions {
+        verify_tls,
+        ca_cert: String::from(ca_cert),
+        follow_redirects: true,
+    })
+}
+
+// Client for health checks, which don't verify certificates and may stop at redirects
+pub fn ping_client(follow_redirects: bool) -> Result<Client, ServiceError> {
+    pooled_client(ClientOptions {
+        verify_tls: false,
+        ca_cert: String::new(),
+        follow_redirects,
+    })
+}
+
+fn pooled_client(options: ClientOptions) -> Result<Client, ServiceError> {
+    // Defaults until a config is stored, e.g. in tests, instead of creating config.toml
+    let config = loaded_config()
+        .map(|config| config.http_client.clone())
+        .unwrap_or_default();
+
+    let mut pool = CLIENTS.lock().unwrap();
+    if pool.as_ref().is_none_or(|pool| pool.config != config) {
+        *pool = Some(ClientPool {
+            config: config.clone(),
+            clients: HashMap::new(),
+        });
+    }
+
+    let clients = &mut pool.as_mut().unwrap().clients;
+    if let Some(client) = clients.get(&options) {
+        return Ok(client.clone());
+    }
+
+    let client = build_client(&config, &options)?;
+    clients.insert(options, client.clone());
+
+    Ok(client)
+}
+
+// Client for public APIs like TVDB and TMDB
+pub fn default_client() -> Result<Client, ServiceError> {
+    http_client(true, "")
+}
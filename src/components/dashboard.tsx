@@ -3,7 +3,7 @@ import { Input } from "@/components/ui/input";
 import { Button } from "@/components/ui/button";
 import { DragDropContext, Droppable, Draggable, DropResult } from "@hello-pangea/dnd";
 import { useSwipeable } from "react-swipeable";
-import { Lock, Unlock, Plus, LayoutGrid } from "lucide-react";
+import { Lock, Unlock, Plus, LayoutGrid, LogOut } from "lucide-react";
 import { memo, useCallback, useEffect, useMemo, useState } from "react";
 import Spinner from "@/components/loading-spinner";
 import { ExpandableDataSection } from "@/components/data-section";
@@ -11,26 +11,65 @@ import { bytesToSize, getGradient, getIconURL, getTimeOfDay, truncateString } fr
 import { WeatherWidget } from "@/components/weather-widget";
 import { useQuery } from "@tanstack/react-query";
 import { UnduckSearchBar } from "@/components/unduck";
+import type { ServiceInstances } from "tuono/types";
 
 interface App {
     id: number;
     name: string;
     status: number;
+    up: boolean;
+    message: string | null;
+    state: "unknown" | "up" | "degraded" | "down" | "maintenance";
+    state_since: number;
+    cert_issuer: string | null;
+    cert_expires: number | null;
+    cert_expiring: boolean;
+    uptime_24h: number | null;
+    uptime_7d: number | null;
+    uptime_30d: number | null;
+    avg_latency: number | null;
+    latency: number | null;
     last_check: number;
     url: string;
     app_order: number;
     is_favorite: boolean;
 }
 
-const AppIcon = memo(({ app, isDragging, isLocked }: { app: App; isDragging: boolean; isLocked: boolean }) => {
+const stateColor = (app: App) => {
+    switch (app.state) {
+        case "down":
+            return "bg-red-400";
+        case "degraded":
+            return "bg-orange-400";
+        case "maintenance":
+            return "bg-sky-400";
+        case "unknown":
+            return "bg-zinc-400";
+        default:
+            return app.cert_expiring ? "bg-amber-400" : "bg-emerald-400";
+    }
+};
+
+const stateTitle = (app: App) => {
+    const since = new Date(app.state_since * 1000).toLocaleTimeString([], { hour: "2-digit", minute: "2-digit" });
+    if (app.state === "down" || app.state === "degraded" || app.state === "maintenance") {
+        return `${app.state.charAt(0).toUpperCase()}${app.state.slice(1)} since ${since}${app.message ? `: ${app.message}` : ""}`;
+    }
+    if (app.cert_expiring && app.cert_expires) {
+        return `Certificate by ${app.cert_issuer} expires ${new Date(app.cert_expires * 1000).toLocaleDateString()}`;
+    }
+    return app.uptime_24h !== null ? `${app.uptime_24h.toFixed(1)}% uptime (24h)` : undefined;
+};
+
+const AppIcon = memo(({ app, isDragging, isLocked, builtIn }: { app: App; isDragging: boolean; isLocked: boolean; builtIn: string[] }) => {
     const [imgError, setImgError] = useState(false);
-    const iconUrl = getIconURL(app.name);
+    const iconUrl = getIconURL(app.name, builtIn);
 
     return (
-        <div className={`flex flex-col items-center ${isDragging ? "opacity-50" : ""} select-none`} onClick={isLocked ? () => window.open(`${!app.name.match(/Plex/gi) ? app.url : `${app.url}/web`}`, "_blank") : () => false} style={{ cursor: isLocked ? "pointer" : "grab" }}>
+        <div className={`flex flex-col items-center ${isDragging ? "opacity-50" : ""} select-none`} onClick={isLocked && app.url.startsWith("http") ? () => window.open(`${!app.name.match(/Plex/gi) ? app.url : `${app.url}/web`}`, "_blank") : () => false} style={{ cursor: isLocked ? "pointer" : "grab" }}>
             <div className="bg-zinc-900/60 border border-white/10 shadow-lg w-16 h-16 rounded-md flex items-center justify-center text-2xl font-medium mb-2  relative transition-all duration-300 ease-in-out transform hover:scale-105 hover:shadow-lg hover:border-zinc-600">
                 {iconUrl && !imgError ? <img className={"drop-shadow"} src={iconUrl} width={42} height={42} alt={app.name} onError={() => setImgError(true)} /> : <LayoutGrid />}
-                <div className={`animate-pulse absolute bottom-1 right-1 w-2 h-2 rounded-full ${[200, 401].includes(app.status) ? "bg-emerald-400" : "bg-red-400"} ring-2 ring-zinc-800`}></div>
+                <div className={`animate-pulse absolute bottom-1 right-1 w-2 h-2 rounded-full ${stateColor(app)} ring-2 ring-zinc-800`} title={stateTitle(app)}></div>
             </div>
             <span className="text-sm text-center font-medium text-zinc-200 hover:text-white transition-colors duration-300 w-full truncate">{app.name.length > 10 ? truncateString(app.name) : app.name}</span>
         </div>
@@ -38,7 +77,7 @@ const AppIcon = memo(({ app, isDragging, isLocked }: { app: App; isDragging: boo
 });
 AppIcon.displayName = "AppIcon";
 
-export default function Dashboard({ username, weather, background }: { username: string | undefined; weather: { lat: number; long: number } | undefined; background: string | null | undefined }) {
+export default function Dashboard({ username, weather, background, loggedIn, canControl, instances }: { username: string | undefined; weather: { lat: number; long: number } | undefined; background: string | null | undefined; loggedIn: boolean; canControl: boolean; instances: ServiceInstances[] }) {
     const { data, isLoading, error } = useQuery({
         queryKey: ["metrics-data"],
         queryFn: async () => {
@@ -69,6 +108,7 @@ export default function Dashboard({ username, weather, background }: { username:
     const [isInitialized, setIsInitialized] = useState(false);
     const [itemsPerPage, setItemsPerPage] = useState(10);
     const [columns, setColumns] = useState(5);
+    const builtIn = useMemo(() => instances.map((entry) => entry.name), [instances]);
 
     // First, let's modify the filteredApps useMemo to also check acronyms
     const filteredApps = useMemo(() => {
@@ -220,13 +260,18 @@ export default function Dashboard({ username, weather, background }: { username:
                         })),
                     ];
 
-                    await fetch("/api/save", {
+                    const res = await fetch("/api/save", {
                         method: "POST",
                         headers: {
                             "Content-Type": "application/json",
                         },
                         body: JSON.stringify({ apps: updatedApps }),
                     });
+                    // Changing the dashboard needs a login when auth is enabled
+                    if (res.status === 401) {
+                        window.location.href = "/login";
+                        return;
+                    }
                     console.log("Saved app order and favorites");
                 } catch (error) {
                     console.error("Error saving app order and favorites:", error);
@@ -237,6 +282,11 @@ export default function Dashboard({ username, weather, background }: { username:
         saveData();
     }, [isLocked]);
 
+    const logout = async () => {
+        await fetch("/api/auth/logout", { method: "POST" });
+        window.location.href = "/login";
+    };
+
     if (isInitialized) {
         return (
             <DragDropContext onDragEnd={onDragEnd} onDragStart={onDragStart}>
@@ -249,9 +299,16 @@ export default function Dashboard({ username, weather, background }: { username:
                         <UnduckSearchBar />
                         <div className="flex items-center space-x-4">
                             <WeatherWidget lat={weather?.lat ?? 0} long={weather?.long ?? 0} />
-                            <button onClick={() => setIsLocked(!isLocked)} className="text-zinc-400 hover:text-zinc-100 transition-colors duration-200">
-                                {isLocked ? <Lock className="h-6 w-6" /> : <Unlock className="h-6 w-6" />}
-                            </button>
+                            {canControl && (
+                                <button onClick={() => setIsLocked(!isLocked)} className="text-zinc-400 hover:text-zinc-100 transition-colors duration-200">
+                                    {isLocked ? <Lock className="h-6 w-6" /> : <Unlock className="h-6 w-6" />}
+                                </button>
+                            )}
+                            {loggedIn && (
+                                <button onClick={logout} className="text-zinc-400 hover:text-zinc-100 transition-colors duration-200">
+                                    <LogOut className="h-6 w-6" />
+                                </button>
+                            )}
                         </div>
                     </header>
                     <div className="grid grid-cols-1 md:grid-cols-3 gap-6 mb-12">
@@ -329,7 +386,7 @@ export default function Dashboard({ username, weather, background }: { username:
                                                 <Draggable key={app.id} draggableId={app.id.toString()} index={index} isDragDisabled={isLocked}>
                                                     {(provided, snapshot) => (
                                                         <div ref={provided.innerRef} {...provided.draggableProps} {...provided.dragHandleProps} className="flex items-center justify-center">
-                                                            <AppIcon app={app} isDragging={snapshot.isDragging} isLocked={isLocked} />
+                                                            <AppIcon app={app} isDragging={snapshot.isDragging} isLocked={isLocked} builtIn={builtIn} />
                                                         </div>
                                                     )}
                                                 </Draggable>
@@ -362,7 +419,7 @@ export default function Dashboard({ username, weather, background }: { username:
                                                 <Draggable key={app.id} draggableId={app.id.toString()} index={index} isDragDisabled={isLocked}>
                                                     {(provided, snapshot) => (
                                                         <div ref={provided.innerRef} {...provided.draggableProps} {...provided.dragHandleProps}>
-                                                            <AppIcon app={app} isDragging={snapshot.isDragging} isLocked={isLocked} />
+                                                            <AppIcon app={app} isDragging={snapshot.isDragging} isLocked={isLocked} builtIn={builtIn} />
                                                         </div>
                                                     )}
                                                 </Draggable>
@@ -380,7 +437,7 @@ export default function Dashboard({ username, weather, background }: { username:
                             </Droppable>
                         </CardContent>
                     </Card>
-                    <ExpandableDataSection />
+                    <ExpandableDataSection instances={instances} />
                 </div>
             </DragDropContext>
         );
@@ -1,12 +1,12 @@
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
-import { formatNumber } from "@/lib/utils";
+import { formatNumber, instanceTitle } from "@/lib/utils";
 import { useQuery } from "@tanstack/react-query";
 import { AdGuardStats } from "tuono/types";
 
-export function AdGuard() {
+export function AdGuard({ instance }: { instance: string }) {
     const { data, isLoading, error } = useQuery<AdGuardStats>({
-        queryKey: ["adguard-stats"],
-        queryFn: () => fetch("/api/adguard/stats").then((res) => res.json()),
+        queryKey: ["adguard-stats", instance],
+        queryFn: () => fetch(`/api/adguard/${instance}/stats`).then((res) => res.json()),
         refetchInterval: 3 * 60e3,
     });
 
@@ -15,7 +15,7 @@ export function AdGuard() {
     return (
         <Card className="backdrop-filter backdrop-blur-lg bg-zinc-900/60 border border-white/10 shadow-lg h-full min-h-[200px] sm:col-span-1">
             <CardHeader>
-                <CardTitle className="text-lg font-medium text-white">AdGuard Home</CardTitle>
+                <CardTitle className="text-lg font-medium text-white">{instanceTitle("AdGuard Home", instance)}</CardTitle>
             </CardHeader>
             <CardContent>
                 <div className="grid grid-cols-2 gap-2 h-full">
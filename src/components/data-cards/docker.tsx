@@ -1,12 +1,12 @@
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
-import { bytesToSize } from "@/lib/utils";
+import { bytesToSize, instanceTitle } from "@/lib/utils";
 import { useQuery } from "@tanstack/react-query";
 import { DockwatchStatsResponse } from "tuono/types";
 
-export function Docker() {
+export function Docker({ instance }: { instance: string }) {
     const { data, isLoading, error } = useQuery<DockwatchStatsResponse>({
-        queryKey: ["dockwatch-overview"],
-        queryFn: () => fetch("/api/dockwatch/overview").then((res) => res.json()),
+        queryKey: ["dockwatch-overview", instance],
+        queryFn: () => fetch(`/api/dockwatch/${instance}/overview`).then((res) => res.json()),
         refetchInterval: 3 * 60 * 1000,
     });
 
@@ -15,7 +15,7 @@ export function Docker() {
     return (
         <Card className="backdrop-filter backdrop-blur-lg bg-zinc-900/60 border border-white/10 shadow-lg h-full min-h-[200px] sm:col-span-1">
             <CardHeader>
-                <CardTitle className="text-lg font-medium text-white">Docker</CardTitle>
+                <CardTitle className="text-lg font-medium text-white">{instanceTitle("Docker", instance)}</CardTitle>
             </CardHeader>
             <CardContent>
                 <div className="grid grid-cols-2 gap-2 h-full">
@@ -1,19 +1,20 @@
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
+import { instanceTitle } from "@/lib/utils";
 import { useQuery } from "@tanstack/react-query";
 import { GluetunV1VPNPublicIP } from "tuono/types";
 import { Button } from "../ui/button";
 import { RotateCcw } from "lucide-react";
 
-export function Gluetun() {
+export function Gluetun({ instance }: { instance: string }) {
     const { data, isLoading, error } = useQuery<GluetunV1VPNPublicIP>({
-        queryKey: ["gluetun-public-ip"],
-        queryFn: () => fetch("/api/gluetun/vpn_public_ip").then((res) => res.json()),
+        queryKey: ["gluetun-public-ip", instance],
+        queryFn: () => fetch(`/api/gluetun/${instance}/vpn_public_ip`).then((res) => res.json()),
         refetchInterval: 3 * 60e3,
     });
     if (!data) return null;
 
     const reconnectVPN = async () => {
-        const [outcome] = await Promise.all([fetch("/api/gluetun/vpn_reconnect").then((res) => res.json())]);
+        const [outcome] = await Promise.all([fetch(`/api/gluetun/${instance}/vpn_reconnect`).then((res) => res.json())]);
         return {
             outcome,
         };
@@ -22,7 +23,7 @@ export function Gluetun() {
     return (
         <Card className="backdrop-filter backdrop-blur-lg bg-zinc-900/60 border border-white/10 shadow-lg h-full min-h-[200px] sm:col-span-1">
             <CardHeader>
-                <CardTitle className="text-lg font-medium text-white">Gluetun VPN</CardTitle>
+                <CardTitle className="text-lg font-medium text-white">{instanceTitle("Gluetun VPN", instance)}</CardTitle>
             </CardHeader>
             <CardContent>
                 <div className="grid grid-cols-2 gap-2 h-full">
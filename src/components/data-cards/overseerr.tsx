@@ -1,6 +1,6 @@
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
 import { Tooltip, TooltipContent, TooltipTrigger } from "@/components/ui/tooltip";
-import { trimText } from "@/lib/utils";
+import { instanceTitle, trimText } from "@/lib/utils";
 import { useQuery, useQueries, UseQueryResult } from "@tanstack/react-query";
 import { useMemo } from "react";
 import { OverseerrV1Request, OverseerrV1Requests, OverseerrV1RequestsCount, TMDB3Movie, TVDBV4SeriesData } from "tuono/types";
@@ -47,17 +47,17 @@ const RequestItem = ({ request, mediaQuery }: RequestItemProps) => {
     );
 };
 
-export function Overseerr() {
+export function Overseerr({ instance }: { instance: string }) {
     const { data, isLoading, error } = useQuery({
-        queryKey: ["overseerr-data"] as const,
+        queryKey: ["overseerr-data", instance] as const,
         queryFn: async () => {
             try {
                 const [requests, requestsCount] = await Promise.all([
-                    fetch("/api/overseerr/requests").then((res) => {
+                    fetch(`/api/overseerr/${instance}/requests`).then((res) => {
                         if (!res.ok) throw new Error("Failed to fetch requests");
                         return res.json() as Promise<OverseerrV1Requests>;
                     }),
-                    fetch("/api/overseerr/requests_count").then((res) => {
+                    fetch(`/api/overseerr/${instance}/requests_count`).then((res) => {
                         if (!res.ok) throw new Error("Failed to fetch request count");
                         return res.json() as Promise<OverseerrV1RequestsCount>;
                     }),
@@ -113,7 +113,7 @@ export function Overseerr() {
     return (
         <Card className="backdrop-filter backdrop-blur-lg bg-zinc-900/60 border border-white/10 shadow-lg h-full sm:col-span-1">
             <CardHeader>
-                <CardTitle className="text-lg font-medium text-white">Overseerr</CardTitle>
+                <CardTitle className="text-lg font-medium text-white">{instanceTitle("Overseerr", instance)}</CardTitle>
             </CardHeader>
             <CardContent>
                 <div className="grid grid-cols-2 sm:grid-cols-4 gap-2">
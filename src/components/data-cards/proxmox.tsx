@@ -1,12 +1,12 @@
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
 import { useQuery } from "@tanstack/react-query";
-import { bytesToSize } from "@/lib/utils";
+import { bytesToSize, instanceTitle } from "@/lib/utils";
 import { ProxmoxV2Data } from "tuono/types";
 
-export function Proxmox() {
+export function Proxmox({ instance }: { instance: string }) {
     const { data, isLoading, error } = useQuery<ProxmoxV2Data[]>({
-        queryKey: ["proxmox-node"],
-        queryFn: () => fetch("/api/proxmox/node").then((res) => res.json()),
+        queryKey: ["proxmox-node", instance],
+        queryFn: () => fetch(`/api/proxmox/${instance}/node`).then((res) => res.json()),
         refetchInterval: 3 * 60 * 1000,
     });
 
@@ -25,7 +25,7 @@ export function Proxmox() {
     return (
         <Card className="backdrop-filter backdrop-blur-lg bg-zinc-900/60 border border-white/10 shadow-lg h-full min-h-[200px] sm:col-span-1">
             <CardHeader>
-                <CardTitle className="text-lg font-medium text-white">Proxmox</CardTitle>
+                <CardTitle className="text-lg font-medium text-white">{instanceTitle("Proxmox", instance)}</CardTitle>
             </CardHeader>
             <CardContent>
                 <div className="grid grid-cols-2 gap-2 h-full">
@@ -1,17 +1,17 @@
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
-import { bytesToSize } from "@/lib/utils";
+import { bytesToSize, instanceTitle } from "@/lib/utils";
 import { ArrowDown, ArrowUp, Check, Loader, Pause, X } from "lucide-react";
 import { useQuery } from "@tanstack/react-query";
 import { QBitV2Torrent } from "tuono/types";
 
-export function QBittorrent() {
+export function QBittorrent({ instance }: { instance: string }) {
     const {
         data: torrents,
         isLoading,
         error,
     } = useQuery<QBitV2Torrent[]>({
-        queryKey: ["qbittorrent-torrents"],
-        queryFn: () => fetch("/api/qbittorrent/torrents").then((res) => res.json()),
+        queryKey: ["qbittorrent-torrents", instance],
+        queryFn: () => fetch(`/api/qbittorrent/${instance}/torrents`).then((res) => res.json()),
         refetchInterval: 3 * 60 * 1000,
     });
 
@@ -25,7 +25,7 @@ export function QBittorrent() {
         return (
             <Card className="backdrop-filter backdrop-blur-lg bg-zinc-900/60 border border-white/10 shadow-lg h-full sm:col-span-3">
                 <CardHeader>
-                    <CardTitle className="text-lg font-medium text-white">qBittorrent</CardTitle>
+                    <CardTitle className="text-lg font-medium text-white">{instanceTitle("qBittorrent", instance)}</CardTitle>
                 </CardHeader>
                 <CardContent>
                     <div className="text-zinc-400">...</div>
@@ -37,7 +37,7 @@ export function QBittorrent() {
     return (
         <Card className="backdrop-filter backdrop-blur-lg bg-zinc-900/60 border border-white/10 shadow-lg h-full sm:col-span-3">
             <CardHeader>
-                <CardTitle className="text-lg font-medium text-white">qBittorrent</CardTitle>
+                <CardTitle className="text-lg font-medium text-white">{instanceTitle("qBittorrent", instance)}</CardTitle>
             </CardHeader>
             <CardContent>
                 <div className="overflow-x-auto">
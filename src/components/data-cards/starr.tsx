@@ -1,21 +1,16 @@
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
-import { formatNumber } from "@/lib/utils";
+import { formatNumber, instanceTitle } from "@/lib/utils";
 import { useQuery } from "@tanstack/react-query";
 import { PlayCircle, Film, Download, Search } from "lucide-react";
 import type { ProwlarrV1IndexerStats, RadarrV3Movies, RadarrV3WantedMissing, SonarrV3Series, SonarrV3WantedMissing } from "tuono/types";
 
-interface StarrData {
-    sonarr: {
-        series: SonarrV3Series[];
-        wantedMissing: SonarrV3WantedMissing;
-    };
-    radarr: {
-        movies: RadarrV3Movies[];
-        wantedMissing: RadarrV3WantedMissing;
-    };
-    prowlarr: ProwlarrV1IndexerStats;
+interface StarrColumn {
+    title: string;
+    cards: { icon: React.ElementType; label: string; value: string | number }[];
 }
 
+const json = (url: string) => fetch(url).then((res) => res.json());
+
 function DataCard({ icon: Icon, label, value }: { icon: React.ElementType; label: string; value: string | number }) {
     return (
         <div className="backdrop-filter backdrop-blur-lg bg-zinc-900/60 border border-white/10 shadow-lg p-2 2xl:p-3 rounded-md flex items-center justify-between">
@@ -28,32 +23,56 @@ function DataCard({ icon: Icon, label, value }: { icon: React.ElementType; label
     );
 }
 
-export function Starr() {
-    const { data, isLoading, error } = useQuery<StarrData>({
-        queryKey: ["starr-data"],
-        queryFn: async (): Promise<StarrData> => {
-            const [series, sonarrWanted, movies, radarrWanted, prowlarrStats] = await Promise.all([fetch("/api/sonarr/series").then((res) => res.json()), fetch("/api/sonarr/wanted_missing").then((res) => res.json()), fetch("/api/radarr/movies").then((res) => res.json()), fetch("/api/radarr/wanted_missing").then((res) => res.json()), fetch("/api/prowlarr/stats").then((res) => res.json())]);
+export function Starr({ sonarr, radarr, prowlarr }: { sonarr: string[]; radarr: string[]; prowlarr: string[] }) {
+    const { data, isLoading, error } = useQuery<StarrColumn[]>({
+        queryKey: ["starr-data", sonarr, radarr, prowlarr],
+        queryFn: async (): Promise<StarrColumn[]> => {
+            // One column per instance, in the order they're configured
+            const [sonarrColumns, radarrColumns, prowlarrColumns] = await Promise.all([
+                Promise.all(
+                    sonarr.map(async (instance) => {
+                        const [series, wanted]: [SonarrV3Series[], SonarrV3WantedMissing] = await Promise.all([json(`/api/sonarr/${instance}/series`), json(`/api/sonarr/${instance}/wanted_missing`)]);
+                        return {
+                            title: instanceTitle("Sonarr", instance),
+                            cards: [
+                                { icon: PlayCircle, label: "Wanted", value: wanted?.totalRecords || 0 },
+                                { icon: Film, label: "Series", value: series?.length ?? 0 },
+                            ],
+                        };
+                    }),
+                ),
+                Promise.all(
+                    radarr.map(async (instance) => {
+                        const [movies, wanted]: [RadarrV3Movies[], RadarrV3WantedMissing] = await Promise.all([json(`/api/radarr/${instance}/movies`), json(`/api/radarr/${instance}/wanted_missing`)]);
+                        return {
+                            title: instanceTitle("Radarr", instance),
+                            cards: [
+                                { icon: Download, label: "Missing", value: wanted?.totalRecords || 0 },
+                                { icon: Film, label: "Movies", value: movies?.length ?? 0 },
+                            ],
+                        };
+                    }),
+                ),
+                Promise.all(
+                    prowlarr.map(async (instance) => {
+                        const stats: ProwlarrV1IndexerStats = await json(`/api/prowlarr/${instance}/stats`);
+                        return {
+                            title: instanceTitle("Prowlarr", instance),
+                            cards: [
+                                { icon: Download, label: "Grabs", value: stats?.hosts[0].numberOfGrabs ?? 0 },
+                                { icon: Search, label: "Queries", value: formatNumber(stats?.hosts[0].numberOfQueries ?? 0) },
+                            ],
+                        };
+                    }),
+                ),
+            ]);
 
-            return {
-                sonarr: {
-                    series: series || [],
-                    wantedMissing: {
-                        totalRecords: sonarrWanted?.totalRecords || 0,
-                    },
-                },
-                radarr: {
-                    movies: movies || [],
-                    wantedMissing: {
-                        totalRecords: radarrWanted?.totalRecords || 0,
-                    },
-                },
-                prowlarr: prowlarrStats,
-            };
+            return [...sonarrColumns, ...radarrColumns, ...prowlarrColumns];
         },
         refetchInterval: 3 * 60 * 1000,
     });
 
-    if (!data) return null;
+    if (!data || data.length === 0) return null;
 
     return (
         <Card className="backdrop-filter backdrop-blur-lg bg-zinc-900/60 border border-white/10 shadow-lg h-full sm:col-span-1">
@@ -62,21 +81,14 @@ export function Starr() {
             </CardHeader>
             <CardContent>
                 <div className="grid grid-cols-2 2xl:grid-cols-3 gap-4">
-                    <div className="space-y-2">
-                        <h3 className="text-zinc-400 text-xs 2xl:text-sm font-medium mb-2">Sonarr</h3>
-                        <DataCard icon={PlayCircle} label="Wanted" value={isLoading || error ? "..." : (data?.sonarr.wantedMissing.totalRecords ?? 0)} />
-                        <DataCard icon={Film} label="Series" value={isLoading || error ? "..." : (data?.sonarr.series.length ?? 0)} />
-                    </div>
-                    <div className="space-y-2">
-                        <h3 className="text-zinc-400 text-xs 2xl:text-sm font-medium mb-2">Radarr</h3>
-                        <DataCard icon={Download} label="Missing" value={isLoading || error ? "..." : (data?.radarr.wantedMissing.totalRecords ?? 0)} />
-                        <DataCard icon={Film} label="Movies" value={isLoading || error ? "..." : (data?.radarr.movies.length ?? 0)} />
-                    </div>
-                    <div className="space-y-2 col-span-2 2xl:col-span-1">
-                        <h3 className="text-zinc-400 text-xs 2xl:text-sm font-medium mb-2">Prowlarr</h3>
-                        <DataCard icon={Download} label="Grabs" value={isLoading || error ? "..." : (data?.prowlarr.hosts[0].numberOfGrabs ?? 0)} />
-                        <DataCard icon={Search} label="Queries" value={isLoading || error ? "..." : formatNumber(data?.prowlarr.hosts[0].numberOfQueries ?? 0)} />
-                    </div>
+                    {data.map((column) => (
+                        <div key={column.title} className="space-y-2">
+                            <h3 className="text-zinc-400 text-xs 2xl:text-sm font-medium mb-2">{column.title}</h3>
+                            {column.cards.map((card) => (
+                                <DataCard key={card.label} icon={card.icon} label={card.label} value={isLoading || error ? "..." : card.value} />
+                            ))}
+                        </div>
+                    ))}
                 </div>
             </CardContent>
         </Card>
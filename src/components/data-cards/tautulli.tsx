@@ -2,14 +2,14 @@ import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
 import { Info } from "lucide-react";
 import { Tooltip, TooltipContent, TooltipTrigger } from "@/components/ui/tooltip";
 import { useQuery } from "@tanstack/react-query";
-import { trimText } from "@/lib/utils";
+import { instanceTitle, trimText } from "@/lib/utils";
 import type { TautulliV2Session, TautulliV2Sessions } from "tuono/types";
 import { Key } from "react";
 
-export function Tautulli() {
+export function Tautulli({ instance }: { instance: string }) {
     const { data, isLoading, error } = useQuery<TautulliV2Sessions>({
-        queryKey: ["tautulli-data"],
-        queryFn: () => fetch("/api/tautulli/sessions").then((res) => res.json()),
+        queryKey: ["tautulli-data", instance],
+        queryFn: () => fetch(`/api/tautulli/${instance}/sessions`).then((res) => res.json()),
         refetchInterval: 3 * 60e3,
     });
 
@@ -20,7 +20,7 @@ export function Tautulli() {
     return (
         <Card className="backdrop-filter backdrop-blur-lg bg-zinc-900/60 border border-white/10 shadow-lg h-full sm:col-span-1">
             <CardHeader>
-                <CardTitle className="text-lg font-medium text-white">Tautulli</CardTitle>
+                <CardTitle className="text-lg font-medium text-white">{instanceTitle("Tautulli", instance)}</CardTitle>
             </CardHeader>
             <CardContent>
                 <h4 className="text-sm font-medium text-zinc-400 mb-2">{isLoading || error ? "..." : sessions.length > 0 ? "Current Active Streams:" : "No Active Streams"}</h4>
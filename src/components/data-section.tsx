@@ -9,9 +9,11 @@ import { Proxmox } from "./data-cards/proxmox";
 import { AdGuard } from "./data-cards/adguard";
 import { QBittorrent } from "./data-cards/qbittorrent";
 import { Gluetun } from "./data-cards/gluetun";
+import type { ServiceInstances } from "tuono/types";
 
-export function ExpandableDataSection() {
+export function ExpandableDataSection({ instances }: { instances: ServiceInstances[] }) {
     const [isExpanded, setIsExpanded] = useState(false);
+    const names = (service: string) => instances.find((entry) => entry.service === service)?.names ?? [];
 
     return (
         <div className="mt-8">
@@ -21,14 +23,28 @@ export function ExpandableDataSection() {
             </Button>
             {isExpanded && (
                 <div className="grid grid-cols-1 sm:grid-cols-3 gap-4 mt-6">
-                    <Docker />
-                    <AdGuard />
-                    <Proxmox />
-                    <Starr />
-                    <Tautulli />
-                    <Overseerr />
-                    <Gluetun />
-                    <QBittorrent />
+                    {names("dockwatch").map((instance) => (
+                        <Docker key={`dockwatch-${instance}`} instance={instance} />
+                    ))}
+                    {names("adguard").map((instance) => (
+                        <AdGuard key={`adguard-${instance}`} instance={instance} />
+                    ))}
+                    {names("proxmox").map((instance) => (
+                        <Proxmox key={`proxmox-${instance}`} instance={instance} />
+                    ))}
+                    <Starr sonarr={names("sonarr")} radarr={names("radarr")} prowlarr={names("prowlarr")} />
+                    {names("tautulli").map((instance) => (
+                        <Tautulli key={`tautulli-${instance}`} instance={instance} />
+                    ))}
+                    {names("overseerr").map((instance) => (
+                        <Overseerr key={`overseerr-${instance}`} instance={instance} />
+                    ))}
+                    {names("gluetun").map((instance) => (
+                        <Gluetun key={`gluetun-${instance}`} instance={instance} />
+                    ))}
+                    {names("qbittorrent").map((instance) => (
+                        <QBittorrent key={`qbittorrent-${instance}`} instance={instance} />
+                    ))}
                 </div>
             )}
         </div>
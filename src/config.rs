@@ -1,33 +1,57 @@
-use config::{Config, ConfigError, File};
+use config::builder::{ConfigBuilder, DefaultState};
+use config::{Config, ConfigError, File, FileFormat};
 use serde::Serialize;
 use serde_derive::Deserialize;
+use std::collections::HashMap;
 use std::io::Write;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info};
+
+use crate::database::{connect_db, create_schemas, set_cached_credential, CachedCredential};
+use crate::logging::set_secrets;
+use crate::services::registry::SERVICES;
+use crate::validation::validate_config;
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
 pub struct APIOauthConfig {
     pub enabled: bool,
     pub api_key: String,
-    pub token: String,
-    pub expires_in: u64,
 }
 
-#[derive(Debug, Deserialize, Clone, Serialize, Default)]
+#[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
 pub struct APIKeyConfig {
+    #[serde(default)]
+    pub name: String,
     pub enabled: bool,
     pub url: String,
     pub api_key: String,
+    #[serde(default = "default_verify_tls")]
+    pub verify_tls: bool,
+    #[serde(default)]
+    pub ca_cert: String,
+    #[serde(default, skip_serializing_if = "HealthCheck::is_default")]
+    pub check: HealthCheck,
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
 pub struct APICredsConfig {
+    #[serde(default)]
+    pub name: String,
     pub enabled: bool,
     pub url: String,
     pub username: String,
     pub password: String,
-    pub cookie: Option<String>,
+    #[serde(default = "default_verify_tls")]
+    pub verify_tls: bool,
+    #[serde(default)]
+    pub ca_cert: String,
+    #[serde(default, skip_serializing_if = "HealthCheck::is_default")]
+    pub check: HealthCheck,
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
@@ -35,6 +59,32 @@ pub struct APICredsConfig {
 pub struct HttpUrl {
     pub name: String,
     pub url: String,
+    #[serde(default, skip_serializing_if = "HealthCheck::is_default")]
+    pub check: HealthCheck,
+}
+
+// What a ping expects from a service before it counts as up
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+#[serde(default)]
+#[allow(unused)]
+pub struct HealthCheck {
+    pub method: String,
+    // Codes or ranges, e.g. ["200-299", "401"]
+    pub expected_status: Vec<String>,
+    // Text the body has to contain
+    pub body: String,
+    pub body_regex: String,
+    // Dotted path into a JSON body, e.g. "data.0.status"
+    pub json_path: String,
+    // Expected value at json_path, any value if empty
+    pub json_value: String,
+    pub headers: HashMap<String, String>,
+    // Basic auth
+    pub username: String,
+    pub password: String,
+    pub follow_redirects: bool,
+    // Seconds, 0 uses [ping] timeout
+    pub timeout: u64,
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
@@ -44,6 +94,301 @@ pub struct HttpConfig {
     pub urls: Vec<HttpUrl>,
 }
 
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MonitorKind {
+    // Connects to host:port
+    Tcp,
+    // Resolves host through resolver, expecting every address in expected
+    Dns,
+    // Connects to host:port and completes a TLS handshake
+    Tls,
+}
+
+// Reachability checks for services that don't speak HTTP
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct MonitorConfig {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: MonitorKind,
+    pub host: String,
+    #[serde(default)]
+    pub port: u16,
+    // DNS server as "ip" or "ip:port"
+    #[serde(default)]
+    pub resolver: String,
+    // A or AAAA
+    #[serde(default = "default_record")]
+    pub record: String,
+    #[serde(default)]
+    pub expected: Vec<String>,
+    #[serde(default = "default_verify_tls")]
+    pub verify_tls: bool,
+    // Seconds, 0 uses [ping] timeout
+    #[serde(default)]
+    pub timeout: u64,
+}
+
+// Shared by every outgoing request to the configured apps, timeouts are in seconds
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+#[serde(default)]
+#[allow(unused)]
+pub struct HttpClientConfig {
+    pub connect_timeout: u64,
+    pub read_timeout: u64,
+    pub user_agent: String,
+    pub proxy: String,
+}
+
+// API responses are cached in the services table, times are in seconds
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub ttl: u64,
+    pub stale_while_revalidate: u64,
+}
+
+// Background refresh of every enabled instance into the cache, intervals are in seconds
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct PollerConfig {
+    pub enabled: bool,
+    pub interval: u64,
+    pub ping_interval: u64,
+    // Per service overrides of interval, e.g. plex = 21600
+    pub intervals: HashMap<String, u64>,
+}
+
+// Status checks behind /api/ping, the timeout is in seconds per check
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct PingConfig {
+    pub concurrency: usize,
+    pub timeout: u64,
+    // Certificates expiring within this many days are flagged
+    pub cert_warning_days: u64,
+    // Every check is kept for history_raw_hours, then merged into hourly rows kept for history_days
+    pub history_raw_hours: u64,
+    pub history_days: u64,
+    // Failed checks are retried this many times, retry_delay seconds apart
+    pub retries: u32,
+    pub retry_delay: u64,
+    // Consecutive failures before a service is down, and passes before it's up again
+    pub failure_threshold: u32,
+    pub recovery_threshold: u32,
+}
+
+// The /api/events stream, intervals are in seconds and only apply while a client is connected
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct EventsConfig {
+    pub enabled: bool,
+    // Tautulli sessions and qBittorrent torrents
+    pub live_interval: u64,
+    pub metrics_interval: u64,
+}
+
+// The /api/prometheus exporter
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct PrometheusConfig {
+    pub enabled: bool,
+    // Sampling takes half a second of CPU measurement per scrape
+    pub system_metrics: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    // Human readable lines
+    Pretty,
+    // One JSON object per line, for log collectors
+    Json,
+}
+
+// Log output, read once at startup
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct LoggingConfig {
+    // A level like "info" or filter directives like "info,homedash_rs=debug", RUST_LOG takes precedence
+    pub level: String,
+    pub format: LogFormat,
+    // Logs every API request with its duration when it finishes
+    pub requests: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    // Every page and API route needs a login
+    All,
+    // Only routes that change something, e.g. /api/save or a VPN reconnect
+    Mutating,
+}
+
+// Login with local users, managed with "homedash user"
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    pub mode: AuthMode,
+    // How long a login lasts
+    pub session_days: u64,
+    // Only send the session cookie over HTTPS
+    pub secure_cookie: bool,
+    pub proxy: ProxyAuthConfig,
+}
+
+// Logins from a forward auth proxy like Authelia or Authentik
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct ProxyAuthConfig {
+    pub enabled: bool,
+    pub user_header: String,
+    // Comma separated group names
+    pub groups_header: String,
+    // The reverse proxy connects to listen, requests are passed on to tuono at upstream
+    // tuono doesn't tell routes the connection's address, so only this listener can check it
+    pub listen: String,
+    pub upstream: String,
+    // Addresses or CIDRs like "172.18.0.0/16", the headers are ignored from anywhere else
+    pub trusted_proxies: Vec<String>,
+    // What members of each group may do, without groups every proxy user may do everything
+    pub groups: Vec<GroupConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct GroupConfig {
+    pub name: String,
+    // Services table names the group sees, e.g. "Radarr (4k)", empty for all
+    #[serde(default)]
+    pub services: Vec<String>,
+    // Saving the layout, pausing services, reconnecting the VPN and the like
+    #[serde(default)]
+    pub control: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelKind {
+    // POSTs the alert as JSON to url
+    Webhook,
+    // Publishes to the topic url, token is an optional access token
+    Ntfy,
+    // Sends to the server url with token as the application token
+    Gotify,
+    // Discord and Slack compatible incoming webhooks
+    Discord,
+    Slack,
+    // Mails every address in to
+    Smtp,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    // Plain connection upgraded with STARTTLS, usually port 587
+    Starttls,
+    // TLS from the start, usually port 465
+    Tls,
+    // No encryption, for local relays
+    None,
+}
+
+// Where alerts are sent, the fields used depend on the type
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct ChannelConfig {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: ChannelKind,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub token: String,
+    // Extra headers of webhook requests
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    #[serde(default = "default_smtp_security")]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub from: String,
+    #[serde(default)]
+    pub to: Vec<String>,
+    #[serde(default = "default_verify_tls")]
+    pub verify_tls: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+    // A pinged service is in the down state
+    ServiceDown,
+    // A Gluetun instance reports a VPN status other than running
+    VpnDown,
+    // A Dockwatch instance counts more unhealthy containers than threshold
+    ContainerUnhealthy,
+    // A Proxmox storage is fuller than threshold percent
+    StorageUsage,
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct AlertRule {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: RuleKind,
+    // Service or instance names the rule watches, empty watches all
+    #[serde(default)]
+    pub services: Vec<String>,
+    // Seconds the condition has to hold before the alert is sent
+    #[serde(default, rename = "for")]
+    pub duration: u64,
+    // Percent for storage_usage, containers for container_unhealthy
+    #[serde(default)]
+    pub threshold: Option<f64>,
+    // Channel names, empty sends to all
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+// Rules are evaluated every interval seconds by the poller
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(default)]
+#[allow(unused)]
+pub struct AlertsConfig {
+    pub enabled: bool,
+    pub interval: u64,
+    pub channels: Vec<ChannelConfig>,
+    pub rules: Vec<AlertRule>,
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize, Default)]
+#[allow(unused)]
+pub struct SettingsConfig {
+    pub enabled: bool,
+    pub token: String,
+}
+
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
 pub struct ConfigFields {
@@ -55,39 +400,368 @@ pub struct ConfigFields {
     pub background_url: Option<String>,
     pub tvdb: APIOauthConfig,
     pub tmdb: APIOauthConfig,
-    pub sonarr: APIKeyConfig,
-    pub radarr: APIKeyConfig,
-    pub prowlarr: APIKeyConfig,
-    pub overseerr: APIKeyConfig,
-    pub qbittorrent: APICredsConfig,
-    pub plex: APIKeyConfig,
-    pub tautulli: APIKeyConfig,
-    pub proxmox: APICredsConfig,
-    pub adguard: APICredsConfig,
-    pub dockwatch: APIKeyConfig,
-    #[serde(default)]
-    pub gluetun: APIKeyConfig,
+    pub sonarr: Vec<APIKeyConfig>,
+    pub radarr: Vec<APIKeyConfig>,
+    pub prowlarr: Vec<APIKeyConfig>,
+    pub overseerr: Vec<APIKeyConfig>,
+    pub qbittorrent: Vec<APICredsConfig>,
+    pub plex: Vec<APIKeyConfig>,
+    pub tautulli: Vec<APIKeyConfig>,
+    pub proxmox: Vec<APICredsConfig>,
+    pub adguard: Vec<APICredsConfig>,
+    pub dockwatch: Vec<APIKeyConfig>,
+    #[serde(default)]
+    pub gluetun: Vec<APIKeyConfig>,
     pub http: HttpConfig,
+    #[serde(default)]
+    pub settings: SettingsConfig,
+    #[serde(default)]
+    pub http_client: HttpClientConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub poller: PollerConfig,
+    #[serde(default)]
+    pub events: EventsConfig,
+    #[serde(default)]
+    pub ping: PingConfig,
+    #[serde(default)]
+    pub monitors: Vec<MonitorConfig>,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    #[serde(default)]
+    pub prometheus: PrometheusConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
 }
 
-#[derive(Debug, Deserialize, Clone, Serialize)]
-#[allow(unused)]
-pub struct OauthValues {
-    pub service: String,
-    pub token: String,
-    pub expires_in: u64,
+fn default_verify_tls() -> bool {
+    true
 }
 
-#[derive(Debug, Deserialize, Clone, Serialize)]
-#[allow(unused)]
-pub struct CookieValues {
-    pub service: String,
-    pub cookie: String,
+fn default_record() -> String {
+    String::from("A")
 }
 
-const LATEST_CONFIG_VERSION: u8 = 3; // Update on config structure changes
+fn default_smtp_port() -> u16 {
+    587
+}
 
-fn get_config_path() -> String {
+fn default_smtp_security() -> SmtpSecurity {
+    SmtpSecurity::Starttls
+}
+
+impl Default for APIKeyConfig {
+    fn default() -> Self {
+        APIKeyConfig {
+            name: String::from(DEFAULT_INSTANCE),
+            enabled: false,
+            url: String::new(),
+            api_key: String::new(),
+            verify_tls: true,
+            ca_cert: String::new(),
+            check: HealthCheck::default(),
+        }
+    }
+}
+
+impl Default for APICredsConfig {
+    fn default() -> Self {
+        APICredsConfig {
+            name: String::from(DEFAULT_INSTANCE),
+            enabled: false,
+            url: String::new(),
+            username: String::new(),
+            password: String::new(),
+            verify_tls: true,
+            ca_cert: String::new(),
+            check: HealthCheck::default(),
+        }
+    }
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        HealthCheck {
+            method: String::from("GET"),
+            expected_status: vec![String::from("200-299"), String::from("401")],
+            body: String::new(),
+            body_regex: String::new(),
+            json_path: String::new(),
+            json_value: String::new(),
+            headers: HashMap::new(),
+            username: String::new(),
+            password: String::new(),
+            follow_redirects: true,
+            timeout: 0,
+        }
+    }
+}
+
+impl HealthCheck {
+    pub fn is_default(&self) -> bool {
+        *self == HealthCheck::default()
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            enabled: true,
+            ttl: 180,
+            stale_while_revalidate: 600,
+        }
+    }
+}
+
+impl Default for PollerConfig {
+    fn default() -> Self {
+        PollerConfig {
+            enabled: true,
+            interval: 120,
+            ping_interval: 300,
+            // Libraries change rarely and counting them is slow on big servers
+            intervals: HashMap::from([(String::from("plex"), 6 * 60 * 60)]),
+        }
+    }
+}
+
+impl MonitorConfig {
+    // Shown as the url in the services table, e.g. "tcp://nas.lan:22" or "dns://192.168.1.2/nas.lan"
+    pub fn url(&self) -> String {
+        match self.kind {
+            MonitorKind::Tcp => format!("tcp://{}:{}", self.host, self.port),
+            MonitorKind::Dns => format!("dns://{}/{}", self.resolver, self.host),
+            MonitorKind::Tls => format!("tls://{}:{}", self.host, self.port),
+        }
+    }
+}
+
+impl PollerConfig {
+    pub fn interval(&self, service: &str) -> u64 {
+        self.intervals
+            .get(service)
+            .copied()
+            .unwrap_or(self.interval)
+    }
+}
+
+impl Default for PingConfig {
+    fn default() -> Self {
+        PingConfig {
+            concurrency: 16,
+            timeout: 5,
+            cert_warning_days: 14,
+            history_raw_hours: 48,
+            history_days: 90,
+            retries: 1,
+            retry_delay: 2,
+            failure_threshold: 3,
+            recovery_threshold: 2,
+        }
+    }
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        EventsConfig {
+            enabled: true,
+            live_interval: 10,
+            metrics_interval: 5,
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: String::from("info"),
+            format: LogFormat::Pretty,
+            requests: true,
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            enabled: false,
+            mode: AuthMode::Mutating,
+            session_days: 30,
+            secure_cookie: false,
+            proxy: ProxyAuthConfig::default(),
+        }
+    }
+}
+
+impl Default for ProxyAuthConfig {
+    fn default() -> Self {
+        ProxyAuthConfig {
+            enabled: false,
+            user_header: String::from("Remote-User"),
+            groups_header: String::from("Remote-Groups"),
+            listen: String::from("0.0.0.0:3001"),
+            upstream: String::from("127.0.0.1:3000"),
+            trusted_proxies: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+}
+
+impl Default for PrometheusConfig {
+    fn default() -> Self {
+        PrometheusConfig {
+            enabled: true,
+            system_metrics: true,
+        }
+    }
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        AlertsConfig {
+            enabled: false,
+            interval: 60,
+            channels: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl ChannelKind {
+    // Whoever knows the url can post to it, Gotify and SMTP keep their secrets in other fields
+    pub fn url_is_secret(self) -> bool {
+        matches!(
+            self,
+            ChannelKind::Webhook | ChannelKind::Ntfy | ChannelKind::Discord | ChannelKind::Slack
+        )
+    }
+}
+
+impl AlertRule {
+    // Storage alerts default to 90%, container alerts to any unhealthy container
+    pub fn threshold(&self) -> f64 {
+        self.threshold.unwrap_or(match self.kind {
+            RuleKind::StorageUsage => 90.0,
+            _ => 0.0,
+        })
+    }
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        HttpClientConfig {
+            connect_timeout: 5,
+            read_timeout: 15,
+            user_agent: format!("homedash-rs/{}", env!("CARGO_PKG_VERSION")),
+            proxy: String::new(),
+        }
+    }
+}
+
+pub trait ServiceInstance {
+    fn name(&self) -> &str;
+    fn enabled(&self) -> bool;
+    fn url(&self) -> &str;
+    fn check(&self) -> &HealthCheck;
+}
+
+impl ServiceInstance for APIKeyConfig {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn check(&self) -> &HealthCheck {
+        &self.check
+    }
+}
+
+impl ServiceInstance for APICredsConfig {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn check(&self) -> &HealthCheck {
+        &self.check
+    }
+}
+
+const LATEST_CONFIG_VERSION: u8 = 6; // Update on config structure changes
+
+pub const DEFAULT_INSTANCE: &str = "default";
+
+// Environment variables like HOMEDASH__SONARR__API_KEY override config.toml values
+const ENV_PREFIX: &str = "HOMEDASH__";
+const ENV_SEPARATOR: &str = "__";
+const ENV_FILE_SUFFIX: &str = "_FILE";
+
+// How often config.toml is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+// In-memory config shared by all routes, swapped when config.toml changes
+static CONFIG: RwLock<Option<Arc<ConfigFields>>> = RwLock::new(None);
+
+// Held for every read-modify-write of config.toml so concurrent updates can't clobber each other
+static CONFIG_WRITE: Mutex<()> = Mutex::new(());
+
+// Config sections that hold a list of named service instances (since version 4)
+const INSTANCE_SECTIONS: [&str; 11] = [
+    "sonarr",
+    "radarr",
+    "prowlarr",
+    "overseerr",
+    "qbittorrent",
+    "plex",
+    "tautulli",
+    "proxmox",
+    "adguard",
+    "dockwatch",
+    "gluetun",
+];
+
+// Finds an enabled instance by name, "default" falls back to the first enabled instance
+pub fn find_instance<'a, T: ServiceInstance>(instances: &'a [T], selector: &str) -> Option<&'a T> {
+    let mut enabled = instances.iter().filter(|instance| instance.enabled());
+
+    if let Some(instance) = enabled.clone().find(|instance| instance.name() == selector) {
+        return Some(instance);
+    }
+
+    if selector == DEFAULT_INSTANCE {
+        return enabled.next();
+    }
+
+    None
+}
+
+// Name shown in the app drawer and stored in the services table, e.g. "Radarr (4k)"
+pub fn instance_display_name(service: &str, instance: &str) -> String {
+    if instance.is_empty() || instance == DEFAULT_INSTANCE {
+        return String::from(service);
+    }
+
+    format!("{} ({})", service, instance)
+}
+
+pub fn get_config_path() -> String {
     let path = if std::path::Path::new("data").exists() {
         "data/config.toml"
     } else {
@@ -97,8 +771,8 @@ fn get_config_path() -> String {
     return String::from(path);
 }
 
-fn create_default_config() -> ConfigFields {
-    ConfigFields {
+pub(crate) fn create_default_config() -> ConfigFields {
+    let mut config = ConfigFields {
         version: LATEST_CONFIG_VERSION,
         base_url: String::from("http://localhost:3000"),
         username: String::from("user"),
@@ -107,167 +781,640 @@ fn create_default_config() -> ConfigFields {
         tvdb: APIOauthConfig {
             enabled: false,
             api_key: String::new(),
-            token: String::new(),
-            expires_in: 0,
         },
         tmdb: APIOauthConfig {
             enabled: false,
             api_key: String::new(),
-            token: String::new(),
-            expires_in: 0,
-        },
-        sonarr: APIKeyConfig {
-            enabled: false,
-            url: String::new(),
-            api_key: String::new(),
-        },
-        radarr: APIKeyConfig {
-            enabled: false,
-            url: String::new(),
-            api_key: String::new(),
-        },
-        prowlarr: APIKeyConfig {
-            enabled: false,
-            url: String::new(),
-            api_key: String::new(),
-        },
-        overseerr: APIKeyConfig {
-            enabled: false,
-            url: String::new(),
-            api_key: String::new(),
-        },
-        qbittorrent: APICredsConfig {
-            enabled: false,
-            url: String::new(),
-            username: String::new(),
-            password: String::new(),
-            cookie: None,
-        },
-        plex: APIKeyConfig {
-            enabled: false,
-            url: String::new(),
-            api_key: String::new(),
-        },
-        tautulli: APIKeyConfig {
-            enabled: false,
-            url: String::new(),
-            api_key: String::new(),
-        },
-        proxmox: APICredsConfig {
-            enabled: false,
-            url: String::new(),
-            username: String::new(),
-            password: String::new(),
-            cookie: None,
-        },
-        adguard: APICredsConfig {
-            enabled: false,
-            url: String::new(),
-            username: String::new(),
-            password: String::new(),
-            cookie: None,
-        },
-        dockwatch: APIKeyConfig {
-            enabled: false,
-            url: String::new(),
-            api_key: String::new(),
-        },
-        gluetun: APIKeyConfig {
-            enabled: false,
-            url: String::new(),
-            api_key: String::new(),
         },
+        sonarr: Vec::new(),
+        radarr: Vec::new(),
+        prowlarr: Vec::new(),
+        overseerr: Vec::new(),
+        qbittorrent: Vec::new(),
+        plex: Vec::new(),
+        tautulli: Vec::new(),
+        proxmox: Vec::new(),
+        adguard: Vec::new(),
+        dockwatch: Vec::new(),
+        gluetun: Vec::new(),
         http: HttpConfig {
             enabled: false,
             urls: Vec::new(),
         },
+        settings: SettingsConfig {
+            enabled: false,
+            token: String::new(),
+        },
+        http_client: HttpClientConfig::default(),
+        cache: CacheConfig::default(),
+        poller: PollerConfig::default(),
+        events: EventsConfig::default(),
+        ping: PingConfig::default(),
+        monitors: Vec::new(),
+        alerts: AlertsConfig::default(),
+        prometheus: PrometheusConfig::default(),
+        logging: LoggingConfig::default(),
+        auth: AuthConfig::default(),
+    };
+
+    // One disabled "default" instance per service
+    for service in SERVICES {
+        service.add_default_instance(&mut config);
     }
+
+    config
 }
 
-pub fn migrate_config(mut config: ConfigFields) -> ConfigFields {
+// Keeps the tokens and cookies older versions stored in config.toml, they'd only have to be fetched again
+fn move_credentials(credentials: Vec<CachedCredential>) {
+    let credentials: Vec<_> = credentials
+        .into_iter()
+        .filter(|credential| !credential.value.is_empty())
+        .collect();
+    if credentials.is_empty() {
+        return;
+    }
+
+    let moved = connect_db().and_then(|conn| {
+        create_schemas(&conn)?;
+        for credential in &credentials {
+            set_cached_credential(&conn, credential)?;
+        }
+
+        // Close db connection
+        conn.close().map_err(|(_, e)| e)?;
+        Ok(())
+    });
+
+    match moved {
+        Ok(()) => info!(
+            "Moved {} credentials from config.toml to the database",
+            credentials.len()
+        ),
+        Err(e) => error!(
+            "Failed to move credentials from config.toml, they'll be fetched again: {}",
+            e
+        ),
+    }
+}
+
+// Upgrades the raw TOML to the latest layout in memory, None if nothing changed
+// Otherwise the credentials taken out of it, for the caller to store once the file is written
+pub fn migrate_config(config: &mut toml::Table) -> Option<Vec<CachedCredential>> {
     let mut migrated = false;
+    let mut credentials = Vec::new();
+    let mut version = config
+        .get("version")
+        .and_then(|version| version.as_integer())
+        .unwrap_or(1) as u8;
 
-    while config.version < LATEST_CONFIG_VERSION {
-        match config.version {
+    while version < LATEST_CONFIG_VERSION {
+        match version {
             1 => {
-                if config.background_url.is_none() {
-                    config.background_url = Some(String::from(""));
-                }
-                config.version = 2;
-                migrated = true;
+                config
+                    .entry("background_url")
+                    .or_insert(toml::Value::String(String::new()));
             }
             2 => {
-                config.gluetun = APIKeyConfig {
-                    enabled: false,
-                    url: String::from(""),
-                    api_key: String::from(""),
-                };
-
-                config.version = 3;
-                migrated = true;
+                let mut gluetun = toml::Table::new();
+                gluetun.insert(String::from("enabled"), toml::Value::Boolean(false));
+                gluetun.insert(String::from("url"), toml::Value::String(String::new()));
+                gluetun.insert(String::from("api_key"), toml::Value::String(String::new()));
+
+                config.insert(String::from("gluetun"), toml::Value::Table(gluetun));
+            }
+            3 => {
+                // Single service tables become a list with one "default" instance
+                for section in INSTANCE_SECTIONS {
+                    if let Some(toml::Value::Table(mut instance)) = config.remove(section) {
+                        instance
+                            .entry("name")
+                            .or_insert(toml::Value::String(String::from(DEFAULT_INSTANCE)));
+
+                        config.insert(
+                            String::from(section),
+                            toml::Value::Array(vec![toml::Value::Table(instance)]),
+                        );
+                    }
+                }
             }
-            _ => {
-                config.version += 1;
-                migrated = true;
+            4 => {
+                // Runtime tokens and cookies live in the credentials_cache table now
+                for section in ["tvdb", "tmdb"] {
+                    if let Some(toml::Value::Table(service)) = config.get_mut(section) {
+                        let token = service.remove("token");
+                        let expires = service.remove("expires_in");
+
+                        if let Some(toml::Value::String(token)) =
+                            token.filter(|_| section == "tvdb")
+                        {
+                            credentials.push(CachedCredential {
+                                service: String::from(section),
+                                instance: String::new(),
+                                value: token,
+                                expires: expires.and_then(|e| e.as_integer()).unwrap_or(0),
+                            });
+                        }
+                    }
+                }
+
+                for section in INSTANCE_SECTIONS {
+                    if let Some(toml::Value::Array(instances)) = config.get_mut(section) {
+                        for instance in instances.iter_mut().filter_map(|i| i.as_table_mut()) {
+                            if let Some(toml::Value::String(cookie)) = instance.remove("cookie") {
+                                credentials.push(CachedCredential {
+                                    service: String::from(section),
+                                    instance: instance
+                                        .get("name")
+                                        .and_then(|name| name.as_str())
+                                        .unwrap_or(DEFAULT_INSTANCE)
+                                        .to_string(),
+                                    value: cookie,
+                                    expires: 0,
+                                });
+                            }
+                        }
+                    }
+                }
             }
+            _ => (),
         }
+
+        version += 1;
+        migrated = true;
+    }
+
+    if !migrated {
+        return None;
+    }
+
+    config.insert(
+        String::from("version"),
+        toml::Value::Integer(version as i64),
+    );
+
+    Some(credentials)
+}
+
+pub fn read_config_table() -> Result<toml::Table, ConfigError> {
+    read_config_table_from(&get_config_path())
+}
+
+fn read_config_table_from(path: &str) -> Result<toml::Table, ConfigError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| ConfigError::Foreign(Box::new(e)))?;
+
+    toml::from_str(&contents).map_err(|e| ConfigError::Foreign(Box::new(e)))
+}
+
+// Writes to a temp file and renames it over config.toml, keeping the previous file as config.toml.bak
+fn write_config_file<T: Serialize>(config: &T) -> Result<(), ConfigError> {
+    let path = get_config_path();
+    let tmp_path = format!("{}.tmp", path);
+    let bak_path = format!("{}.bak", path);
+
+    let toml_string = toml::to_string(config).map_err(|e| ConfigError::Foreign(Box::new(e)))?;
+
+    let mut file =
+        std::fs::File::create(&tmp_path).map_err(|e| ConfigError::Foreign(Box::new(e)))?;
+    file.write_all(toml_string.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| ConfigError::Foreign(Box::new(e)))?;
+
+    if let Ok(metadata) = std::fs::metadata(&path) {
+        let _ = std::fs::set_permissions(&tmp_path, metadata.permissions());
+        std::fs::copy(&path, &bak_path).map_err(|e| ConfigError::Foreign(Box::new(e)))?;
+    }
+
+    std::fs::rename(&tmp_path, &path).map_err(|e| ConfigError::Foreign(Box::new(e)))
+}
+
+// The only way config.toml gets changed: read, update and write happen under one lock
+// The update returns whether anything changed, unchanged files are not rewritten
+fn update_config_file<F>(update: F) -> Result<toml::Table, ConfigError>
+where
+    F: FnOnce(&mut toml::Table) -> bool,
+{
+    let _guard = CONFIG_WRITE.lock().unwrap();
+
+    if !std::path::Path::new(&get_config_path()).exists() {
+        write_config_file(&create_default_config())?;
     }
 
-    if migrated {
-        if let Ok(toml_string) = toml::to_string(&config) {
-            if let Ok(mut file) = std::fs::File::create(get_config_path()) {
-                let _ = file.write_all(toml_string.as_bytes());
+    let mut config = read_config_table()?;
+    if update(&mut config) {
+        write_config_file(&config)?;
+    }
+
+    Ok(config)
+}
+
+// Reads a secret mounted as a file (Docker/Kubernetes secrets), without the trailing newline
+fn read_secret_file(path: &str) -> Result<String, ConfigError> {
+    let secret = std::fs::read_to_string(path)
+        .map_err(|e| ConfigError::Message(format!("Failed to read secret file {}: {}", path, e)))?;
+
+    Ok(String::from(secret.trim_end_matches(['\n', '\r'])))
+}
+
+// Turns "RADARR__4K__API_KEY" into the config path "radarr[1].api_key"
+// List entries are selected by index or instance name, without one the "default" (or first) instance is used
+// Defaults give the field names of list entries, an unknown instance in a selector's place is an error
+fn env_key_to_path(
+    key: &str,
+    config: &toml::Table,
+    defaults: &toml::Table,
+) -> Result<String, ConfigError> {
+    let mut path = String::new();
+    let mut current: Option<&toml::Value> = None;
+    let mut current_default: Option<&toml::Value> = None;
+    let mut segments = key
+        .split(ENV_SEPARATOR)
+        .map(|segment| segment.to_lowercase())
+        .peekable();
+
+    while let Some(segment) = segments.next() {
+        if !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(&segment);
+
+        current = match current {
+            None => config.get(&segment),
+            Some(toml::Value::Table(table)) => table.get(&segment),
+            _ => None,
+        };
+        current_default = match current_default {
+            None if path == segment => defaults.get(&segment),
+            Some(toml::Value::Table(table)) => table.get(&segment),
+            _ => None,
+        };
+
+        if let Some(toml::Value::Array(items)) = current {
+            let find_by_name = |name: &str| {
+                items.iter().position(|item| {
+                    item.get("name")
+                        .and_then(|item_name| item_name.as_str())
+                        .is_some_and(|item_name| item_name.eq_ignore_ascii_case(name))
+                })
+            };
+            let default_items = match current_default {
+                Some(toml::Value::Array(default_items)) => default_items.as_slice(),
+                _ => &[],
+            };
+            let is_field = |name: &str| {
+                items
+                    .iter()
+                    .chain(default_items)
+                    .any(|item| item.get(name).is_some())
+            };
+
+            let selector = segments.peek().cloned();
+            let after_selector = segments.clone().nth(1);
+            let index = match selector {
+                Some(selector) => {
+                    match selector.parse::<usize>().ok().or(find_by_name(&selector)) {
+                        Some(index) => {
+                            segments.next();
+                            index
+                        }
+                        // Fields left out when they're default, like "check", are only known by what follows
+                        None if is_field(&selector)
+                            || after_selector.is_some_and(|next| !is_field(&next)) =>
+                        {
+                            find_by_name(DEFAULT_INSTANCE).unwrap_or(0)
+                        }
+                        None => {
+                            error!(
+                                "{}{} selects unknown instance \"{}\" of {}",
+                                ENV_PREFIX, key, selector, path
+                            );
+                            return Err(ConfigError::Message(format!(
+                                "{}{}: \"{}\" is neither an index nor an instance name of {}",
+                                ENV_PREFIX, key, selector, path
+                            )));
+                        }
+                    }
+                }
+                None => find_by_name(DEFAULT_INSTANCE).unwrap_or(0),
+            };
+
+            // An override can't add an entry, it would lack every other required field
+            if index >= items.len() {
+                return Err(ConfigError::Message(format!(
+                    "{}{}: {} has no entry {}, add the instance to config.toml first",
+                    ENV_PREFIX, key, path, index
+                )));
             }
+
+            path.push_str(&format!("[{}]", index));
+            current = items.get(index);
+            current_default = default_items.first();
         }
     }
 
-    config
+    Ok(path)
 }
 
+fn apply_env_overrides(
+    mut builder: ConfigBuilder<DefaultState>,
+    config: &toml::Table,
+) -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+    let defaults = toml::Table::try_from(create_default_config())
+        .map_err(|e| ConfigError::Foreign(Box::new(e)))?;
+
+    // vars() would panic on anything that isn't UTF-8, even if it isn't ours
+    for (key, value) in std::env::vars_os() {
+        let Some(key) = key.to_str().and_then(|key| key.strip_prefix(ENV_PREFIX)) else {
+            continue;
+        };
+        let value = value.into_string().map_err(|_| {
+            ConfigError::Message(format!("{}{} is not valid UTF-8", ENV_PREFIX, key))
+        })?;
+
+        // HOMEDASH__SONARR__API_KEY_FILE=/run/secrets/sonarr reads the value from a file
+        let (key, value) = match key.strip_suffix(ENV_FILE_SUFFIX) {
+            Some(key) => (key, read_secret_file(&value)?),
+            None => (key, value),
+        };
+
+        builder = builder.set_override(env_key_to_path(key, config, &defaults)?, value)?;
+    }
+
+    Ok(builder)
+}
+
+// Returns the current config snapshot, loading it from disk on first use
 pub fn get_config() -> Result<ConfigFields, ConfigError> {
-    let config_path = get_config_path();
+    Ok(config_snapshot()?.as_ref().clone())
+}
 
-    if !std::path::Path::new(&config_path).exists() {
-        let default_config = create_default_config();
-        let toml_string = toml::to_string(&default_config).unwrap();
-        let mut file = std::fs::File::create(&config_path).unwrap();
-        file.write_all(toml_string.as_bytes()).unwrap();
+pub fn config_snapshot() -> Result<Arc<ConfigFields>, ConfigError> {
+    if let Some(config) = CONFIG.read().unwrap().as_ref() {
+        return Ok(Arc::clone(config));
     }
 
-    let config = Config::builder()
-        .add_source(File::with_name(&config_path.trim_end_matches(".toml")).required(true))
+    Ok(store_config(read_config()?))
+}
+
+// The stored snapshot, None before anything stored one, never reads from disk
+pub fn loaded_config() -> Option<Arc<ConfigFields>> {
+    CONFIG.read().unwrap().as_ref().map(Arc::clone)
+}
+
+pub fn store_config(config: ConfigFields) -> Arc<ConfigFields> {
+    set_secrets(&config);
+
+    let config = Arc::new(config);
+    *CONFIG.write().unwrap() = Some(Arc::clone(&config));
+
+    config
+}
+
+fn config_modified() -> Option<SystemTime> {
+    std::fs::metadata(get_config_path())
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// Reloads config.toml whenever it changes, invalid files keep the last good config
+pub fn watch_config<F>(on_change: F)
+where
+    F: Fn(&ConfigFields) + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut last_modified = config_modified();
+
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+
+            let modified = config_modified();
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            let config = match read_config() {
+                Ok(config) => config,
+                Err(e) => {
+                    error!("Config Error: {} (keeping last good config)", e);
+                    continue;
+                }
+            };
+
+            let issues = validate_config(&config);
+            if !issues.is_empty() {
+                error!(
+                    "Config Error: {} problem(s) found (keeping last good config)",
+                    issues.len()
+                );
+                for issue in issues {
+                    error!("  {}", issue);
+                }
+                continue;
+            }
+
+            info!("Reloaded config");
+            on_change(&store_config(config));
+        }
+    });
+}
+
+// Reads config.toml from disk, creating or migrating it if needed
+pub fn read_config() -> Result<ConfigFields, ConfigError> {
+    // Migrations run on the raw TOML since older layouts don't fit ConfigFields
+    let mut credentials = Vec::new();
+    let table = update_config_file(|table| match migrate_config(table) {
+        Some(moved) => {
+            credentials = moved;
+            true
+        }
+        None => false,
+    })?;
+
+    // Only after the migrated file is written, checks of a config never touch the database
+    move_credentials(credentials);
+
+    build_config(&table)
+}
+
+// Loads a config file without creating, migrating or rewriting it on disk
+pub fn load_config(path: &str) -> Result<ConfigFields, ConfigError> {
+    let mut table = read_config_table_from(path)?;
+    // Credentials of old layouts are dropped, only read_config stores them
+    migrate_config(&mut table);
+
+    build_config(&table)
+}
+
+// config.toml as it is on disk, without environment overrides
+pub fn stored_config() -> Result<ConfigFields, ConfigError> {
+    let mut table = read_config_table()?;
+    migrate_config(&mut table);
+
+    config_builder(&table)?.build()?.try_deserialize()
+}
+
+fn config_builder(table: &toml::Table) -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+    let toml_string = toml::to_string(table).map_err(|e| ConfigError::Foreign(Box::new(e)))?;
+
+    Ok(Config::builder().add_source(File::from_str(&toml_string, FileFormat::Toml)))
+}
+
+fn build_config(table: &toml::Table) -> Result<ConfigFields, ConfigError> {
+    apply_env_overrides(config_builder(table)?, table)?
         .build()?
-        .try_deserialize()?;
-    Ok(migrate_config(config))
+        .try_deserialize()
 }
 
-pub fn write_oauth_config(fields: &OauthValues) -> Result<ConfigFields, ConfigError> {
-    let mut config = get_config()?;
+// Applies environment overrides to a config that hasn't been written yet
+pub fn preview_config(config: &ConfigFields) -> Result<ConfigFields, ConfigError> {
+    build_config(&toml::Table::try_from(config).map_err(|e| ConfigError::Foreign(Box::new(e)))?)
+}
 
-    if fields.service == "tvdb" {
-        config.tvdb.token = String::from(&fields.token);
-        config.tvdb.expires_in = fields.expires_in;
-    }
-    // Can add more oauth service logins here in the future
+// Replaces config.toml with the given config and swaps in the new snapshot
+pub fn write_config(config: &ConfigFields) -> Result<ConfigFields, ConfigError> {
+    let mut config = config.clone();
+    config.version = LATEST_CONFIG_VERSION;
 
-    let toml_string = toml::to_string(&config).unwrap();
-    let mut file = std::fs::File::create(get_config_path()).unwrap();
-    file.write_all(toml_string.as_bytes()).unwrap();
+    let table = toml::Table::try_from(&config).map_err(|e| ConfigError::Foreign(Box::new(e)))?;
+    update_config_file(|file| {
+        *file = table;
+        true
+    })?;
 
-    Ok(config)
+    Ok(store_config(read_config()?).as_ref().clone())
 }
 
-pub fn write_cookie_config(fields: &CookieValues) -> Result<ConfigFields, ConfigError> {
-    let mut config = get_config()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        [ping]
+        interval = 60
+
+        [[radarr]]
+        name = "default"
+        api_key = "a"
+
+        [[radarr]]
+        name = "4k"
+        api_key = "b"
+
+        [[sonarr]]
+        name = "main"
+        api_key = "c"
+
+        [http]
+        urls = [{ name = "nas", url = "http://nas.lan" }]
+
+        [[monitors]]
+        name = "router"
+        type = "http"
+    "#;
+
+    fn path(key: &str) -> Result<String, ConfigError> {
+        let config: toml::Table = toml::from_str(CONFIG).unwrap();
+        let defaults = toml::Table::try_from(create_default_config()).unwrap();
 
-    if fields.service == "qbittorrent" {
-        config.qbittorrent.cookie = Some(String::from(&fields.cookie));
+        env_key_to_path(key, &config, &defaults)
     }
-    // Can add more cookie service logins here in the future
 
-    let toml_string = toml::to_string(&config).unwrap();
-    let mut file = std::fs::File::create(get_config_path()).unwrap();
-    file.write_all(toml_string.as_bytes()).unwrap();
+    #[test]
+    fn takes_credentials_out_of_old_configs() {
+        let mut table: toml::Table = toml::from_str(
+            r#"
+            version = 4
+            [tvdb]
+            enabled = true
+            api_key = "key"
+            token = "tok123"
+            expires_in = 1900000000
+            [[qbittorrent]]
+            name = "default"
+            cookie = "SID=abc"
+        "#,
+        )
+        .unwrap();
 
-    Ok(config)
+        let credentials = migrate_config(&mut table).unwrap();
+        let moved: Vec<_> = credentials
+            .iter()
+            .map(|c| {
+                (
+                    c.service.as_str(),
+                    c.instance.as_str(),
+                    c.value.as_str(),
+                    c.expires,
+                )
+            })
+            .collect();
+        assert_eq!(
+            moved,
+            [
+                ("tvdb", "", "tok123", 1900000000),
+                ("qbittorrent", "default", "SID=abc", 0)
+            ]
+        );
+        assert!(table["tvdb"].get("token").is_none());
+        assert_eq!(
+            table["version"].as_integer(),
+            Some(LATEST_CONFIG_VERSION as i64)
+        );
+
+        // Already migrated
+        assert!(migrate_config(&mut table).is_none());
+    }
+
+    #[test]
+    fn maps_nested_keys() {
+        assert_eq!(path("PING__INTERVAL").unwrap(), "ping.interval");
+        assert_eq!(
+            path("AUTH__PROXY__USER_HEADER").unwrap(),
+            "auth.proxy.user_header"
+        );
+    }
+
+    #[test]
+    fn selects_list_entries() {
+        assert_eq!(path("RADARR__1__API_KEY").unwrap(), "radarr[1].api_key");
+        assert_eq!(path("RADARR__4K__API_KEY").unwrap(), "radarr[1].api_key");
+        assert_eq!(path("RADARR__API_KEY").unwrap(), "radarr[0].api_key");
+        // Without a "default" instance the first one is used
+        assert_eq!(path("SONARR__API_KEY").unwrap(), "sonarr[0].api_key");
+        assert_eq!(path("HTTP__URLS__0__URL").unwrap(), "http.urls[0].url");
+        assert_eq!(path("HTTP__URLS__NAS__URL").unwrap(), "http.urls[0].url");
+        // "check" is left out of config.toml when it's the default
+        assert_eq!(
+            path("MONITORS__ROUTER__CHECK__PATH").unwrap(),
+            "monitors[0].check.path"
+        );
+        assert_eq!(
+            path("MONITORS__CHECK__PATH").unwrap(),
+            "monitors[0].check.path"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_instances() {
+        let error = path("RADARR__5K__API_KEY").unwrap_err().to_string();
+        assert!(error.contains("\"5k\""), "{}", error);
+    }
+
+    #[test]
+    fn rejects_missing_entries() {
+        // Past the end of a list
+        let error = path("RADARR__2__API_KEY").unwrap_err().to_string();
+        assert!(error.contains("no entry 2"), "{}", error);
+
+        // Empty lists, with and without an index
+        for key in ["PROWLARR__0__API_KEY", "PROWLARR__API_KEY"] {
+            let config: toml::Table = toml::from_str("prowlarr = []").unwrap();
+            let defaults = toml::Table::try_from(create_default_config()).unwrap();
+            let error = env_key_to_path(key, &config, &defaults)
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains("no entry 0"), "{}: {}", key, error);
+        }
+    }
 }
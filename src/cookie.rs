@@ -1,9 +1,11 @@
-use reqwest::Client;
 use serde::Serialize;
 use serde_derive::Deserialize;
-use std::error::Error;
+use tracing::{debug, info};
 
-use crate::config::{write_cookie_config, APICredsConfig, CookieValues};
+use crate::client::http_client;
+use crate::config::APICredsConfig;
+use crate::database::{connect_db, get_cached_credential, set_cached_credential, CachedCredential};
+use crate::services::ServiceError;
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
@@ -11,6 +13,14 @@ pub struct CookieFields {
     pub request: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct CookieValues {
+    pub service: String,
+    pub instance: String,
+    pub cookie: String,
+}
+
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
 struct TvDbBody {
@@ -27,16 +37,17 @@ struct TvDbResponse {
 pub async fn retrieve_cookie(
     credentials: &APICredsConfig,
     fields: CookieFields,
-) -> Result<CookieValues, Box<dyn Error>> {
-    let client = Client::new();
+) -> Result<CookieValues, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
 
     if Some(String::from("qbittorrent")) == fields.request && credentials.enabled {
         let mut values = CookieValues {
             service: String::from("qbittorrent"),
-            cookie: credentials.cookie.clone().unwrap_or_default(),
+            instance: credentials.name.clone(),
+            cookie: cached_cookie("qbittorrent", &credentials.name).unwrap_or_default(),
         };
 
-        println!("Retrieving qbittorrent cookie");
+        debug!(instance = %credentials.name, "Retrieving qbittorrent cookie");
 
         let url = format!("{}/api/v2/auth/login", credentials.url);
         let response = client
@@ -46,12 +57,9 @@ pub async fn retrieve_cookie(
                 ("password", &credentials.password),
             ])
             .send()
-            .await
-            .unwrap();
+            .await?
+            .error_for_status()?;
 
-        if !response.status().is_success() {
-            return Err(Box::from("Failed to retrieve qbittorrent cookie"));
-        }
         // Get the cookie from the response headers
         if let Some(cookie_header) = response.headers().get("set-cookie") {
             if let Ok(cookie_str) = cookie_header.to_str() {
@@ -60,16 +68,38 @@ pub async fn retrieve_cookie(
                     values.cookie = cookie_value.to_string();
                 }
             }
+        } else {
+            // qBittorrent answers a failed login with 200 "Fails." and no cookie
+            return Err(ServiceError::Unauthorized);
         }
 
-        write_cookie_config(&values).unwrap();
+        let conn = connect_db()?;
+        set_cached_credential(
+            &conn,
+            &CachedCredential {
+                service: values.service.clone(),
+                instance: values.instance.clone(),
+                value: values.cookie.clone(),
+                expires: 0,
+            },
+        )?;
+        conn.close().unwrap();
 
-        println!("Retrieved qbittorrent cookie");
+        info!(instance = %credentials.name, "Retrieved qbittorrent cookie");
 
         return Ok(values);
     }
 
     // Can add more cookie service logins here in the future
 
-    Err(Box::from("No cookie service requested"))
+    Err(ServiceError::Disabled)
+}
+
+// Returns the last session cookie stored for a service instance
+pub fn cached_cookie(service: &str, instance: &str) -> Option<String> {
+    let conn = connect_db().ok()?;
+    let cached = get_cached_credential(&conn, service, instance).ok();
+    conn.close().ok()?;
+
+    cached.map(|credential| credential.value)
 }
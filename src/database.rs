@@ -1,8 +1,12 @@
-use rusqlite::{params, Connection, Result};
+use rusqlite::types::{FromSql, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension, Result, ToSql};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::config::ConfigFields;
+use crate::config::{instance_display_name, ConfigFields};
+use crate::maintenance::services_in_maintenance;
+use crate::services::registry::SERVICES;
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
@@ -15,18 +19,175 @@ pub struct Poster {
     pub json_data: String,
 }
 
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceState {
+    // Not checked yet
+    Unknown,
+    Up,
+    // Failing, but fewer than ping.failure_threshold times in a row
+    Degraded,
+    Down,
+    Maintenance,
+}
+
+impl ServiceState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServiceState::Unknown => "unknown",
+            ServiceState::Up => "up",
+            ServiceState::Degraded => "degraded",
+            ServiceState::Down => "down",
+            ServiceState::Maintenance => "maintenance",
+        }
+    }
+
+    pub fn parse(value: &str) -> ServiceState {
+        match value {
+            "up" => ServiceState::Up,
+            "degraded" => ServiceState::Degraded,
+            "down" => ServiceState::Down,
+            "maintenance" => ServiceState::Maintenance,
+            _ => ServiceState::Unknown,
+        }
+    }
+}
+
+impl ToSql for ServiceState {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for ServiceState {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Null => Ok(ServiceState::Unknown),
+            value => value.as_str().map(ServiceState::parse),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct MaintenanceWindow {
+    #[serde(default)]
+    pub id: u32,
+    // None applies the window to every service
+    pub service_id: Option<u32>,
+    pub name: String,
+    // One-off window as unix timestamps
+    pub starts_at: Option<i64>,
+    pub ends_at: Option<i64>,
+    // Recurring window, a cron expression in local time and its length in minutes
+    pub schedule: Option<String>,
+    pub duration: Option<u32>,
+}
+
+// What the state machine needs to know about a service
+#[derive(Debug, Clone)]
+#[allow(unused)]
+pub struct ServiceStatus {
+    pub state: ServiceState,
+    pub state_since: i64,
+    pub failures: u32,
+    pub successes: u32,
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct StateTransition {
+    pub from: ServiceState,
+    pub to: ServiceState,
+    pub changed_at: i64,
+    pub message: Option<String>,
+    // Seconds until the next transition, None for the current state
+    pub duration: Option<i64>,
+}
+
+// An alert whose condition currently holds, notified once it was sent
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct Alert {
+    pub rule: String,
+    pub service: String,
+    pub subject: String,
+    pub message: String,
+    pub since: i64,
+    pub notified: bool,
+}
+
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
 pub struct Ping {
     pub id: Option<u32>,
     pub name: String,
     pub status: u32,
+    pub up: bool,
+    pub message: Option<String>,
+    pub state: ServiceState,
+    // When the service entered its state, e.g. "down since 14:02"
+    pub state_since: i64,
+    pub cert_issuer: Option<String>,
+    pub cert_expires: Option<i64>,
+    // Expires within ping.cert_warning_days
+    pub cert_expiring: bool,
+    // Percent of passed checks, None without history
+    pub uptime_24h: Option<f64>,
+    pub uptime_7d: Option<f64>,
+    pub uptime_30d: Option<f64>,
+    // Milliseconds over the last 24 hours
+    pub avg_latency: Option<f64>,
+    // Milliseconds of the last check, None if it had no response
+    pub latency: Option<f64>,
     pub last_check: u32,
     pub url: String,
     pub app_order: i32,
     pub is_favorite: bool,
 }
 
+// One check as written to ping_history
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct PingCheck {
+    pub checked_at: i64,
+    pub status: u16,
+    pub up: bool,
+    pub latency: Option<u64>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct PingHistory {
+    pub checked_at: i64,
+    // 0 for a single check, 3600 for an hour of merged checks
+    pub period: i64,
+    pub checks: i64,
+    pub up_checks: i64,
+    pub status: u16,
+    pub latency: Option<f64>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct CachedCredential {
+    pub service: String,
+    pub instance: String,
+    pub value: String,
+    pub expires: i64,
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct CachedResponse {
+    pub fetched_at: i64,
+    pub data: serde_json::Value,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
 pub struct Preference {
@@ -35,6 +196,15 @@ pub struct Preference {
     pub app_order: i32,
 }
 
+// A local login, the password hash never leaves the database module
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct User {
+    pub id: u32,
+    pub username: String,
+    pub created_at: i64,
+}
+
 pub fn connect_db() -> Result<Connection, Box<dyn Error>> {
     let db_path = if std::path::Path::new("data").exists() {
         "data/database.db"
@@ -50,28 +220,88 @@ pub fn connect_db() -> Result<Connection, Box<dyn Error>> {
     Ok(conn)
 }
 
+// Adds a column to a table created by an older version
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists = conn
+        .prepare(&format!(
+            "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ))?
+        .exists([column])?;
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            (),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn drop_column(conn: &Connection, table: &str, column: &str) -> Result<()> {
+    let exists = conn
+        .prepare(&format!(
+            "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ))?
+        .exists([column])?;
+
+    if exists {
+        conn.execute(&format!("ALTER TABLE {} DROP COLUMN {}", table, column), ())?;
+    }
+
+    Ok(())
+}
+
 pub fn create_schemas(conn: &Connection) -> Result<()> {
     /*
          id = service id
          name = service name
-         status = service status
+         status = HTTP status of the last check, 0 if there was no response
+         up = whether the last check passed
+         message = why the last check failed
+         state = unknown, up, degraded, down or maintenance
+         state_since = when the service entered its state
+         failures = consecutive failed checks
+         successes = consecutive passed checks
+         paused_since = when monitoring was paused by hand, NULL if it isn't
+         cert_issuer = issuer of the HTTPS or TLS peer certificate
+         cert_expires = notAfter of that certificate as a unix timestamp
          last_check = last time the service was checked
-         expires = time in seconds before the json_data cache expires
          url = service url
-         json_data = service data in json format + needs a timestamp appended for cache expiration
     */
     conn.execute(
         "CREATE TABLE IF NOT EXISTS services (
               id          INTEGER PRIMARY KEY,
               name        TEXT NOT NULL UNIQUE,
               status      INTEGER DEFAULT 0,
+              up          BOOLEAN DEFAULT FALSE,
+              message     TEXT,
+              state       TEXT DEFAULT 'unknown',
+              state_since INTEGER DEFAULT 0,
+              failures    INTEGER DEFAULT 0,
+              successes   INTEGER DEFAULT 0,
+              paused_since INTEGER,
+              cert_issuer TEXT,
+              cert_expires INTEGER,
               last_check  INTEGER DEFAULT 0,
-              expires     INTEGER DEFAULT 180,
-              url         TEXT NOT NULL,
-              json_data   TEXT
+              url         TEXT NOT NULL
            )",
         (),
     )?;
+    add_column(conn, "services", "up", "BOOLEAN DEFAULT FALSE")?;
+    add_column(conn, "services", "message", "TEXT")?;
+    add_column(conn, "services", "state", "TEXT DEFAULT 'unknown'")?;
+    add_column(conn, "services", "state_since", "INTEGER DEFAULT 0")?;
+    add_column(conn, "services", "failures", "INTEGER DEFAULT 0")?;
+    add_column(conn, "services", "successes", "INTEGER DEFAULT 0")?;
+    add_column(conn, "services", "paused_since", "INTEGER")?;
+    add_column(conn, "services", "cert_issuer", "TEXT")?;
+    add_column(conn, "services", "cert_expires", "INTEGER")?;
+    // API responses are cached in response_cache with the TTL from the config
+    drop_column(conn, "services", "expires")?;
+    drop_column(conn, "services", "json_data")?;
 
     /*
          id = preference id
@@ -146,6 +376,218 @@ pub fn create_schemas(conn: &Connection) -> Result<()> {
         (),
     )?;
 
+    /*
+        service_id = service id
+        checked_at = unix timestamp of the check, start of the hour for merged rows
+        period = 0 for a single check, 3600 for an hour of merged checks
+        checks = number of checks in the row
+        up_checks = number of those checks that passed
+        status = HTTP status of the (last) check
+        latency = milliseconds, averaged for merged rows
+        message = why the (last) check failed
+    */
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ping_history (
+              id          INTEGER PRIMARY KEY,
+              service_id  INTEGER NOT NULL,
+              checked_at  INTEGER NOT NULL,
+              period      INTEGER DEFAULT 0,
+              checks      INTEGER DEFAULT 1,
+              up_checks   INTEGER DEFAULT 0,
+              status      INTEGER DEFAULT 0,
+              latency     REAL,
+              message     TEXT,
+              FOREIGN KEY(service_id) REFERENCES services(id)
+            )",
+        (),
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS ping_history_service ON ping_history (service_id, checked_at)",
+        (),
+    )?;
+
+    /*
+        trigger to delete the history when a service is deleted
+    */
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS delete_ping_history
+              AFTER DELETE ON services
+              BEGIN
+               DELETE FROM ping_history WHERE service_id = OLD.id;
+              END;",
+        (),
+    )?;
+
+    /*
+        service_id = service id
+        from_state = state before the change
+        to_state = state after the change
+        changed_at = unix timestamp of the change
+        message = why the check failed, if it did
+    */
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS state_transitions (
+              id          INTEGER PRIMARY KEY,
+              service_id  INTEGER NOT NULL,
+              from_state  TEXT NOT NULL,
+              to_state    TEXT NOT NULL,
+              changed_at  INTEGER NOT NULL,
+              message     TEXT,
+              FOREIGN KEY(service_id) REFERENCES services(id)
+            )",
+        (),
+    )?;
+
+    /*
+        trigger to delete the transitions when a service is deleted
+    */
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS delete_state_transitions
+              AFTER DELETE ON services
+              BEGIN
+               DELETE FROM state_transitions WHERE service_id = OLD.id;
+              END;",
+        (),
+    )?;
+
+    /*
+        id = window id
+        service_id = service id, NULL for every service
+        name = what the window is for, e.g. "Proxmox reboot"
+        starts_at, ends_at = one-off window as unix timestamps
+        schedule = recurring window as a cron expression in local time, e.g. "0 4 * * 0"
+        duration = length of a recurring window in minutes
+    */
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS maintenance_windows (
+              id          INTEGER PRIMARY KEY,
+              service_id  INTEGER,
+              name        TEXT NOT NULL,
+              starts_at   INTEGER,
+              ends_at     INTEGER,
+              schedule    TEXT,
+              duration    INTEGER,
+              FOREIGN KEY(service_id) REFERENCES services(id)
+            )",
+        (),
+    )?;
+
+    /*
+        trigger to delete the windows of a service when it is deleted
+    */
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS delete_maintenance_windows
+              AFTER DELETE ON services
+              BEGIN
+               DELETE FROM maintenance_windows WHERE service_id = OLD.id;
+              END;",
+        (),
+    )?;
+
+    /*
+        rule = alert rule name
+        service = services table or instance name the alert is about
+        subject = what fired, unique per rule, e.g. "Proxmox pve/local-lvm"
+        message = last description of the condition
+        since = when the condition started
+        notified = whether the alert was sent, a recovery is only sent if it was
+    */
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS alerts (
+              rule        TEXT NOT NULL,
+              service     TEXT NOT NULL,
+              subject     TEXT NOT NULL,
+              message     TEXT NOT NULL,
+              since       INTEGER NOT NULL,
+              notified    BOOLEAN DEFAULT FALSE,
+              PRIMARY KEY(rule, subject)
+            )",
+        (),
+    )?;
+
+    /*
+        service = registry id of the service, e.g. radarr
+        instance = instance name, e.g. 4k
+        endpoint = cached API endpoint, e.g. movies
+        url = instance url the response came from, responses of a changed url aren't served
+        fetched_at = unix timestamp of the last good response
+        data = that response in json, NULL until one succeeded
+        error = last failed refresh, data stays the last good response
+    */
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS response_cache (
+              service     TEXT NOT NULL,
+              instance    TEXT NOT NULL DEFAULT '',
+              endpoint    TEXT NOT NULL,
+              url         TEXT NOT NULL DEFAULT '',
+              fetched_at  INTEGER DEFAULT 0,
+              data        TEXT,
+              error       TEXT,
+              PRIMARY KEY (service, instance, endpoint)
+            )",
+        (),
+    )?;
+
+    /*
+        service = service the credential belongs to, e.g. tvdb
+        instance = service instance name, empty for single instance services
+        value = bearer token or session cookie
+        expires = unix timestamp when the value expires, 0 if unknown
+    */
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS credentials_cache (
+              service     TEXT NOT NULL,
+              instance    TEXT NOT NULL DEFAULT '',
+              value       TEXT NOT NULL,
+              expires     INTEGER DEFAULT 0,
+              PRIMARY KEY (service, instance)
+            )",
+        (),
+    )?;
+
+    /*
+        username = login name
+        password_hash = argon2 PHC string, e.g. "$argon2id$v=19$m=19456,t=2,p=1$..."
+        created_at = unix timestamp
+    */
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS users (
+              id              INTEGER PRIMARY KEY,
+              username        TEXT NOT NULL UNIQUE,
+              password_hash   TEXT NOT NULL,
+              created_at      INTEGER NOT NULL
+            )",
+        (),
+    )?;
+
+    /*
+        token = random value of the session cookie
+        user_id = users table id
+        created_at = unix timestamp of the login
+        expires_at = unix timestamp after which the session is no longer accepted
+    */
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sessions (
+              token       TEXT PRIMARY KEY,
+              user_id     INTEGER NOT NULL,
+              created_at  INTEGER NOT NULL,
+              expires_at  INTEGER NOT NULL
+            )",
+        (),
+    )?;
+
+    /*
+        trigger to end the sessions of a deleted user
+    */
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS delete_sessions
+              AFTER DELETE ON users
+              BEGIN
+               DELETE FROM sessions WHERE user_id = OLD.id;
+              END;",
+        (),
+    )?;
+
     /*
        test data
     */
@@ -158,98 +600,161 @@ pub fn create_schemas(conn: &Connection) -> Result<()> {
 }
 
 pub fn populate_tables(conn: &Connection, config: &ConfigFields) -> Result<()> {
-    let mut upsert = conn.prepare("INSERT INTO services (name, url) VALUES (?1, ?2) ON CONFLICT(name) DO UPDATE SET url = excluded.url")?;
+    // A changed URL points at a different server, so its cached responses and certificate are dropped
+    let mut upsert = conn.prepare(
+        "INSERT INTO services (name, url) VALUES (?1, ?2)
+         ON CONFLICT(name) DO UPDATE SET
+            cert_issuer = CASE WHEN url = excluded.url THEN cert_issuer ELSE NULL END,
+            cert_expires = CASE WHEN url = excluded.url THEN cert_expires ELSE NULL END,
+            url = excluded.url",
+    )?;
     let mut delete = conn.prepare("DELETE FROM services WHERE name = ?1")?;
 
-    // Handle built-in services
-    if config.sonarr.enabled {
-        upsert.execute(params![String::from("Sonarr"), config.sonarr.url])?;
-    } else {
-        delete.execute(params![String::from("Sonarr")])?;
-    }
-
-    if config.radarr.enabled {
-        upsert.execute(params![String::from("Radarr"), config.radarr.url])?;
-    } else {
-        delete.execute(params![String::from("Radarr")])?;
+    // Collect every enabled service instance and HTTP URL from the config
+    let mut config_names = Vec::new();
+    for (name, url) in configured_services(config) {
+        upsert.execute(params![name, url])?;
+        config_names.push(name);
     }
 
-    if config.prowlarr.enabled {
-        upsert.execute(params![String::from("Prowlarr"), config.prowlarr.url])?;
-    } else {
-        delete.execute(params![String::from("Prowlarr")])?;
-    }
+    // Delete every service that is no longer in the current config
+    let mut stmt = conn.prepare("SELECT name FROM services")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
 
-    if config.overseerr.enabled {
-        upsert.execute(params![String::from("Overseerr"), config.overseerr.url])?;
-    } else {
-        delete.execute(params![String::from("Overseerr")])?;
-    }
-
-    if config.qbittorrent.enabled {
-        upsert.execute(params![String::from("qBittorrent"), config.qbittorrent.url])?;
-    } else {
-        delete.execute(params![String::from("qBittorrent")])?;
+    for row in rows {
+        let name = row?;
+        if !config_names.contains(&name) {
+            delete.execute(params![name])?;
+        }
     }
 
-    if config.plex.enabled {
-        upsert.execute(params![String::from("Plex"), config.plex.url])?;
-    } else {
-        delete.execute(params![String::from("Plex")])?;
+    // Cached responses of removed or disabled instances
+    let mut instances = Vec::new();
+    for service in SERVICES {
+        for instance in service.instances(config) {
+            if instance.enabled() {
+                instances.push((String::from(service.id()), String::from(instance.name())));
+            }
+        }
     }
 
-    if config.tautulli.enabled {
-        upsert.execute(params![String::from("Tautulli"), config.tautulli.url])?;
-    } else {
-        delete.execute(params![String::from("Tautulli")])?;
-    }
+    let mut stmt = conn.prepare("SELECT DISTINCT service, instance FROM response_cache")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    let mut delete =
+        conn.prepare("DELETE FROM response_cache WHERE service = ?1 AND instance = ?2")?;
 
-    if config.proxmox.enabled {
-        upsert.execute(params![String::from("Proxmox"), config.proxmox.url])?;
-    } else {
-        delete.execute(params![String::from("Proxmox")])?;
+    for row in rows {
+        let (service, instance) = row?;
+        if !instances.contains(&(service.clone(), instance.clone())) {
+            delete.execute(params![service, instance])?;
+        }
     }
 
-    if config.adguard.enabled {
-        upsert.execute(params![String::from("AdGuard"), config.adguard.url])?;
-    } else {
-        delete.execute(params![String::from("AdGuard")])?;
-    }
+    Ok(())
+}
 
-    if config.dockwatch.enabled {
-        upsert.execute(params![String::from("Dockwatch"), config.dockwatch.url])?;
-    } else {
-        delete.execute(params![String::from("Dockwatch")])?;
-    }
+// Returns (display name, url) for every enabled service instance, HTTP URL and monitor
+pub fn configured_services(config: &ConfigFields) -> Vec<(String, String)> {
+    let mut services = configured_apps(config);
 
-    // For HTTP URLs, first collect all configured URLs
-    let mut config_http_names = Vec::new();
     if config.http.enabled {
         for url in &config.http.urls {
-            upsert.execute(params![url.name, url.url])?;
-            config_http_names.push(String::from(&url.name));
+            services.push((url.name.clone(), url.url.clone()));
         }
     }
 
-    // Only delete HTTP URLs that are not in the current config
-    let mut stmt = conn.prepare(
-        "SELECT name, url FROM services
-         WHERE name NOT IN ('Sonarr', 'Radarr', 'Prowlarr', 'Overseerr',
-         'qBittorrent', 'Plex', 'Tautulli', 'Proxmox', 'AdGuard', 'Dockwatch')",
-    )?;
+    for monitor in &config.monitors {
+        services.push((monitor.name.clone(), monitor.url()));
+    }
 
-    let rows = stmt.query_map([], |row| {
-        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-    })?;
+    services
+}
 
-    for row in rows {
-        let (name, _url) = row?;
-        if !config_http_names.contains(&name) {
-            delete.execute(params![name])?;
+// Returns (display name, url) for every enabled built-in service instance
+pub fn configured_apps(config: &ConfigFields) -> Vec<(String, String)> {
+    let mut services = Vec::new();
+
+    for service in SERVICES.iter().filter(|service| service.pinged()) {
+        for instance in service.instances(config) {
+            if instance.enabled() {
+                services.push((
+                    instance_display_name(service.name(), instance.name()),
+                    String::from(instance.url()),
+                ));
+            }
         }
     }
 
-    Ok(())
+    services
+}
+
+// The url a response came from and the response, None if the endpoint was never cached
+pub fn get_cached_response(
+    conn: &Connection,
+    service: &str,
+    instance: &str,
+    endpoint: &str,
+) -> Result<Option<(String, CachedResponse)>, Box<dyn Error>> {
+    let row = conn
+        .query_row(
+            "SELECT url, fetched_at, data, error FROM response_cache
+             WHERE service = ?1 AND instance = ?2 AND endpoint = ?3",
+            params![service, instance, endpoint],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            },
+        )
+        .optional()?;
+
+    Ok(row.map(|(url, fetched_at, data, error)| {
+        let data = data
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or(serde_json::Value::Null);
+
+        (
+            url,
+            CachedResponse {
+                fetched_at,
+                data,
+                error,
+            },
+        )
+    }))
+}
+
+pub fn set_cached_response(
+    conn: &Connection,
+    service: &str,
+    instance: &str,
+    endpoint: &str,
+    url: &str,
+    response: &CachedResponse,
+) -> Result<usize, Box<dyn Error>> {
+    Ok(conn.execute(
+        "INSERT INTO response_cache (service, instance, endpoint, url, fetched_at, data, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(service, instance, endpoint) DO UPDATE SET
+            url = excluded.url,
+            fetched_at = excluded.fetched_at,
+            data = excluded.data,
+            error = excluded.error",
+        params![
+            service,
+            instance,
+            endpoint,
+            url,
+            response.fetched_at,
+            (!response.data.is_null()).then(|| response.data.to_string()),
+            response.error
+        ],
+    )?)
 }
 
 pub fn get_tvdb_id(conn: &Connection, tvdb_id: u32) -> Result<Poster, Box<dyn Error>> {
@@ -300,33 +805,572 @@ pub fn insert_tmdb_id(conn: &Connection, poster: &Poster) -> Result<usize, Box<d
     )?)
 }
 
-pub fn get_ping_data(conn: &Connection) -> Result<Vec<Ping>, Box<dyn Error>> {
+pub fn get_cached_credential(
+    conn: &Connection,
+    service: &str,
+    instance: &str,
+) -> Result<CachedCredential, Box<dyn Error>> {
+    Ok(conn.query_row(
+        "SELECT service, instance, value, expires FROM credentials_cache
+         WHERE service = ?1 AND instance = ?2",
+        params![service, instance],
+        |row| {
+            Ok(CachedCredential {
+                service: row.get(0)?,
+                instance: row.get(1)?,
+                value: row.get(2)?,
+                expires: row.get(3)?,
+            })
+        },
+    )?)
+}
+
+pub fn set_cached_credential(
+    conn: &Connection,
+    credential: &CachedCredential,
+) -> Result<usize, Box<dyn Error>> {
+    Ok(conn.execute(
+        "INSERT INTO credentials_cache (service, instance, value, expires) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(service, instance) DO UPDATE SET value = excluded.value, expires = excluded.expires",
+        params![
+            credential.service,
+            credential.instance,
+            credential.value,
+            credential.expires
+        ],
+    )?)
+}
+
+// Certificates expiring before expiring_before (unix timestamp) are flagged
+pub fn get_ping_data(conn: &Connection, expiring_before: i64) -> Result<Vec<Ping>, Box<dyn Error>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
     let mut stmt = conn.prepare(
-        "SELECT s.id, s.name, s.status, s.last_check, s.url, p.app_order, p.is_favorite
+        "SELECT s.id, s.name, s.status, s.up, s.message, s.state, s.state_since,
+            s.cert_issuer, s.cert_expires,
+            COALESCE(s.cert_expires < ?1, FALSE),
+            (SELECT SUM(h.up_checks) * 100.0 / SUM(h.checks) FROM ping_history h
+                WHERE h.service_id = s.id AND h.checked_at >= ?2),
+            (SELECT SUM(h.up_checks) * 100.0 / SUM(h.checks) FROM ping_history h
+                WHERE h.service_id = s.id AND h.checked_at >= ?3),
+            (SELECT SUM(h.up_checks) * 100.0 / SUM(h.checks) FROM ping_history h
+                WHERE h.service_id = s.id AND h.checked_at >= ?4),
+            (SELECT SUM(h.latency * h.checks) / SUM(CASE WHEN h.latency IS NOT NULL THEN h.checks END)
+                FROM ping_history h WHERE h.service_id = s.id AND h.checked_at >= ?2),
+            (SELECT h.latency FROM ping_history h
+                WHERE h.service_id = s.id AND h.period = 0 ORDER BY h.checked_at DESC LIMIT 1),
+            s.last_check, s.url, p.app_order, p.is_favorite
          FROM services s
          LEFT JOIN preferences p ON s.id = p.service_id
          ORDER BY p.app_order",
     )?;
 
-    let ping_iter = stmt.query_map([], |row| {
+    let params = params![
+        expiring_before,
+        now - 86400,
+        now - 7 * 86400,
+        now - 30 * 86400
+    ];
+    let ping_iter = stmt.query_map(params, |row| {
         Ok(Ping {
             id: row.get(0)?,
             name: row.get(1)?,
             status: row.get(2)?,
-            last_check: row.get(3)?,
-            url: row.get(4)?,
-            app_order: row.get(5)?,
-            is_favorite: row.get(6)?,
+            up: row.get(3)?,
+            message: row.get(4)?,
+            state: row.get(5)?,
+            state_since: row.get(6)?,
+            cert_issuer: row.get(7)?,
+            cert_expires: row.get(8)?,
+            cert_expiring: row.get(9)?,
+            uptime_24h: row.get(10)?,
+            uptime_7d: row.get(11)?,
+            uptime_30d: row.get(12)?,
+            avg_latency: row.get(13)?,
+            latency: row.get(14)?,
+            last_check: row.get(15)?,
+            url: row.get(16)?,
+            app_order: row.get(17)?,
+            is_favorite: row.get(18)?,
         })
     })?;
 
+    // Windows and pauses apply right away, not only from the next ping
+    let maintenance = services_in_maintenance(conn, now)?;
+
     let mut pings = Vec::new();
     for ping in ping_iter {
-        pings.push(ping?);
+        let mut ping = ping?;
+        if let Some(since) = maintenance.get(&ping.name) {
+            if ping.state != ServiceState::Maintenance {
+                ping.state = ServiceState::Maintenance;
+                ping.state_since = *since;
+            }
+        }
+        pings.push(ping);
     }
     Ok(pings)
 }
 
+pub fn insert_ping_history(
+    conn: &Connection,
+    service: &str,
+    check: &PingCheck,
+) -> Result<usize, Box<dyn Error>> {
+    Ok(conn.execute(
+        "INSERT INTO ping_history (service_id, checked_at, up_checks, status, latency, message)
+         SELECT id, ?2, ?3, ?4, ?5, ?6 FROM services WHERE name = ?1",
+        params![
+            service,
+            check.checked_at,
+            check.up as i64,
+            check.status,
+            check.latency.map(|latency| latency as f64),
+            check.message
+        ],
+    )?)
+}
+
+// Merges single checks older than raw_before into hourly rows, drops history and transitions older than delete_before
+pub fn compact_ping_history(
+    conn: &Connection,
+    raw_before: i64,
+    delete_before: i64,
+) -> Result<(), Box<dyn Error>> {
+    // Only whole hours are merged, a later run would add a second row for the rest of the hour
+    let raw_before = raw_before - raw_before.rem_euclid(3600);
+
+    // With MAX(checked_at), SQLite takes status and message from the last check of the hour
+    conn.execute(
+        "INSERT INTO ping_history (service_id, checked_at, period, checks, up_checks, status, latency, message)
+         SELECT service_id, hour, 3600, checks, up_checks, status, latency, message FROM (
+            SELECT service_id, checked_at / 3600 * 3600 AS hour, MAX(checked_at),
+                SUM(checks) AS checks, SUM(up_checks) AS up_checks, status,
+                AVG(latency) AS latency, message
+            FROM ping_history WHERE period = 0 AND checked_at < ?1
+            GROUP BY service_id, hour
+         )",
+        [raw_before],
+    )?;
+    conn.execute(
+        "DELETE FROM ping_history WHERE period = 0 AND checked_at < ?1",
+        [raw_before],
+    )?;
+    conn.execute(
+        "DELETE FROM ping_history WHERE checked_at < ?1",
+        [delete_before],
+    )?;
+    conn.execute(
+        "DELETE FROM state_transitions WHERE changed_at < ?1",
+        [delete_before],
+    )?;
+
+    Ok(())
+}
+
+// Rows /api/ping/<id>/history returns at most
+pub const MAX_PING_HISTORY: u32 = 10000;
+
+// Most recent history rows of a service checked at or after since, oldest first, None if the service doesn't exist
+pub fn get_ping_history(
+    conn: &Connection,
+    service_id: u32,
+    since: i64,
+    limit: u32,
+) -> Result<Option<Vec<PingHistory>>, Box<dyn Error>> {
+    let exists = conn
+        .prepare("SELECT 1 FROM services WHERE id = ?1")?
+        .exists([service_id])?;
+    if !exists {
+        return Ok(None);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT * FROM (
+            SELECT checked_at, period, checks, up_checks, status, latency, message
+            FROM ping_history WHERE service_id = ?1 AND checked_at >= ?2
+            ORDER BY checked_at DESC LIMIT ?3
+         ) ORDER BY checked_at",
+    )?;
+    let rows = stmt.query_map(params![service_id, since, limit], |row| {
+        Ok(PingHistory {
+            checked_at: row.get(0)?,
+            period: row.get(1)?,
+            checks: row.get(2)?,
+            up_checks: row.get(3)?,
+            status: row.get(4)?,
+            latency: row.get(5)?,
+            message: row.get(6)?,
+        })
+    })?;
+
+    Ok(Some(rows.collect::<Result<_, _>>()?))
+}
+
+pub fn get_service_name(conn: &Connection, id: u32) -> Result<Option<String>, Box<dyn Error>> {
+    Ok(conn
+        .query_row("SELECT name FROM services WHERE id = ?1", [id], |row| {
+            row.get(0)
+        })
+        .optional()?)
+}
+
+// None if the service has no row
+pub fn get_service_status(
+    conn: &Connection,
+    service: &str,
+) -> Result<Option<ServiceStatus>, Box<dyn Error>> {
+    Ok(conn
+        .query_row(
+            "SELECT state, state_since, failures, successes FROM services WHERE name = ?1",
+            [service],
+            |row| {
+                Ok(ServiceStatus {
+                    state: row.get(0)?,
+                    state_since: row.get(1)?,
+                    failures: row.get(2)?,
+                    successes: row.get(3)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+pub fn insert_state_transition(
+    conn: &Connection,
+    service: &str,
+    transition: &StateTransition,
+) -> Result<usize, Box<dyn Error>> {
+    Ok(conn.execute(
+        "INSERT INTO state_transitions (service_id, from_state, to_state, changed_at, message)
+         SELECT id, ?2, ?3, ?4, ?5 FROM services WHERE name = ?1",
+        params![
+            service,
+            transition.from,
+            transition.to,
+            transition.changed_at,
+            transition.message
+        ],
+    )?)
+}
+
+// Every transition of a service, oldest first, None if the service doesn't exist
+pub fn get_state_transitions(
+    conn: &Connection,
+    service_id: u32,
+) -> Result<Option<Vec<StateTransition>>, Box<dyn Error>> {
+    let exists = conn
+        .prepare("SELECT 1 FROM services WHERE id = ?1")?
+        .exists([service_id])?;
+    if !exists {
+        return Ok(None);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT from_state, to_state, changed_at, message
+         FROM state_transitions WHERE service_id = ?1 ORDER BY changed_at, id",
+    )?;
+    let mut transitions: Vec<StateTransition> = stmt
+        .query_map([service_id], |row| {
+            Ok(StateTransition {
+                from: row.get(0)?,
+                to: row.get(1)?,
+                changed_at: row.get(2)?,
+                message: row.get(3)?,
+                duration: None,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+
+    for index in 1..transitions.len() {
+        transitions[index - 1].duration =
+            Some(transitions[index].changed_at - transitions[index - 1].changed_at);
+    }
+
+    Ok(Some(transitions))
+}
+
+pub fn get_maintenance_windows(
+    conn: &Connection,
+) -> Result<Vec<MaintenanceWindow>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, service_id, name, starts_at, ends_at, schedule, duration
+         FROM maintenance_windows ORDER BY id",
+    )?;
+    let windows = stmt
+        .query_map([], |row| {
+            Ok(MaintenanceWindow {
+                id: row.get(0)?,
+                service_id: row.get(1)?,
+                name: row.get(2)?,
+                starts_at: row.get(3)?,
+                ends_at: row.get(4)?,
+                schedule: row.get(5)?,
+                duration: row.get(6)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+
+    Ok(windows)
+}
+
+// Returns the new window id, None if its service doesn't exist
+pub fn insert_maintenance_window(
+    conn: &Connection,
+    window: &MaintenanceWindow,
+) -> Result<Option<i64>, Box<dyn Error>> {
+    if let Some(service_id) = window.service_id {
+        let exists = conn
+            .prepare("SELECT 1 FROM services WHERE id = ?1")?
+            .exists([service_id])?;
+        if !exists {
+            return Ok(None);
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO maintenance_windows (service_id, name, starts_at, ends_at, schedule, duration)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            window.service_id,
+            window.name,
+            window.starts_at,
+            window.ends_at,
+            window.schedule,
+            window.duration
+        ],
+    )?;
+
+    Ok(Some(conn.last_insert_rowid()))
+}
+
+// False if the window doesn't exist
+pub fn delete_maintenance_window(conn: &Connection, id: u32) -> Result<bool, Box<dyn Error>> {
+    Ok(conn.execute("DELETE FROM maintenance_windows WHERE id = ?1", [id])? > 0)
+}
+
+// (service id, name, paused_since)
+pub type Pause = (u32, String, Option<i64>);
+
+// Pause of every service
+pub fn get_pauses(conn: &Connection) -> Result<Vec<Pause>, Box<dyn Error>> {
+    let mut stmt = conn.prepare("SELECT id, name, paused_since FROM services")?;
+    let pauses = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<_, _>>()?;
+
+    Ok(pauses)
+}
+
+// Pausing keeps an existing pause time, false if the service doesn't exist
+pub fn set_paused(
+    conn: &Connection,
+    service_id: u32,
+    paused: bool,
+) -> Result<bool, Box<dyn Error>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let updated = if paused {
+        conn.execute(
+            "UPDATE services SET paused_since = COALESCE(paused_since, ?1) WHERE id = ?2",
+            params![now, service_id],
+        )?
+    } else {
+        conn.execute(
+            "UPDATE services SET paused_since = NULL WHERE id = ?1",
+            [service_id],
+        )?
+    };
+
+    Ok(updated > 0)
+}
+
+pub fn get_alerts(conn: &Connection) -> Result<Vec<Alert>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT rule, service, subject, message, since, notified FROM alerts ORDER BY since",
+    )?;
+    let alerts = stmt
+        .query_map([], |row| {
+            Ok(Alert {
+                rule: row.get(0)?,
+                service: row.get(1)?,
+                subject: row.get(2)?,
+                message: row.get(3)?,
+                since: row.get(4)?,
+                notified: row.get(5)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+
+    Ok(alerts)
+}
+
+// Writes the alerts that still hold and removes the resolved ones in one transaction
+pub fn update_alerts(
+    conn: &mut Connection,
+    active: &[Alert],
+    resolved: &[Alert],
+) -> Result<(), Box<dyn Error>> {
+    let tx = conn.transaction()?;
+
+    for alert in active {
+        tx.execute(
+            "INSERT INTO alerts (rule, service, subject, message, since, notified)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(rule, subject) DO UPDATE SET
+                service = excluded.service,
+                message = excluded.message,
+                since = excluded.since,
+                notified = excluded.notified",
+            params![
+                alert.rule,
+                alert.service,
+                alert.subject,
+                alert.message,
+                alert.since,
+                alert.notified
+            ],
+        )?;
+    }
+    for alert in resolved {
+        tx.execute(
+            "DELETE FROM alerts WHERE rule = ?1 AND subject = ?2",
+            params![alert.rule, alert.subject],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn get_users(conn: &Connection) -> Result<Vec<User>, Box<dyn Error>> {
+    let mut stmt = conn.prepare("SELECT id, username, created_at FROM users ORDER BY username")?;
+    let users = stmt
+        .query_map([], |row| {
+            Ok(User {
+                id: row.get(0)?,
+                username: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+
+    Ok(users)
+}
+
+// The user and their password hash, None if there is no such user
+pub fn get_user_credentials(
+    conn: &Connection,
+    username: &str,
+) -> Result<Option<(User, String)>, Box<dyn Error>> {
+    let credentials = conn
+        .query_row(
+            "SELECT id, username, created_at, password_hash FROM users WHERE username = ?1",
+            [username],
+            |row| {
+                Ok((
+                    User {
+                        id: row.get(0)?,
+                        username: row.get(1)?,
+                        created_at: row.get(2)?,
+                    },
+                    row.get(3)?,
+                ))
+            },
+        )
+        .optional()?;
+
+    Ok(credentials)
+}
+
+// False if the username is taken
+pub fn insert_user(
+    conn: &Connection,
+    username: &str,
+    password_hash: &str,
+) -> Result<bool, Box<dyn Error>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO users (username, password_hash, created_at) VALUES (?1, ?2, ?3)",
+        params![username, password_hash, now],
+    )?;
+
+    Ok(inserted > 0)
+}
+
+// Also ends the user's sessions, false if the user doesn't exist
+pub fn set_password(
+    conn: &mut Connection,
+    username: &str,
+    password_hash: &str,
+) -> Result<bool, Box<dyn Error>> {
+    let tx = conn.transaction()?;
+
+    let updated = tx.execute(
+        "UPDATE users SET password_hash = ?1 WHERE username = ?2",
+        params![password_hash, username],
+    )?;
+    tx.execute(
+        "DELETE FROM sessions WHERE user_id IN (SELECT id FROM users WHERE username = ?1)",
+        [username],
+    )?;
+
+    tx.commit()?;
+    Ok(updated > 0)
+}
+
+// False if the user doesn't exist
+pub fn delete_user(conn: &Connection, username: &str) -> Result<bool, Box<dyn Error>> {
+    Ok(conn.execute("DELETE FROM users WHERE username = ?1", [username])? > 0)
+}
+
+// Expired sessions are cleaned up whenever someone logs in
+pub fn insert_session(
+    conn: &Connection,
+    token: &str,
+    user_id: u32,
+    expires_at: i64,
+) -> Result<(), Box<dyn Error>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+    conn.execute("DELETE FROM sessions WHERE expires_at <= ?1", [now])?;
+    conn.execute(
+        "INSERT INTO sessions (token, user_id, created_at, expires_at) VALUES (?1, ?2, ?3, ?4)",
+        params![token, user_id, now, expires_at],
+    )?;
+
+    Ok(())
+}
+
+// User of a session that hasn't expired yet
+pub fn get_session_user(
+    conn: &Connection,
+    token: &str,
+    now: i64,
+) -> Result<Option<User>, Box<dyn Error>> {
+    let user = conn
+        .query_row(
+            "SELECT users.id, users.username, users.created_at FROM sessions
+             JOIN users ON users.id = sessions.user_id
+             WHERE sessions.token = ?1 AND sessions.expires_at > ?2",
+            params![token, now],
+            |row| {
+                Ok(User {
+                    id: row.get(0)?,
+                    username: row.get(1)?,
+                    created_at: row.get(2)?,
+                })
+            },
+        )
+        .optional()?;
+
+    Ok(user)
+}
+
+pub fn delete_session(conn: &Connection, token: &str) -> Result<(), Box<dyn Error>> {
+    conn.execute("DELETE FROM sessions WHERE token = ?1", [token])?;
+    Ok(())
+}
+
 pub fn update_app_preferences(
     conn: &mut Connection,
     apps: Vec<Preference>,
@@ -0,0 +1,100 @@
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use tokio::sync::broadcast::{self, error::RecvError, Sender};
+use tuono_lib::axum::response::sse::{Event as SseEvent, KeepAlive, KeepAliveStream, Sse};
+
+use crate::database::ServiceState;
+use crate::system_metrics::SystemMetrics;
+
+// Events missed by a slow client are dropped, it catches up with the next ones
+const CAPACITY: usize = 256;
+
+// Endpoints refreshed every events.live_interval while a client is connected
+pub const LIVE_ENDPOINTS: [(&str, &str); 2] =
+    [("tautulli", "sessions"), ("qbittorrent", "torrents")];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    // A service changed state, message says why a check failed
+    Status {
+        name: String,
+        url: String,
+        status: u16,
+        state: ServiceState,
+        previous: ServiceState,
+        message: Option<String>,
+    },
+    // A refreshed endpoint returned different data, service is the services table name
+    Data {
+        service: String,
+        endpoint: String,
+        data: Value,
+    },
+    Metrics(SystemMetrics),
+}
+
+impl Event {
+    fn name(&self) -> &'static str {
+        match self {
+            Event::Status { .. } => "status",
+            Event::Data { .. } => "data",
+            Event::Metrics(_) => "metrics",
+        }
+    }
+
+    // Services table name the event is about, None for system metrics
+    fn service(&self) -> Option<&str> {
+        match self {
+            Event::Status { name, .. } => Some(name),
+            Event::Data { service, .. } => Some(service),
+            Event::Metrics(_) => None,
+        }
+    }
+}
+
+type Events = Pin<Box<dyn Stream<Item = Result<SseEvent, Infallible>> + Send>>;
+
+pub type EventStream = Sse<KeepAliveStream<Events>>;
+
+static EVENTS: OnceLock<Sender<Event>> = OnceLock::new();
+
+fn sender() -> &'static Sender<Event> {
+    EVENTS.get_or_init(|| broadcast::channel(CAPACITY).0)
+}
+
+// Sends an event to every connected client, a no-op without clients
+pub fn publish(event: Event) {
+    let _ = sender().send(event);
+}
+
+// Number of connected clients, the poller only does live work when there are any
+pub fn subscribers() -> usize {
+    sender().receiver_count()
+}
+
+// SSE stream of the published events about visible services, named by type, e.g. "event: status"
+pub fn event_stream(visible: impl Fn(&str) -> bool + Send + 'static) -> EventStream {
+    let state = (sender().subscribe(), visible);
+    let events = stream::unfold(state, |(mut receiver, visible)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) if !event.service().is_none_or(&visible) => continue,
+                Ok(event) => {
+                    let data = serde_json::to_string(&event).unwrap_or_default();
+                    let event = SseEvent::default().event(event.name()).data(data);
+
+                    return Some((Ok(event), (receiver, visible)));
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(Box::pin(events) as Events).keep_alive(KeepAlive::default())
+}
@@ -1,7 +1,22 @@
+pub mod alerts;
+pub mod auth;
+pub mod cache;
+pub mod certificate;
+pub mod client;
 pub mod config;
 pub mod cookie;
 pub mod database;
+pub mod events;
+pub mod logging;
+pub mod maintenance;
+pub mod monitor;
+pub mod notifier;
 pub mod oauth;
 pub mod ping;
+pub mod poller;
+pub mod prometheus;
+pub mod proxy_listener;
 pub mod services;
+pub mod settings;
 pub mod system_metrics;
+pub mod validation;
@@ -82,8 +82,17 @@ export const truncateString = (text: string): string => {
     return result;
 };
 
-export const getIconURL = (app: string) => {
+// Card title for an instance, the default one keeps the plain title
+export const instanceTitle = (title: string, instance: string): string => {
+    return instance === "default" ? title : `${title} (${instance})`;
+};
+
+// builtIn are the names of the registered services, their extra instances are shown as e.g. "Radarr (4k)"
+export const getIconURL = (name: string, builtIn: string[]) => {
     let cdn = "https://cdn.jsdelivr.net/gh/selfhst/icons/png";
+    // Strip the instance suffix of built-in services, custom ones may have brackets in their name
+    const suffix = name.match(/^(.*?)\s*\(.*\)$/);
+    const app = suffix && builtIn.includes(suffix[1]) ? suffix[1] : name;
 
     switch (app) {
         case "AdGuard":
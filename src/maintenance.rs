@@ -0,0 +1,328 @@
+use chrono::{Datelike, Local, TimeZone, Timelike};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::database::{get_maintenance_windows, get_pauses, MaintenanceWindow};
+
+// Longest scheduled window, keeps the minute by minute lookback cheap
+pub const MAX_DURATION: u32 = 7 * 24 * 60;
+
+// One cron field as the values it matches
+struct Field {
+    values: Vec<u32>,
+    // Starts with "*", matters for the day of month / day of week rule
+    any: bool,
+}
+
+impl Field {
+    fn parse(field: &str, min: u32, max: u32) -> Result<Field, String> {
+        let mut values = Vec::new();
+
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (
+                    range,
+                    step.parse::<u32>()
+                        .ok()
+                        .filter(|step| *step > 0)
+                        .ok_or_else(|| format!("invalid step in {}", part))?,
+                ),
+                None => (part, 1),
+            };
+
+            let number = |value: &str| -> Result<u32, String> {
+                value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|value| (min..=max).contains(value))
+                    .ok_or_else(|| format!("{} is not between {} and {}", value, min, max))
+            };
+            let (start, end) = match range {
+                "*" => (min, max),
+                range => match range.split_once('-') {
+                    Some((start, end)) => (number(start)?, number(end)?),
+                    // "5/15" means from 5 to the end
+                    None if step > 1 => (number(range)?, max),
+                    None => (number(range)?, number(range)?),
+                },
+            };
+            if start > end {
+                return Err(format!("invalid range {}", range));
+            }
+
+            values.extend((start..=end).step_by(step as usize));
+        }
+
+        Ok(Field {
+            values,
+            // Like Vixie cron, "*/2" counts as unrestricted too
+            any: field.starts_with('*'),
+        })
+    }
+
+    fn matches(&self, value: u32) -> bool {
+        self.values.contains(&value)
+    }
+}
+
+// A five field cron expression, "minute hour day-of-month month day-of-week", in local time
+pub struct Schedule {
+    minute: Field,
+    hour: Field,
+    day: Field,
+    month: Field,
+    weekday: Field,
+}
+
+impl Schedule {
+    pub fn parse(expression: &str) -> Result<Schedule, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(String::from(
+                "expected 5 fields: minute hour day-of-month month day-of-week",
+            ));
+        };
+
+        Ok(Schedule {
+            minute: Field::parse(minute, 0, 59)?,
+            hour: Field::parse(hour, 0, 23)?,
+            day: Field::parse(day, 1, 31)?,
+            month: Field::parse(month, 1, 12)?,
+            // 0 and 7 are both Sunday
+            weekday: Field::parse(weekday, 0, 7)?,
+        })
+    }
+
+    // Whether the schedule starts a window in the minute of timestamp
+    fn matches(&self, timestamp: i64) -> bool {
+        let Some(time) = Local.timestamp_opt(timestamp, 0).earliest() else {
+            return false;
+        };
+        let weekday = time.weekday().num_days_from_sunday();
+
+        let day_matches = self.day.matches(time.day());
+        let weekday_matches =
+            self.weekday.matches(weekday) || (weekday == 0 && self.weekday.matches(7));
+        // Like cron, a restricted day of month and day of week match if either does
+        let date_matches = match (self.day.any, self.weekday.any) {
+            (false, false) => day_matches || weekday_matches,
+            _ => day_matches && weekday_matches,
+        };
+
+        date_matches
+            && self.minute.matches(time.minute())
+            && self.hour.matches(time.hour())
+            && self.month.matches(time.month())
+    }
+}
+
+// Checks a window before it's stored
+pub fn validate_window(window: &MaintenanceWindow) -> Result<(), String> {
+    match (
+        &window.schedule,
+        window.duration,
+        window.starts_at,
+        window.ends_at,
+    ) {
+        (Some(schedule), Some(duration), None, None) => {
+            Schedule::parse(schedule)?;
+            if duration == 0 || duration > MAX_DURATION {
+                return Err(format!(
+                    "duration must be between 1 and {} minutes",
+                    MAX_DURATION
+                ));
+            }
+            Ok(())
+        }
+        (None, None, Some(starts_at), Some(ends_at)) => {
+            if ends_at <= starts_at {
+                return Err(String::from("ends_at must be after starts_at"));
+            }
+            Ok(())
+        }
+        _ => Err(String::from(
+            "a window needs either schedule and duration or starts_at and ends_at",
+        )),
+    }
+}
+
+// When the occurrence of window covering now started, None if it isn't active
+pub fn active_since(window: &MaintenanceWindow, now: i64) -> Option<i64> {
+    if let (Some(starts_at), Some(ends_at)) = (window.starts_at, window.ends_at) {
+        return (starts_at..ends_at).contains(&now).then_some(starts_at);
+    }
+
+    let schedule = Schedule::parse(window.schedule.as_deref()?).ok()?;
+    let duration = window.duration?.min(MAX_DURATION) as i64;
+
+    // Earliest minute within the last duration minutes that started a window
+    let minute = now - now.rem_euclid(60);
+    (0..duration)
+        .rev()
+        .map(|ago| minute - ago * 60)
+        .find(|start| schedule.matches(*start))
+}
+
+// When the earliest active window without a service started, None if there is none
+// Such windows also cover instances that aren't pinged and have no services row
+pub fn global_maintenance(conn: &Connection, now: i64) -> Result<Option<i64>, Box<dyn Error>> {
+    Ok(get_maintenance_windows(conn)?
+        .iter()
+        .filter(|window| window.service_id.is_none())
+        .filter_map(|window| active_since(window, now))
+        .min())
+}
+
+// Services in maintenance by name, with when it started
+// Paused services and windows without a service count too
+pub fn services_in_maintenance(
+    conn: &Connection,
+    now: i64,
+) -> Result<HashMap<String, i64>, Box<dyn Error>> {
+    // (service id, start) of every active window
+    let active: Vec<(Option<u32>, i64)> = get_maintenance_windows(conn)?
+        .iter()
+        .filter_map(|window| Some((window.service_id, active_since(window, now)?)))
+        .collect();
+
+    let mut maintenance: HashMap<String, i64> = HashMap::new();
+    for (id, name, paused_since) in get_pauses(conn)? {
+        let since = active
+            .iter()
+            .filter(|(service_id, _)| service_id.is_none_or(|service_id| service_id == id))
+            .map(|(_, since)| *since)
+            .chain(paused_since)
+            .min();
+
+        if let Some(since) = since {
+            maintenance.insert(name, since);
+        }
+    }
+
+    Ok(maintenance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A local time, so the tests don't depend on the timezone
+    fn local(day: u32, month: u32, hour: u32, minute: u32) -> i64 {
+        Local
+            .with_ymd_and_hms(2026, month, day, hour, minute, 0)
+            .unwrap()
+            .timestamp()
+    }
+
+    fn values(field: &str, min: u32, max: u32) -> Vec<u32> {
+        Field::parse(field, min, max).unwrap().values
+    }
+
+    fn recurring(schedule: &str, duration: u32) -> MaintenanceWindow {
+        MaintenanceWindow {
+            id: 1,
+            service_id: None,
+            name: String::from("backup"),
+            starts_at: None,
+            ends_at: None,
+            schedule: Some(String::from(schedule)),
+            duration: Some(duration),
+        }
+    }
+
+    #[test]
+    fn parses_ranges_steps_and_lists() {
+        assert_eq!(values("*", 1, 12), (1..=12).collect::<Vec<_>>());
+        assert_eq!(values("5", 0, 59), vec![5]);
+        assert_eq!(values("1-5", 0, 59), vec![1, 2, 3, 4, 5]);
+        assert_eq!(values("*/15", 0, 59), vec![0, 15, 30, 45]);
+        assert_eq!(values("5/20", 0, 59), vec![5, 25, 45]);
+        assert_eq!(values("10-20/5", 0, 59), vec![10, 15, 20]);
+        assert_eq!(values("1,3,10-12", 1, 31), vec![1, 3, 10, 11, 12]);
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        for field in ["60", "5-1", "*/0", "*/x", "a", "", "1,", "-1"] {
+            assert!(Field::parse(field, 0, 59).is_err(), "{}", field);
+        }
+        assert!(Field::parse("0", 1, 31).is_err());
+        assert!(Schedule::parse("0 3 * *").is_err());
+        assert!(Schedule::parse("0 3 * * * *").is_err());
+        assert!(Schedule::parse("0 24 * * *").is_err());
+        assert!(Schedule::parse("0 3 * * 8").is_err());
+    }
+
+    #[test]
+    fn marks_star_fields_as_any() {
+        assert!(Field::parse("*", 1, 31).unwrap().any);
+        assert!(Field::parse("*/2", 1, 31).unwrap().any);
+        assert!(!Field::parse("1-31", 1, 31).unwrap().any);
+        assert!(!Field::parse("1,*", 1, 31).unwrap().any);
+    }
+
+    #[test]
+    fn matches_sunday_as_0_and_7() {
+        // 2026-06-07 is a Sunday
+        for expression in ["0 3 * * 0", "0 3 * * 7", "0 3 * * 6-7"] {
+            let schedule = Schedule::parse(expression).unwrap();
+            assert!(schedule.matches(local(7, 6, 3, 0)), "{}", expression);
+            assert!(!schedule.matches(local(8, 6, 3, 0)), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn matches_day_of_month_or_day_of_week() {
+        // 2026-06-01, 06-08 and 06-15 are Mondays, 07-01 is a Wednesday
+        let schedule = Schedule::parse("0 3 1 * 1").unwrap();
+        assert!(schedule.matches(local(1, 7, 3, 0)));
+        assert!(schedule.matches(local(8, 6, 3, 0)));
+        assert!(!schedule.matches(local(2, 6, 3, 0)));
+
+        // An unrestricted day of month has to match as well
+        let schedule = Schedule::parse("0 3 */2 * 1").unwrap();
+        assert!(schedule.matches(local(1, 6, 3, 0)));
+        assert!(schedule.matches(local(15, 6, 3, 0)));
+        assert!(!schedule.matches(local(8, 6, 3, 0)));
+        assert!(!schedule.matches(local(3, 6, 3, 0)));
+
+        let schedule = Schedule::parse("0 3 * * 1").unwrap();
+        assert!(schedule.matches(local(8, 6, 3, 0)));
+        assert!(!schedule.matches(local(2, 6, 3, 0)));
+    }
+
+    #[test]
+    fn finds_the_start_of_recurring_windows() {
+        let window = recurring("30 2 * * *", 120);
+        let start = local(2, 6, 2, 30);
+        assert_eq!(active_since(&window, start), Some(start));
+        assert_eq!(active_since(&window, local(2, 6, 4, 29) + 59), Some(start));
+        assert_eq!(active_since(&window, local(2, 6, 4, 30)), None);
+        assert_eq!(active_since(&window, local(2, 6, 2, 29)), None);
+
+        // Overlapping occurrences report the earliest
+        let window = recurring("*/10 2 * * *", 30);
+        assert_eq!(
+            active_since(&window, local(2, 6, 2, 25)),
+            Some(local(2, 6, 2, 0))
+        );
+
+        assert_eq!(active_since(&recurring("not cron", 60), start), None);
+    }
+
+    #[test]
+    fn finds_one_off_windows() {
+        let window = MaintenanceWindow {
+            schedule: None,
+            duration: None,
+            starts_at: Some(1000),
+            ends_at: Some(2000),
+            ..recurring("", 0)
+        };
+        assert_eq!(active_since(&window, 999), None);
+        assert_eq!(active_since(&window, 1000), Some(1000));
+        assert_eq!(active_since(&window, 1999), Some(1000));
+        assert_eq!(active_since(&window, 2000), None);
+    }
+}
@@ -0,0 +1,439 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{self, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{lookup_host, TcpStream, UdpSocket};
+use tokio_native_tls::TlsConnector;
+
+use crate::certificate::{parse_certificate, CertificateInfo};
+use crate::config::{MonitorConfig, MonitorKind};
+
+const DNS_PORT: u16 = 53;
+const RECORD_A: u16 = 1;
+const RECORD_AAAA: u16 = 28;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const MAX_LABEL: usize = 63;
+const MAX_NAME: usize = 255;
+
+// Runs a non-HTTP check, the error says why it failed
+// TLS checks return the peer certificate
+pub async fn check_monitor(monitor: &MonitorConfig) -> Result<Option<CertificateInfo>, String> {
+    match monitor.kind {
+        MonitorKind::Tcp => check_tcp(monitor).await.map(|_| None),
+        MonitorKind::Dns => check_dns(monitor).await.map(|_| None),
+        MonitorKind::Tls => check_tls(monitor).await,
+    }
+}
+
+async fn connect(monitor: &MonitorConfig) -> Result<TcpStream, String> {
+    TcpStream::connect((monitor.host.as_str(), monitor.port))
+        .await
+        .map_err(|e| {
+            format!(
+                "failed to connect to {}:{}: {}",
+                monitor.host, monitor.port, e
+            )
+        })
+}
+
+async fn check_tcp(monitor: &MonitorConfig) -> Result<(), String> {
+    connect(monitor).await.map(|_| ())
+}
+
+async fn check_tls(monitor: &MonitorConfig) -> Result<Option<CertificateInfo>, String> {
+    let stream = connect(monitor).await?;
+
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(!monitor.verify_tls)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let stream = TlsConnector::from(connector)
+        .connect(&monitor.host, stream)
+        .await
+        .map_err(|e| format!("TLS handshake failed: {}", e))?;
+
+    let certificate = stream.get_ref().peer_certificate().ok().flatten();
+    Ok(certificate
+        .and_then(|certificate| certificate.to_der().ok())
+        .and_then(|der| parse_certificate(&der)))
+}
+
+// Accepts "ip", "ip:port", "[ipv6]:port" or a hostname
+async fn resolver_address(resolver: &str) -> Result<SocketAddr, String> {
+    if let Ok(address) = resolver.parse::<SocketAddr>() {
+        return Ok(address);
+    }
+    if let Ok(ip) = resolver.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, DNS_PORT));
+    }
+
+    let host = if resolver.contains(':') {
+        String::from(resolver)
+    } else {
+        format!("{}:{}", resolver, DNS_PORT)
+    };
+
+    let mut addresses = lookup_host(host)
+        .await
+        .map_err(|e| format!("failed to resolve resolver {}: {}", resolver, e))?;
+
+    addresses
+        .next()
+        .ok_or_else(|| format!("resolver {} has no address", resolver))
+}
+
+pub fn record_type(record: &str) -> Option<u16> {
+    match record.to_uppercase().as_str() {
+        "A" => Some(RECORD_A),
+        "AAAA" => Some(RECORD_AAAA),
+        _ => None,
+    }
+}
+
+// Truncated answers are asked again over TCP, where messages are prefixed with their length
+async fn query_tcp(resolver: SocketAddr, id: u16, query: &[u8]) -> Result<Vec<u8>, String> {
+    let mut stream = TcpStream::connect(resolver)
+        .await
+        .map_err(|e| format!("failed to connect to {}: {}", resolver, e))?;
+
+    let mut message = (query.len() as u16).to_be_bytes().to_vec();
+    message.extend_from_slice(query);
+    stream
+        .write_all(&message)
+        .await
+        .map_err(|e| format!("failed to query {}: {}", resolver, e))?;
+
+    let mut length = [0u8; 2];
+    stream
+        .read_exact(&mut length)
+        .await
+        .map_err(|e| format!("no answer from {}: {}", resolver, e))?;
+    let mut response = vec![0u8; u16::from_be_bytes(length) as usize];
+    stream
+        .read_exact(&mut response)
+        .await
+        .map_err(|e| format!("no answer from {}: {}", resolver, e))?;
+
+    if response.get(0..2) != Some(&id.to_be_bytes()[..]) {
+        return Err(format!("{} answered a different query", resolver));
+    }
+
+    Ok(response)
+}
+
+async fn check_dns(monitor: &MonitorConfig) -> Result<(), String> {
+    let qtype = record_type(&monitor.record)
+        .ok_or_else(|| format!("unsupported record type {}", monitor.record))?;
+    let resolver = resolver_address(&monitor.resolver).await?;
+
+    let local: SocketAddr = if resolver.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(local).await.map_err(|e| e.to_string())?;
+    socket.connect(resolver).await.map_err(|e| e.to_string())?;
+
+    let id = SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .map(|now| now.subsec_nanos() as u16)
+        .unwrap_or(0);
+    let query = dns_query(id, &monitor.host, qtype)
+        .map_err(|e| format!("invalid name {}: {}", monitor.host, e))?;
+    socket
+        .send(&query)
+        .await
+        .map_err(|e| format!("failed to query {}: {}", resolver, e))?;
+
+    // Skip stray datagrams that don't answer our query
+    let mut buffer = [0u8; 1500];
+    let length = loop {
+        let length = socket
+            .recv(&mut buffer)
+            .await
+            .map_err(|e| format!("no answer from {}: {}", resolver, e))?;
+        if length >= 2 && u16::from_be_bytes([buffer[0], buffer[1]]) == id {
+            break length;
+        }
+    };
+
+    let addresses = if is_truncated(&buffer[..length]) {
+        dns_answers(&query_tcp(resolver, id, &query).await?, qtype)?
+    } else {
+        dns_answers(&buffer[..length], qtype)?
+    };
+    if addresses.is_empty() {
+        return Err(format!(
+            "{} has no {} records",
+            monitor.host, monitor.record
+        ));
+    }
+
+    let missing: Vec<&String> = monitor
+        .expected
+        .iter()
+        .filter(|expected| {
+            expected
+                .parse::<IpAddr>()
+                .map_or(true, |expected| !addresses.contains(&expected))
+        })
+        .collect();
+    if !missing.is_empty() {
+        let addresses: Vec<String> = addresses.iter().map(IpAddr::to_string).collect();
+        return Err(format!(
+            "{} resolved to {}, expected {}",
+            monitor.host,
+            addresses.join(", "),
+            monitor.expected.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+// Labels of a name to query, rejects names DNS can't carry
+pub fn dns_labels(host: &str) -> Result<Vec<&str>, String> {
+    let name = host.strip_suffix('.').unwrap_or(host);
+    if name.is_empty() {
+        return Err(String::from("is empty"));
+    }
+
+    let labels: Vec<&str> = name.split('.').collect();
+    for label in &labels {
+        if label.is_empty() {
+            return Err(format!("\"{}\" has an empty label", host));
+        }
+        if label.len() > MAX_LABEL {
+            return Err(format!(
+                "label \"{}\" is longer than {} bytes",
+                label, MAX_LABEL
+            ));
+        }
+    }
+
+    // Every label takes a length byte, plus the root label at the end
+    if labels.iter().map(|label| label.len() + 1).sum::<usize>() + 1 > MAX_NAME {
+        return Err(format!("\"{}\" is longer than {} bytes", host, MAX_NAME));
+    }
+
+    Ok(labels)
+}
+
+// Standard recursive query for one name
+fn dns_query(id: u16, host: &str, qtype: u16) -> Result<Vec<u8>, String> {
+    let labels = dns_labels(host)?;
+
+    let mut packet = Vec::with_capacity(32 + host.len());
+    packet.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired, one question
+    packet.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    for label in labels {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+
+    packet.extend_from_slice(&qtype.to_be_bytes());
+    // Class IN
+    packet.extend_from_slice(&1u16.to_be_bytes());
+
+    Ok(packet)
+}
+
+// Returns the offset after a possibly compressed name
+fn skip_name(packet: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let length = *packet.get(offset)?;
+        match length & 0xC0 {
+            // Pointer to a name elsewhere in the packet, which ends this one
+            0xC0 => {
+                packet.get(offset + 1)?;
+                return Some(offset + 2);
+            }
+            0x00 if length == 0 => return Some(offset + 1),
+            0x00 => offset += 1 + length as usize,
+            // 0x40 and 0x80 are extended and reserved label types, not lengths
+            _ => return None,
+        }
+    }
+}
+
+fn is_truncated(packet: &[u8]) -> bool {
+    packet
+        .get(2..4)
+        .is_some_and(|flags| u16::from_be_bytes([flags[0], flags[1]]) & FLAG_TRUNCATED != 0)
+}
+
+// Addresses of the given type in a response, answers like CNAMEs are skipped
+fn dns_answers(packet: &[u8], qtype: u16) -> Result<Vec<IpAddr>, String> {
+    let malformed = || String::from("malformed DNS response");
+    let read_u16 = |offset: usize| -> Option<u16> {
+        Some(u16::from_be_bytes([
+            *packet.get(offset)?,
+            *packet.get(offset + 1)?,
+        ]))
+    };
+
+    let flags = read_u16(2).ok_or_else(malformed)?;
+    // Only part of the answers fit, the rest would silently be missing
+    if flags & FLAG_TRUNCATED != 0 {
+        return Err(String::from("DNS response was truncated"));
+    }
+    match flags & 0x000F {
+        0 => (),
+        3 => return Err(String::from("name does not exist (NXDOMAIN)")),
+        rcode => return Err(format!("resolver returned error code {}", rcode)),
+    }
+
+    let questions = read_u16(4).ok_or_else(malformed)?;
+    let answers = read_u16(6).ok_or_else(malformed)?;
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_name(packet, offset).ok_or_else(malformed)? + 4;
+    }
+
+    let mut addresses = Vec::new();
+    for _ in 0..answers {
+        offset = skip_name(packet, offset).ok_or_else(malformed)?;
+        let rtype = read_u16(offset).ok_or_else(malformed)?;
+        let length = read_u16(offset + 8).ok_or_else(malformed)? as usize;
+        let data = packet
+            .get(offset + 10..offset + 10 + length)
+            .ok_or_else(malformed)?;
+        offset += 10 + length;
+
+        if rtype != qtype {
+            continue;
+        }
+        match (rtype, data.len()) {
+            (RECORD_A, 4) => addresses.push(IpAddr::from(<[u8; 4]>::try_from(data).unwrap())),
+            (RECORD_AAAA, 16) => addresses.push(IpAddr::from(<[u8; 16]>::try_from(data).unwrap())),
+            _ => return Err(malformed()),
+        }
+    }
+
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // example.com A, the answer's name points back at the question
+    const A_RESPONSE: &str = concat!(
+        "123481800001000100000000076578616d706c6503636f6d0000010001",
+        "c00c0001000100000e1000045db8d70e"
+    );
+    // example.com AAAA
+    const AAAA_RESPONSE: &str = concat!(
+        "123481800001000100000000076578616d706c6503636f6d00001c0001",
+        "c00c001c000100000e10001026062800021fcb07682080daaf6b8b2c"
+    );
+    // www.github.com A, a CNAME to github.com and its address
+    const CNAME_RESPONSE: &str = concat!(
+        "123481800001000200000000037777770667697468756203636f6d0000010001",
+        "c00c0005000100000e100002c010",
+        "c010000100010000003c00048c527904"
+    );
+    // www.github.com A answered with NXDOMAIN
+    const NXDOMAIN_RESPONSE: &str =
+        "abcd81830001000000000000037777770667697468756203636f6d0000010001";
+    // example.com A with the TC bit set and the answers left out
+    const TRUNCATED_RESPONSE: &str = "123483800001000000000000076578616d706c6503636f6d0000010001";
+
+    #[test]
+    fn reads_a_records() {
+        let addresses = dns_answers(&packet(A_RESPONSE), RECORD_A).unwrap();
+        assert_eq!(addresses, vec!["93.184.215.14".parse::<IpAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn reads_aaaa_records() {
+        let addresses = dns_answers(&packet(AAAA_RESPONSE), RECORD_AAAA).unwrap();
+        assert_eq!(
+            addresses,
+            vec!["2606:2800:21f:cb07:6820:80da:af6b:8b2c"
+                .parse::<IpAddr>()
+                .unwrap()]
+        );
+    }
+
+    #[test]
+    fn follows_cname_answers() {
+        let addresses = dns_answers(&packet(CNAME_RESPONSE), RECORD_A).unwrap();
+        assert_eq!(addresses, vec!["140.82.121.4".parse::<IpAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn reports_nxdomain() {
+        let error = dns_answers(&packet(NXDOMAIN_RESPONSE), RECORD_A).unwrap_err();
+        assert!(error.contains("NXDOMAIN"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_truncated_responses() {
+        let response = packet(TRUNCATED_RESPONSE);
+        assert!(is_truncated(&response));
+        assert!(!is_truncated(&packet(A_RESPONSE)));
+        assert!(dns_answers(&response, RECORD_A).is_err());
+    }
+
+    #[test]
+    fn rejects_cut_off_responses() {
+        let response = packet(A_RESPONSE);
+        for length in [3, 12, 20, response.len() - 1] {
+            assert!(dns_answers(&response[..length], RECORD_A).is_err());
+        }
+    }
+
+    #[test]
+    fn skips_compressed_names() {
+        let response = packet(CNAME_RESPONSE);
+        // The question's name, then a pointer
+        assert_eq!(skip_name(&response, 12), Some(28));
+        assert_eq!(skip_name(&response, 32), Some(34));
+        // A pointer missing its second byte
+        assert_eq!(skip_name(&response[..33], 32), None);
+    }
+
+    #[test]
+    fn rejects_extended_label_types() {
+        // 0x40 and 0x80 prefixes aren't label lengths
+        assert_eq!(skip_name(&[0x41, b'a', 0x00], 0), None);
+        assert_eq!(skip_name(&[0x81, b'a', 0x00], 0), None);
+        assert_eq!(skip_name(&[0x01, b'a', 0x00], 0), Some(3));
+    }
+
+    #[test]
+    fn builds_queries() {
+        let query = dns_query(0x1234, "example.com.", RECORD_A).unwrap();
+        assert_eq!(
+            query,
+            packet("123401000001000000000000076578616d706c6503636f6d0000010001")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let long_label = "a".repeat(64);
+        assert!(dns_query(1, &format!("{}.lan", long_label), RECORD_A).is_err());
+        assert!(dns_query(1, &format!("{}.lan", &long_label[1..]), RECORD_A).is_ok());
+        assert!(dns_query(1, "nas..lan", RECORD_A).is_err());
+        assert!(dns_query(1, ".lan", RECORD_A).is_err());
+        assert!(dns_query(1, "", RECORD_A).is_err());
+        assert!(dns_query(1, ".", RECORD_A).is_err());
+
+        let long_name = vec!["a".repeat(63); 4].join(".");
+        assert!(dns_labels(&long_name).is_err());
+        assert!(dns_labels(&long_name[2..]).is_ok());
+    }
+}
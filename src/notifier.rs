@@ -0,0 +1,677 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+use serde_json::json;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_native_tls::{TlsConnector, TlsStream};
+
+use crate::client::http_client;
+use crate::config::{ChannelConfig, ChannelKind, ConfigFields, SmtpSecurity};
+
+// Upper bound for one SMTP delivery
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+// What a channel is told, also the body of webhook channels
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub rule: String,
+    // Services table or instance name the alert is about
+    pub service: String,
+    // What exactly fired, e.g. "Proxmox pve/local-lvm"
+    pub subject: String,
+    pub status: AlertStatus,
+    pub message: String,
+    // When the condition started
+    pub since: i64,
+    pub timestamp: i64,
+}
+
+impl Notification {
+    pub fn title(&self) -> String {
+        match self.status {
+            AlertStatus::Firing => format!("{}: {}", self.rule, self.subject),
+            AlertStatus::Resolved => format!("Resolved {}: {}", self.rule, self.subject),
+        }
+    }
+
+    // Message plus when it started, recoveries also say how long it lasted
+    pub fn body(&self) -> String {
+        let since = Local
+            .timestamp_opt(self.since, 0)
+            .earliest()
+            .map(|since| since.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+
+        match self.status {
+            AlertStatus::Firing => format!("{}\nSince {}", self.message, since),
+            AlertStatus::Resolved => format!(
+                "Recovered after {} minutes, since {}\nWas: {}",
+                (self.timestamp - self.since).max(0) / 60,
+                since,
+                self.message
+            ),
+        }
+    }
+}
+
+// Sends a notification to one channel, the error says why it failed
+pub async fn notify(channel: &ChannelConfig, notification: &Notification) -> Result<(), String> {
+    match channel.kind {
+        ChannelKind::Smtp => tokio::time::timeout(SMTP_TIMEOUT, send_mail(channel, notification))
+            .await
+            .map_err(|_| String::from("timed out"))?,
+        _ => send_http(channel, notification)
+            .await
+            .map_err(|e| e.to_string()),
+    }
+}
+
+// Sends a sample alert to the named channel, None if there is no such channel
+pub async fn send_test(config: &ConfigFields, channel: &str) -> Option<Result<(), String>> {
+    let channel = config
+        .alerts
+        .channels
+        .iter()
+        .find(|candidate| candidate.name == channel)?;
+    let now = Local::now().timestamp();
+
+    Some(
+        notify(
+            channel,
+            &Notification {
+                rule: String::from("Test"),
+                service: String::from("homedash-rs"),
+                subject: channel.name.clone(),
+                status: AlertStatus::Firing,
+                message: String::from("This is a test notification from homedash-rs"),
+                since: now,
+                timestamp: now,
+            },
+        )
+        .await,
+    )
+}
+
+async fn send_http(
+    channel: &ChannelConfig,
+    notification: &Notification,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let client = http_client(channel.verify_tls, "")?;
+    let firing = notification.status == AlertStatus::Firing;
+
+    let request = match channel.kind {
+        ChannelKind::Webhook => {
+            let mut request = client.post(&channel.url).json(notification);
+            for (name, value) in &channel.headers {
+                request = request.header(name, value);
+            }
+            request
+        }
+        // Query parameters instead of headers so titles can be UTF-8
+        ChannelKind::Ntfy => {
+            let (tags, priority) = match notification.status {
+                AlertStatus::Firing => ("warning", "high"),
+                AlertStatus::Resolved => ("white_check_mark", "default"),
+            };
+            let request = client
+                .post(&channel.url)
+                .query(&[
+                    ("title", notification.title().as_str()),
+                    ("tags", tags),
+                    ("priority", priority),
+                ])
+                .body(notification.body());
+            if channel.token.is_empty() {
+                request
+            } else {
+                request.bearer_auth(&channel.token)
+            }
+        }
+        ChannelKind::Gotify => client
+            .post(format!("{}/message", channel.url.trim_end_matches('/')))
+            .header("X-Gotify-Key", &channel.token)
+            .json(&json!({
+                "title": notification.title(),
+                "message": notification.body(),
+                "priority": if firing { 8 } else { 4 },
+            })),
+        ChannelKind::Discord => client.post(&channel.url).json(&json!({
+            "content": format!("**{}**\n{}", notification.title(), notification.body()),
+        })),
+        ChannelKind::Slack => client.post(&channel.url).json(&json!({
+            "text": format!("*{}*\n{}", notification.title(), notification.body()),
+        })),
+        ChannelKind::Smtp => unreachable!("SMTP channels are sent by send_mail"),
+    };
+
+    request.send().await?.error_for_status()?;
+
+    Ok(())
+}
+
+// One SMTP conversation, replies are read until the line without a continuation dash
+struct Smtp<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Smtp<S> {
+    fn new(stream: S) -> Self {
+        Smtp {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    async fn reply(&mut self, expected: u16) -> Result<String, String> {
+        let mut reply = String::new();
+
+        loop {
+            let mut line = String::new();
+            let read = self
+                .stream
+                .read_line(&mut line)
+                .await
+                .map_err(|e| e.to_string())?;
+            if read == 0 {
+                return Err(String::from("connection closed by server"));
+            }
+            reply.push_str(&line);
+
+            // "250-SIZE" continues, "250 OK" ends the reply
+            if line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+        }
+
+        let code = reply.get(0..3).and_then(|code| code.parse::<u16>().ok());
+        if code != Some(expected) {
+            return Err(format!("unexpected reply: {}", reply.trim_end()));
+        }
+
+        Ok(reply)
+    }
+
+    async fn command(&mut self, command: &str, expected: u16) -> Result<String, String> {
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        stream.flush().await.map_err(|e| e.to_string())?;
+
+        self.reply(expected).await
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+}
+
+async fn start_tls<S: AsyncRead + AsyncWrite + Unpin>(
+    channel: &ChannelConfig,
+    stream: S,
+) -> Result<TlsStream<S>, String> {
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(!channel.verify_tls)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    TlsConnector::from(connector)
+        .connect(&channel.host, stream)
+        .await
+        .map_err(|e| format!("TLS handshake failed: {}", e))
+}
+
+async fn send_mail(channel: &ChannelConfig, notification: &Notification) -> Result<(), String> {
+    let stream = TcpStream::connect((channel.host.as_str(), channel.port))
+        .await
+        .map_err(|e| {
+            format!(
+                "failed to connect to {}:{}: {}",
+                channel.host, channel.port, e
+            )
+        })?;
+    let ehlo = "EHLO homedash-rs";
+
+    match channel.security {
+        SmtpSecurity::Tls => {
+            let mut smtp = Smtp::new(start_tls(channel, stream).await?);
+            smtp.reply(220).await?;
+            smtp.command(ehlo, 250).await?;
+            deliver(&mut smtp, channel, notification).await
+        }
+        SmtpSecurity::Starttls => {
+            let mut smtp = Smtp::new(stream);
+            smtp.reply(220).await?;
+            smtp.command(ehlo, 250).await?;
+            smtp.command("STARTTLS", 220).await?;
+
+            let mut smtp = Smtp::new(start_tls(channel, smtp.into_inner()).await?);
+            smtp.command(ehlo, 250).await?;
+            deliver(&mut smtp, channel, notification).await
+        }
+        SmtpSecurity::None => {
+            let mut smtp = Smtp::new(stream);
+            smtp.reply(220).await?;
+            smtp.command(ehlo, 250).await?;
+            deliver(&mut smtp, channel, notification).await
+        }
+    }
+}
+
+// Non-ASCII subjects are sent as RFC 2047 encoded words
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        return String::from(value);
+    }
+
+    format!("=?utf-8?B?{}?=", STANDARD.encode(value))
+}
+
+async fn deliver<S: AsyncRead + AsyncWrite + Unpin>(
+    smtp: &mut Smtp<S>,
+    channel: &ChannelConfig,
+    notification: &Notification,
+) -> Result<(), String> {
+    if !channel.username.is_empty() {
+        // AUTH PLAIN is only base64, never send it over an unencrypted connection
+        if channel.security == SmtpSecurity::None {
+            return Err(String::from(
+                "refusing to send credentials without TLS, set security to starttls or tls",
+            ));
+        }
+        let credentials = format!("\0{}\0{}", channel.username, channel.password);
+        smtp.command(&format!("AUTH PLAIN {}", STANDARD.encode(credentials)), 235)
+            .await?;
+    }
+
+    smtp.command(&format!("MAIL FROM:<{}>", channel.from), 250)
+        .await?;
+    for to in &channel.to {
+        smtp.command(&format!("RCPT TO:<{}>", to), 250).await?;
+    }
+    smtp.command("DATA", 354).await?;
+
+    // Lines starting with a dot are escaped, a lone dot ends the message
+    let body: Vec<String> = notification
+        .body()
+        .lines()
+        .map(|line| {
+            if line.starts_with('.') {
+                format!(".{}", line)
+            } else {
+                String::from(line)
+            }
+        })
+        .collect();
+    let message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}\r\n.",
+        channel.from,
+        channel.to.join(", "),
+        encode_header(&notification.title()),
+        Local::now().to_rfc2822(),
+        body.join("\r\n")
+    );
+    smtp.command(&message, 250).await?;
+    smtp.command("QUIT", 221).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+    use tokio::task::JoinHandle;
+
+    // One request as seen by the listener, header names are lowercase
+    pub struct Received {
+        pub target: String,
+        pub headers: Vec<(String, String)>,
+        pub body: String,
+    }
+
+    impl Received {
+        pub fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(header, _)| header == name)
+                .map(|(_, value)| value.as_str())
+        }
+
+        pub fn json(&self) -> serde_json::Value {
+            serde_json::from_str(&self.body).unwrap()
+        }
+    }
+
+    // An HTTP server on a free port answering every request with status, returns its URL
+    pub async fn listen(status: u16) -> (String, UnboundedReceiver<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let mut stream = BufReader::new(stream);
+
+                let mut request_line = String::new();
+                stream.read_line(&mut request_line).await.unwrap();
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).await.unwrap();
+                    let Some((name, value)) = line.trim_end().split_once(':') else {
+                        break;
+                    };
+                    headers.push((name.to_lowercase(), String::from(value.trim())));
+                }
+
+                let length = headers
+                    .iter()
+                    .find(|(name, _)| name == "content-length")
+                    .map_or(0, |(_, value)| value.parse().unwrap());
+                let mut body = vec![0; length];
+                stream.read_exact(&mut body).await.unwrap();
+
+                let target = request_line.split(' ').nth(1).unwrap_or_default();
+                let _ = sender.send(Received {
+                    target: String::from(target),
+                    headers,
+                    body: String::from_utf8(body).unwrap(),
+                });
+
+                let response = format!(
+                    "HTTP/1.1 {} Test\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                let _ = stream.get_mut().write_all(response.as_bytes()).await;
+            }
+        });
+
+        (url, receiver)
+    }
+
+    pub fn channel(toml: &str) -> ChannelConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn sample(status: AlertStatus) -> Notification {
+        Notification {
+            rule: String::from("Service down"),
+            service: String::from("nas"),
+            subject: String::from("nas"),
+            status,
+            message: String::from("nas is down since 14:02: connection refused"),
+            since: 1_700_000_000,
+            timestamp: 1_700_000_600,
+        }
+    }
+
+    #[tokio::test]
+    async fn posts_webhooks_as_json() {
+        let (url, mut requests) = listen(200).await;
+        let channel = channel(&format!(
+            "name = \"hook\"\ntype = \"webhook\"\nurl = \"{}/alert\"\nheaders = {{ X-Token = \"abc\" }}",
+            url
+        ));
+        let notification = sample(AlertStatus::Firing);
+
+        notify(&channel, &notification).await.unwrap();
+
+        let request = requests.recv().await.unwrap();
+        assert_eq!(request.target, "/alert");
+        assert_eq!(request.header("x-token"), Some("abc"));
+        assert_eq!(request.json(), serde_json::to_value(&notification).unwrap());
+        assert_eq!(request.json()["status"], "firing");
+    }
+
+    #[tokio::test]
+    async fn publishes_to_ntfy() {
+        let (url, mut requests) = listen(200).await;
+        let channel = channel(&format!(
+            "name = \"phone\"\ntype = \"ntfy\"\nurl = \"{}/homelab\"\ntoken = \"tk_1\"",
+            url
+        ));
+        let notification = sample(AlertStatus::Firing);
+
+        notify(&channel, &notification).await.unwrap();
+
+        let request = requests.recv().await.unwrap();
+        assert_eq!(
+            request.target,
+            "/homelab?title=Service+down%3A+nas&tags=warning&priority=high"
+        );
+        assert_eq!(request.header("authorization"), Some("Bearer tk_1"));
+        assert_eq!(request.body, notification.body());
+    }
+
+    #[tokio::test]
+    async fn sends_gotify_messages() {
+        let (url, mut requests) = listen(200).await;
+        let channel = channel(&format!(
+            "name = \"gotify\"\ntype = \"gotify\"\nurl = \"{}/\"\ntoken = \"app\"",
+            url
+        ));
+        let notification = sample(AlertStatus::Resolved);
+
+        notify(&channel, &notification).await.unwrap();
+
+        let request = requests.recv().await.unwrap();
+        assert_eq!(request.target, "/message");
+        assert_eq!(request.header("x-gotify-key"), Some("app"));
+        assert_eq!(
+            request.json(),
+            json!({
+                "title": "Resolved Service down: nas",
+                "message": notification.body(),
+                "priority": 4,
+            })
+        );
+        assert!(notification
+            .body()
+            .starts_with("Recovered after 10 minutes"));
+    }
+
+    #[tokio::test]
+    async fn formats_discord_and_slack_messages() {
+        let (url, mut requests) = listen(204).await;
+        let notification = sample(AlertStatus::Firing);
+
+        let discord = channel(&format!(
+            "name = \"d\"\ntype = \"discord\"\nurl = \"{}\"",
+            url
+        ));
+        notify(&discord, &notification).await.unwrap();
+        assert_eq!(
+            requests.recv().await.unwrap().json(),
+            json!({ "content": format!("**Service down: nas**\n{}", notification.body()) })
+        );
+
+        let slack = channel(&format!(
+            "name = \"s\"\ntype = \"slack\"\nurl = \"{}\"",
+            url
+        ));
+        notify(&slack, &notification).await.unwrap();
+        assert_eq!(
+            requests.recv().await.unwrap().json(),
+            json!({ "text": format!("*Service down: nas*\n{}", notification.body()) })
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_on_error_statuses() {
+        let (url, mut requests) = listen(500).await;
+        let channel = channel(&format!(
+            "name = \"hook\"\ntype = \"webhook\"\nurl = \"{}\"",
+            url
+        ));
+
+        assert!(notify(&channel, &sample(AlertStatus::Firing))
+            .await
+            .is_err());
+        assert!(requests.recv().await.is_some());
+    }
+
+    // A mail server that rejects STARTTLS, returns its port and every line it received
+    async fn smtp_server() -> (u16, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut received = Vec::new();
+            let mut data = false;
+
+            stream
+                .get_mut()
+                .write_all(b"220 mail.test ESMTP\r\n")
+                .await
+                .unwrap();
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let line = String::from(line.trim_end_matches("\r\n"));
+                received.push(line.clone());
+
+                let reply: &[u8] = match line.as_str() {
+                    "." if data => {
+                        data = false;
+                        b"250 2.0.0 queued\r\n"
+                    }
+                    _ if data => continue,
+                    "DATA" => {
+                        data = true;
+                        b"354 end with .\r\n"
+                    }
+                    "EHLO homedash-rs" => {
+                        b"250-mail.test\r\n250-SIZE 10240000\r\n250-STARTTLS\r\n250 AUTH PLAIN\r\n"
+                    }
+                    "STARTTLS" => b"454 4.7.0 TLS not available\r\n",
+                    "QUIT" => b"221 bye\r\n",
+                    line if line.starts_with("AUTH PLAIN ") => b"235 authenticated\r\n",
+                    _ => b"250 OK\r\n",
+                };
+                stream.get_mut().write_all(reply).await.unwrap();
+            }
+
+            received
+        });
+
+        (port, server)
+    }
+
+    fn smtp_channel(port: u16, extra: &str) -> ChannelConfig {
+        channel(&format!(
+            "name = \"mail\"\ntype = \"smtp\"\nhost = \"127.0.0.1\"\nport = {}\nfrom = \"homedash@example.com\"\nto = [\"me@example.com\", \"you@example.com\"]\n{}",
+            port, extra
+        ))
+    }
+
+    #[tokio::test]
+    async fn sends_mail_with_dot_stuffing() {
+        let (port, server) = smtp_server().await;
+        let channel = smtp_channel(port, "security = \"none\"");
+        let mut notification = sample(AlertStatus::Firing);
+        notification.message = String::from("first\n.hidden\n.");
+
+        notify(&channel, &notification).await.unwrap();
+
+        let received = server.await.unwrap();
+        let data = received.iter().position(|line| line == "DATA").unwrap();
+        assert_eq!(
+            received[..data],
+            [
+                "EHLO homedash-rs",
+                "MAIL FROM:<homedash@example.com>",
+                "RCPT TO:<me@example.com>",
+                "RCPT TO:<you@example.com>",
+            ]
+        );
+        assert!(received.contains(&String::from("Subject: Service down: nas")));
+        assert!(received.contains(&String::from("To: me@example.com, you@example.com")));
+
+        let body = received.iter().position(|line| line.is_empty()).unwrap();
+        assert_eq!(received[body + 1..body + 4], ["first", "..hidden", ".."]);
+        assert_eq!(received[received.len() - 2..], [".", "QUIT"]);
+    }
+
+    #[tokio::test]
+    async fn stops_when_starttls_is_rejected() {
+        let (port, server) = smtp_server().await;
+        let channel = smtp_channel(
+            port,
+            "security = \"starttls\"\nusername = \"homedash\"\npassword = \"secret\"",
+        );
+
+        let error = notify(&channel, &sample(AlertStatus::Firing))
+            .await
+            .unwrap_err();
+        assert!(error.contains("454"), "{}", error);
+
+        let received = server.await.unwrap();
+        assert_eq!(received, ["EHLO homedash-rs", "STARTTLS"]);
+    }
+
+    #[tokio::test]
+    async fn refuses_credentials_without_tls() {
+        let (port, server) = smtp_server().await;
+        let channel = smtp_channel(
+            port,
+            "security = \"none\"\nusername = \"homedash\"\npassword = \"secret\"",
+        );
+
+        let error = notify(&channel, &sample(AlertStatus::Firing))
+            .await
+            .unwrap_err();
+        assert!(error.contains("without TLS"), "{}", error);
+
+        let received = server.await.unwrap();
+        assert!(received.iter().all(|line| !line.starts_with("AUTH")));
+    }
+
+    #[tokio::test]
+    async fn reads_multiline_replies() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut smtp = Smtp::new(client);
+
+        server
+            .write_all(b"250-mail.test\r\n250-SIZE 10240000\r\n250 8BITMIME\r\n")
+            .await
+            .unwrap();
+        let reply = smtp.reply(250).await.unwrap();
+        assert_eq!(reply.lines().count(), 3);
+
+        server
+            .write_all(b"550 5.1.1 no such user\r\n")
+            .await
+            .unwrap();
+        let error = smtp.reply(250).await.unwrap_err();
+        assert!(error.contains("550 5.1.1"), "{}", error);
+
+        server.write_all(b"250-mail.test\r\n").await.unwrap();
+        drop(server);
+        assert!(smtp.reply(250).await.is_err());
+    }
+
+    #[test]
+    fn encodes_non_ascii_subjects() {
+        assert_eq!(encode_header("Storage: pve"), "Storage: pve");
+        assert_eq!(
+            encode_header("Speicher: Größe"),
+            "=?utf-8?B?U3BlaWNoZXI6IEdyw7bDn2U=?="
+        );
+    }
+}
@@ -1,12 +1,12 @@
-use reqwest::Client;
 use serde::Serialize;
 use serde_derive::Deserialize;
-use std::{
-    error::Error,
-    time::{self, SystemTime},
-};
+use std::time::{self, SystemTime};
+use tracing::{debug, info};
 
-use crate::config::{write_oauth_config, APIOauthConfig, OauthValues};
+use crate::client::default_client;
+use crate::config::APIOauthConfig;
+use crate::database::{connect_db, get_cached_credential, set_cached_credential, CachedCredential};
+use crate::services::{json, ServiceError};
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
@@ -14,6 +14,14 @@ pub struct OAuthFields {
     pub request: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[allow(unused)]
+pub struct OauthValues {
+    pub service: String,
+    pub token: String,
+    pub expires_in: u64,
+}
+
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[allow(unused)]
 struct TvDbBody {
@@ -30,22 +38,26 @@ struct TvDbResponse {
 pub async fn retrieve_oauth(
     credentials: &APIOauthConfig,
     fields: OAuthFields,
-) -> Result<OauthValues, Box<dyn Error>> {
-    let client = Client::new();
+) -> Result<OauthValues, ServiceError> {
+    let client = default_client()?;
     let date_now = SystemTime::now()
         .duration_since(time::UNIX_EPOCH)
         .unwrap()
         .as_secs();
 
     if Some(String::from("tvdb")) == fields.request && credentials.enabled {
+        let conn = connect_db()?;
+        let cached = get_cached_credential(&conn, "tvdb", "").ok();
+        conn.close().unwrap();
+
         let mut values = OauthValues {
             service: String::from("tvdb"),
-            token: credentials.token.clone(),
-            expires_in: credentials.expires_in.clone(),
+            token: cached.clone().map(|c| c.value).unwrap_or_default(),
+            expires_in: cached.map(|c| c.expires as u64).unwrap_or_default(),
         };
 
-        if date_now > credentials.expires_in {
-            println!("Refreshing tvdb token");
+        if date_now > values.expires_in {
+            debug!("Refreshing tvdb token");
 
             let response = client
                 .post("https://api4.thetvdb.com/v4/login")
@@ -53,20 +65,26 @@ pub async fn retrieve_oauth(
                     "apikey": credentials.api_key,
                 }))
                 .send()
-                .await
-                .unwrap();
-
-            if !response.status().is_success() {
-                return Err(Box::from("Failed to refresh tvdb token"));
-            }
+                .await?
+                .error_for_status()?;
 
-            let token_info: TvDbResponse = response.json().await?;
+            let token_info = json::<TvDbResponse>(response).await?;
             values.token = token_info.data.token;
             values.expires_in = date_now + 30 * 24 * 60 * 60;
 
-            write_oauth_config(&values).unwrap();
+            let conn = connect_db()?;
+            set_cached_credential(
+                &conn,
+                &CachedCredential {
+                    service: values.service.clone(),
+                    instance: String::new(),
+                    value: values.token.clone(),
+                    expires: values.expires_in as i64,
+                },
+            )?;
+            conn.close().unwrap();
 
-            println!("Refreshed tvdb token");
+            info!("Refreshed tvdb token");
         }
 
         return Ok(values);
@@ -74,5 +92,5 @@ pub async fn retrieve_oauth(
 
     // Can add more oauth service logins here in the future
 
-    Err(Box::from("No oauth service requested"))
+    Err(ServiceError::Disabled)
 }
@@ -1,136 +1,719 @@
+use futures_util::stream::{self, StreamExt};
+use regex::Regex;
+use reqwest::tls::TlsInfo;
+use reqwest::{Client, Method, Response};
+use rusqlite::{params, Connection};
+use serde_json::Value;
+use std::collections::HashMap;
 use std::error::Error;
-use std::sync::mpsc;
-use std::sync::Arc;
-use std::sync::Mutex;
-use std::thread;
+use std::ops::RangeInclusive;
 use std::time;
 use std::time::Duration;
 use std::time::Instant;
 use std::time::SystemTime;
+use tracing::{debug, info, warn};
 
-use rusqlite::Connection;
+use crate::certificate::{parse_certificate, CertificateInfo};
+use crate::client::ping_client;
+use crate::config::{instance_display_name, ConfigFields, HealthCheck, MonitorConfig, PingConfig};
+use crate::database::{
+    compact_ping_history, connect_db, get_service_status, insert_ping_history,
+    insert_state_transition, PingCheck, ServiceState, StateTransition,
+};
+use crate::events::{publish, Event};
+use crate::maintenance::services_in_maintenance;
+use crate::monitor::check_monitor;
+use crate::services::registry::SERVICES;
 
-use crate::config::ConfigFields;
+// How a target is checked
+pub enum Probe {
+    Http(HealthCheck),
+    Monitor(MonitorConfig),
+}
+
+// A row in the services table and how to check it
+pub struct Target {
+    pub name: String,
+    pub url: String,
+    pub probe: Probe,
+}
+
+pub struct CheckResult {
+    // HTTP status, 0 if there was no response
+    pub status: u16,
+    pub up: bool,
+    // Why the check failed
+    pub message: Option<String>,
+    // Peer certificate of HTTPS and TLS checks
+    pub certificate: Option<CertificateInfo>,
+    // Milliseconds until the response headers or the connection, None without one
+    pub latency: Option<u64>,
+}
+
+impl CheckResult {
+    fn up(status: u16) -> Self {
+        CheckResult {
+            status,
+            up: true,
+            message: None,
+            certificate: None,
+            latency: None,
+        }
+    }
+
+    fn down(status: u16, message: String) -> Self {
+        CheckResult {
+            status,
+            up: false,
+            message: Some(message),
+            certificate: None,
+            latency: None,
+        }
+    }
+}
 
-pub fn ping_all_urls(config: &ConfigFields, conn: &Connection) -> Result<(), Box<dyn Error>> {
+// Every enabled service instance, HTTP URL and monitor, in the same order as configured_services
+pub fn targets(config: &ConfigFields) -> Vec<Target> {
+    let mut targets = Vec::new();
+
+    for service in SERVICES.iter().filter(|service| service.pinged()) {
+        for instance in service.instances(config) {
+            if instance.enabled() {
+                targets.push(Target {
+                    name: instance_display_name(service.name(), instance.name()),
+                    url: String::from(instance.url()),
+                    probe: Probe::Http(instance.check().clone()),
+                });
+            }
+        }
+    }
+
+    if config.http.enabled {
+        for url in &config.http.urls {
+            targets.push(Target {
+                name: url.name.clone(),
+                url: url.url.clone(),
+                probe: Probe::Http(url.check.clone()),
+            });
+        }
+    }
+
+    for monitor in &config.monitors {
+        targets.push(Target {
+            name: monitor.name.clone(),
+            url: monitor.url(),
+            probe: Probe::Monitor(monitor.clone()),
+        });
+    }
+
+    targets
+}
+
+// Parses expected_status entries like "200", "200-299" or "200-299,301"
+pub fn status_ranges(expected: &[String]) -> Result<Vec<RangeInclusive<u16>>, String> {
+    expected
+        .iter()
+        .flat_map(|entry| entry.split(','))
+        .map(|entry| {
+            let (start, end) = entry.split_once('-').unwrap_or((entry, entry));
+            let parse = |code: &str| {
+                code.trim()
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|code| (100..=599).contains(code))
+                    .ok_or_else(|| format!("\"{}\" is not a status code or range", entry.trim()))
+            };
+
+            let (start, end) = (parse(start)?, parse(end)?);
+            if start > end {
+                return Err(format!("\"{}\" is an empty range", entry.trim()));
+            }
+            Ok(start..=end)
+        })
+        .collect()
+}
+
+// Follows a dotted path like "data.0.status" into a JSON value, a leading "$." is allowed
+fn json_lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let path = path.strip_prefix("$.").unwrap_or(path);
+
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(value, |value, key| match value {
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => value.get(key),
+        })
+}
+
+// Whether the last ping is older than interval seconds
+pub fn ping_due(conn: &Connection, interval: u64) -> bool {
     let current_time: i64 = SystemTime::now()
         .duration_since(time::UNIX_EPOCH)
         .unwrap()
         .as_secs() as i64;
 
-    // Check if last ping was within 5 minutes
     let last_check: i64 = conn
         .query_row("SELECT MAX(last_check) FROM services", [], |row| row.get(0))
         .unwrap_or(0);
 
-    if current_time - last_check < 300 {
-        // 5 minutes = 300 seconds
-        println!("Skipping ping check - last check was less than 5 minutes ago");
-        return Ok(());
-    }
+    current_time - last_check >= interval as i64
+}
 
-    let last_check: i64 = current_time;
-    let results = Arc::new(Mutex::new(Vec::new()));
-    let mut urls = vec![];
+// Checks every configured service, at most ping.concurrency at a time
+// Dropping the future cancels the checks still running, nothing is written then
+pub async fn ping_all_urls(config: &ConfigFields) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let last_check: i64 = SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
 
-    if config.sonarr.enabled {
-        urls.push(&config.sonarr.url);
-    }
-    if config.radarr.enabled {
-        urls.push(&config.radarr.url);
+    let start_time = Instant::now();
+
+    // Services in maintenance aren't checked, so they don't count against uptime
+    let maintenance = connect_db()
+        .and_then(|conn| {
+            let maintenance = services_in_maintenance(&conn, last_check)?;
+            conn.close().map_err(|(_, e)| e)?;
+            Ok(maintenance)
+        })
+        .map_err(|e| e.to_string())?;
+    let (paused, checked) = split_paused(targets(config), &maintenance);
+
+    let results: Vec<(Target, CheckResult)> = stream::iter(checked)
+        .map(|target| async move {
+            let result = check_with_retries(&target, &config.ping).await;
+
+            match &result.message {
+                None => debug!(
+                    service = %target.name,
+                    status = result.status,
+                    "Ping Success for {}",
+                    target.url
+                ),
+                Some(message) => warn!(
+                    service = %target.name,
+                    status = result.status,
+                    "Ping Error for {}: {}",
+                    target.url,
+                    message
+                ),
+            }
+
+            (target, result)
+        })
+        .buffer_unordered(config.ping.concurrency.max(1))
+        .collect()
+        .await;
+
+    save_results(&config.ping, &results, &paused, &maintenance, last_check)
+        .map_err(|e| e.to_string())?;
+
+    let duration = start_time.elapsed();
+    info!(
+        checked = results.len(),
+        paused = paused.len(),
+        "Total time to ping all URLs: {:?}",
+        duration
+    );
+
+    Ok(())
+}
+
+// A failed check is retried after a short delay before it counts as a failure
+async fn check_with_retries(target: &Target, config: &PingConfig) -> CheckResult {
+    let mut result = check_url(target, config.timeout).await;
+    for _ in 0..config.retries {
+        if result.up {
+            break;
+        }
+        tokio::time::sleep(Duration::from_secs(config.retry_delay)).await;
+        result = check_url(target, config.timeout).await;
     }
-    if config.prowlarr.enabled {
-        urls.push(&config.prowlarr.url);
+
+    result
+}
+
+async fn check_url(target: &Target, default_timeout: u64) -> CheckResult {
+    let check = match &target.probe {
+        Probe::Http(check) => check,
+        Probe::Monitor(monitor) => return check_monitor_target(monitor, default_timeout).await,
+    };
+    let timeout = match check.timeout {
+        0 => default_timeout,
+        timeout => timeout,
+    };
+
+    let client = match ping_client(check.follow_redirects) {
+        Ok(client) => client,
+        Err(e) => return CheckResult::down(0, e.to_string()),
+    };
+
+    match send(&client, &target.url, check, Duration::from_secs(timeout)).await {
+        Ok(result) => result,
+        Err(e) => CheckResult::down(0, e.to_string()),
     }
-    if config.overseerr.enabled {
-        urls.push(&config.overseerr.url);
+}
+
+// Non-HTTP checks have no status code
+async fn check_monitor_target(monitor: &MonitorConfig, default_timeout: u64) -> CheckResult {
+    let timeout = match monitor.timeout {
+        0 => default_timeout,
+        timeout => timeout,
+    };
+
+    let started = Instant::now();
+
+    match tokio::time::timeout(Duration::from_secs(timeout), check_monitor(monitor)).await {
+        Ok(Ok(certificate)) => CheckResult {
+            certificate,
+            latency: Some(started.elapsed().as_millis() as u64),
+            ..CheckResult::up(0)
+        },
+        Ok(Err(e)) => CheckResult::down(0, e),
+        Err(_) => CheckResult::down(0, format!("timed out after {}s", timeout)),
     }
-    if config.qbittorrent.enabled {
-        urls.push(&config.qbittorrent.url);
+}
+
+async fn send(
+    client: &Client,
+    url: &str,
+    check: &HealthCheck,
+    timeout: Duration,
+) -> Result<CheckResult, Box<dyn Error + Send + Sync>> {
+    let method = Method::from_bytes(check.method.to_uppercase().as_bytes())?;
+
+    let mut request = client.request(method, url).timeout(timeout);
+    for (name, value) in &check.headers {
+        request = request.header(name, value);
     }
-    if config.plex.enabled {
-        urls.push(&config.plex.url);
+    if !check.username.is_empty() {
+        request = request.basic_auth(&check.username, Some(&check.password));
     }
-    if config.tautulli.enabled {
-        urls.push(&config.tautulli.url);
+
+    let started = Instant::now();
+    let response = request.send().await?;
+    let latency = started.elapsed().as_millis() as u64;
+    let certificate = response
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(TlsInfo::peer_certificate)
+        .and_then(parse_certificate);
+
+    let result = evaluate(response, check).await?;
+
+    Ok(CheckResult {
+        certificate,
+        latency: Some(latency),
+        ..result
+    })
+}
+
+async fn evaluate(
+    response: Response,
+    check: &HealthCheck,
+) -> Result<CheckResult, Box<dyn Error + Send + Sync>> {
+    let status = response.status().as_u16();
+
+    if !status_ranges(&check.expected_status)?
+        .iter()
+        .any(|range| range.contains(&status))
+    {
+        return Ok(CheckResult::down(
+            status,
+            format!("unexpected status {}", status),
+        ));
     }
-    if config.proxmox.enabled {
-        urls.push(&config.proxmox.url);
+
+    if check.body.is_empty() && check.body_regex.is_empty() && check.json_path.is_empty() {
+        return Ok(CheckResult::up(status));
     }
-    if config.adguard.enabled {
-        urls.push(&config.adguard.url);
+
+    let body = response.text().await?;
+
+    if !check.body.is_empty() && !body.contains(&check.body) {
+        return Ok(CheckResult::down(
+            status,
+            format!("body doesn't contain \"{}\"", check.body),
+        ));
     }
-    if config.dockwatch.enabled {
-        urls.push(&config.dockwatch.url);
+
+    if !check.body_regex.is_empty() && !Regex::new(&check.body_regex)?.is_match(&body) {
+        return Ok(CheckResult::down(
+            status,
+            format!("body doesn't match /{}/", check.body_regex),
+        ));
     }
-    if config.http.enabled {
-        for url in &config.http.urls {
-            urls.push(&url.url);
+
+    if !check.json_path.is_empty() {
+        let json: Value = serde_json::from_str(&body)?;
+
+        let value = match json_lookup(&json, &check.json_path) {
+            Some(Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => {
+                return Ok(CheckResult::down(
+                    status,
+                    format!("no value at {}", check.json_path),
+                ))
+            }
+        };
+
+        if !check.json_value.is_empty() && value != check.json_value {
+            return Ok(CheckResult::down(
+                status,
+                format!(
+                    "{} is \"{}\", expected \"{}\"",
+                    check.json_path, value, check.json_value
+                ),
+            ));
         }
     }
 
-    let mut handles = vec![];
+    Ok(CheckResult::up(status))
+}
 
-    let start_time = Instant::now();
+// Targets in maintenance and the ones to check
+fn split_paused(
+    targets: Vec<Target>,
+    maintenance: &HashMap<String, i64>,
+) -> (Vec<Target>, Vec<Target>) {
+    targets
+        .into_iter()
+        .partition(|target| maintenance.contains_key(&target.name))
+}
 
-    for url in urls {
-        let results_clone = Arc::clone(&results);
-        let url_clone = url.clone();
-        let handle = thread::spawn(move || match ping_url(&url_clone) {
-            Ok(status) => {
-                results_clone
-                    .lock()
-                    .unwrap()
-                    .push((url_clone.clone(), status));
-                println!("Ping Success for {}: Status Code {}", url_clone, status)
-            }
-            Err(e) => {
-                results_clone.lock().unwrap().push((url_clone.clone(), 503));
-                println!("Ping Error for {}: {}", url_clone, e)
-            }
+// Failures have to repeat failure_threshold times before a service is down,
+// passes recovery_threshold times before a degraded or down service is up again
+fn next_state(
+    current: ServiceState,
+    passed: bool,
+    failures: u32,
+    successes: u32,
+    config: &PingConfig,
+) -> ServiceState {
+    // Coming out of maintenance the service starts over like it was never checked
+    let current = match current {
+        ServiceState::Maintenance => ServiceState::Unknown,
+        state => state,
+    };
+
+    match (current, passed) {
+        (ServiceState::Unknown | ServiceState::Up, true) => ServiceState::Up,
+        (_, true) if successes >= config.recovery_threshold => ServiceState::Up,
+        (current, true) => current,
+        (_, false) if failures >= config.failure_threshold => ServiceState::Down,
+        (ServiceState::Down, false) => ServiceState::Down,
+        (_, false) => ServiceState::Degraded,
+    }
+}
+
+// Stores a state change and queues its event
+fn record_transition(
+    conn: &Connection,
+    events: &mut Vec<Event>,
+    target: &Target,
+    status: u16,
+    transition: StateTransition,
+) -> Result<(), Box<dyn Error>> {
+    insert_state_transition(conn, &target.name, &transition)?;
+
+    // Never checked before, the first result isn't news
+    if transition.from != ServiceState::Unknown {
+        events.push(Event::Status {
+            name: target.name.clone(),
+            url: target.url.clone(),
+            status,
+            state: transition.to,
+            previous: transition.from,
+            message: transition.message,
         });
-        handles.push(handle);
     }
 
-    for handle in handles {
-        handle.join().unwrap();
+    Ok(())
+}
+
+// Writes all results and their history in one transaction, then announces state transitions
+// paused are the targets in maintenance, which weren't checked
+fn save_results(
+    config: &PingConfig,
+    results: &[(Target, CheckResult)],
+    paused: &[Target],
+    maintenance: &HashMap<String, i64>,
+    last_check: i64,
+) -> Result<(), Box<dyn Error>> {
+    let mut conn = connect_db()?;
+    let mut transitions = Vec::new();
+
+    let tx = conn.transaction()?;
+    for target in paused {
+        let Some(previous) = get_service_status(&tx, &target.name)? else {
+            continue;
+        };
+        if previous.state == ServiceState::Maintenance {
+            continue;
+        }
+
+        // Windows can start before the last ping, but not before the previous state did
+        let since = maintenance
+            .get(&target.name)
+            .map_or(last_check, |since| (*since).max(previous.state_since));
+        tx.execute(
+            "UPDATE services SET state = ?1, state_since = ?2, failures = 0, successes = 0
+             WHERE name = ?3",
+            params![ServiceState::Maintenance, since, target.name],
+        )?;
+        record_transition(
+            &tx,
+            &mut transitions,
+            target,
+            0,
+            StateTransition {
+                from: previous.state,
+                to: ServiceState::Maintenance,
+                changed_at: since,
+                message: None,
+                duration: None,
+            },
+        )?;
     }
 
-    for result in results.lock().unwrap().clone().into_iter() {
-        let (url, status) = result;
-        println!(
-            "Updating database with ping results for {} ({})",
-            url, status
-        );
-        conn.execute(
-            "UPDATE services SET status = ?1, last_check = ?2 WHERE url = ?3",
-            (status, last_check, url),
+    for (target, result) in results {
+        let Some(previous) = get_service_status(&tx, &target.name)? else {
+            continue;
+        };
+
+        let (failures, successes) = if result.up {
+            (0, previous.successes + 1)
+        } else {
+            (previous.failures + 1, 0)
+        };
+        let state = next_state(previous.state, result.up, failures, successes, config);
+
+        // Failed checks keep the last known certificate
+        let certificate = result.certificate.as_ref();
+        tx.execute(
+            "UPDATE services SET status = ?1, up = ?2, message = ?3, last_check = ?4,
+                cert_issuer = COALESCE(?5, cert_issuer), cert_expires = COALESCE(?6, cert_expires),
+                failures = ?7, successes = ?8,
+                state_since = CASE WHEN state = ?9 THEN state_since ELSE ?4 END, state = ?9
+             WHERE name = ?10",
+            params![
+                result.status,
+                result.up,
+                result.message,
+                last_check,
+                certificate.map(|certificate| &certificate.issuer),
+                certificate.map(|certificate| certificate.expires),
+                failures,
+                successes,
+                state,
+                target.name,
+            ],
+        )?;
+
+        insert_ping_history(
+            &tx,
+            &target.name,
+            &PingCheck {
+                checked_at: last_check,
+                status: result.status,
+                up: result.up,
+                latency: result.latency,
+                message: result.message.clone(),
+            },
         )?;
+
+        if state != previous.state {
+            record_transition(
+                &tx,
+                &mut transitions,
+                target,
+                result.status,
+                StateTransition {
+                    from: previous.state,
+                    to: state,
+                    changed_at: last_check,
+                    message: result.message.clone(),
+                    duration: None,
+                },
+            )?;
+        }
     }
+    compact_ping_history(
+        &tx,
+        last_check - config.history_raw_hours as i64 * 3600,
+        last_check - config.history_days as i64 * 86400,
+    )?;
+    tx.commit()?;
+    debug!("Updated database with {} ping results", results.len());
 
-    let duration = start_time.elapsed();
-    println!("Total time to ping all URLs: {:?}", duration);
+    conn.close().map_err(|(_, e)| e)?;
+
+    transitions.into_iter().for_each(publish);
 
     Ok(())
 }
 
-fn ping_url(url: &str) -> Result<u16, Box<dyn Error>> {
-    let (tx, rx) = mpsc::channel();
-    let url = String::from(url); // Clone the URL into a String
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ranges(expected: &[&str]) -> Result<Vec<RangeInclusive<u16>>, String> {
+        status_ranges(
+            &expected
+                .iter()
+                .map(|e| String::from(*e))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn state(current: ServiceState, passed: bool, failures: u32, successes: u32) -> ServiceState {
+        let config = PingConfig {
+            failure_threshold: 3,
+            recovery_threshold: 2,
+            ..PingConfig::default()
+        };
+        next_state(current, passed, failures, successes, &config)
+    }
+
+    #[test]
+    fn goes_down_after_repeated_failures() {
+        use ServiceState::*;
 
-    thread::spawn(move || {
-        let client = reqwest::blocking::Client::builder()
-            .danger_accept_invalid_certs(true) // Accept invalid certificates
-            .timeout(Duration::from_secs(2)) // 2s timeout
-            .build()
-            .unwrap();
+        assert_eq!(state(Unknown, true, 0, 1), Up);
+        assert_eq!(state(Unknown, false, 1, 0), Degraded);
+        assert_eq!(state(Up, true, 0, 5), Up);
+        assert_eq!(state(Up, false, 1, 0), Degraded);
+        assert_eq!(state(Degraded, false, 2, 0), Degraded);
+        assert_eq!(state(Degraded, false, 3, 0), Down);
+        assert_eq!(state(Down, false, 4, 0), Down);
+    }
+
+    #[test]
+    fn recovers_after_repeated_passes() {
+        use ServiceState::*;
+
+        assert_eq!(state(Degraded, true, 0, 1), Degraded);
+        assert_eq!(state(Degraded, true, 0, 2), Up);
+        assert_eq!(state(Down, true, 0, 1), Down);
+        assert_eq!(state(Down, true, 0, 2), Up);
+        // A failure in between starts the count over, which the caller does by resetting successes
+        assert_eq!(state(Down, false, 1, 0), Down);
+    }
+
+    #[test]
+    fn uses_thresholds_of_one() {
+        use ServiceState::*;
 
-        match client.get(&url).send() {
-            Ok(response) => tx.send(Ok(response)).unwrap(),
-            Err(e) => tx.send(Err(Box::new(e))).unwrap(),
+        let config = PingConfig {
+            failure_threshold: 1,
+            recovery_threshold: 1,
+            ..PingConfig::default()
+        };
+        assert_eq!(next_state(Up, false, 1, 0, &config), Down);
+        assert_eq!(next_state(Down, true, 0, 1, &config), Up);
+    }
+
+    #[test]
+    fn starts_over_after_maintenance() {
+        use ServiceState::*;
+
+        assert_eq!(state(Maintenance, true, 0, 1), Up);
+        assert_eq!(state(Maintenance, false, 1, 0), Degraded);
+        assert_eq!(state(Maintenance, false, 3, 0), Down);
+    }
+
+    #[test]
+    fn skips_targets_in_maintenance() {
+        let mut config = crate::config::create_default_config();
+        config.http.enabled = true;
+        config.http.urls = ["nas", "router", "printer"]
+            .iter()
+            .map(|name| {
+                toml::from_str(&format!(
+                    "name = \"{}\"\nurl = \"http://{}.lan\"",
+                    name, name
+                ))
+                .unwrap()
+            })
+            .collect();
+        let maintenance = HashMap::from([(String::from("router"), 0)]);
+
+        let (paused, checked) = split_paused(targets(&config), &maintenance);
+        let names = |targets: &[Target]| -> Vec<String> {
+            targets.iter().map(|target| target.name.clone()).collect()
+        };
+        assert_eq!(names(&paused), ["router"]);
+        assert_eq!(names(&checked), ["nas", "printer"]);
+    }
+
+    #[test]
+    fn parses_status_ranges() {
+        assert_eq!(ranges(&["200"]).unwrap(), vec![200..=200]);
+        assert_eq!(ranges(&["200-299"]).unwrap(), vec![200..=299]);
+        assert_eq!(
+            ranges(&["200-299,301", " 401 "]).unwrap(),
+            vec![200..=299, 301..=301, 401..=401]
+        );
+        assert!(ranges(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_status_ranges() {
+        for entry in [
+            "",
+            "abc",
+            "99",
+            "600",
+            "200-",
+            "-299",
+            "299-200",
+            "200-299-300",
+            "200,",
+        ] {
+            assert!(ranges(&[entry]).is_err(), "{:?}", entry);
         }
-    });
 
-    let response = rx.recv_timeout(Duration::from_secs(5))??;
-    Ok(response.status().as_u16())
+        let error = ranges(&["200", "2xx"]).unwrap_err();
+        assert_eq!(error, "\"2xx\" is not a status code or range");
+    }
+
+    #[test]
+    fn looks_up_json_paths() {
+        let value = json!({
+            "status": "ok",
+            "data": [{ "status": "healthy", "count": 3 }, { "status": "degraded" }],
+            "nested": { "deep": { "flag": true } }
+        });
+
+        assert_eq!(json_lookup(&value, "status"), Some(&json!("ok")));
+        assert_eq!(json_lookup(&value, "$.status"), Some(&json!("ok")));
+        assert_eq!(
+            json_lookup(&value, "data.1.status"),
+            Some(&json!("degraded"))
+        );
+        assert_eq!(json_lookup(&value, "data.0.count"), Some(&json!(3)));
+        assert_eq!(json_lookup(&value, "nested.deep.flag"), Some(&json!(true)));
+        // Empty segments are skipped, an empty path is the whole value
+        assert_eq!(json_lookup(&value, "nested..deep.flag"), Some(&json!(true)));
+        assert_eq!(json_lookup(&value, ""), Some(&value));
+    }
+
+    #[test]
+    fn misses_malformed_json_paths() {
+        let value = json!({ "status": "ok", "data": [{ "status": "healthy" }] });
+
+        for path in [
+            "missing",
+            "data.2.status",
+            "data.first.status",
+            "data.-1",
+            "status.length",
+            "data.0.status.more",
+        ] {
+            assert_eq!(json_lookup(&value, path), None, "{}", path);
+        }
+        assert_eq!(json_lookup(&json!("scalar"), "status"), None);
+    }
 }
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+use crate::alerts::evaluate_alerts;
+use crate::cache::{cached_endpoint, refresh};
+use crate::config::{config_snapshot, ConfigFields};
+use crate::database::connect_db;
+use crate::events::{publish, subscribers, Event, LIVE_ENDPOINTS};
+use crate::ping::{ping_all_urls, ping_due};
+use crate::services::overseerr::{OverseerrV1Requests, OVERSEERR};
+use crate::services::registry::{ServiceClient, SERVICES};
+use crate::services::tmdb::get_movie_entry;
+use crate::services::tvdb::get_series_entry;
+use crate::system_metrics::get_system_metrics;
+
+// How often the poller checks what is due, intervals are rounded up to this
+const TICK: Duration = Duration::from_secs(5);
+
+// Refreshes every enabled instance into the cache and pings all services in the background
+// TMDB and TVDB entries of Overseerr requests are looked up ahead of time too
+// While /api/events has clients it also refreshes live endpoints faster and samples system metrics
+// Alert rules are evaluated every alerts.interval
+// Runs on its own thread and runtime so it doesn't depend on the web server's runtime
+pub fn start_poller() {
+    thread::spawn(|| {
+        let runtime = match tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                error!("Failed to start poller: {}", e);
+                return;
+            }
+        };
+
+        runtime.block_on(poll());
+    });
+}
+
+async fn poll() {
+    // Last refresh per service, instance and endpoint, e.g. "radarr/4k/movies"
+    let mut refreshed: HashMap<String, Instant> = HashMap::new();
+    let mut ping: Option<JoinHandle<()>> = None;
+    let mut sampled: Option<Instant> = None;
+    let mut alerts: Option<(Instant, JoinHandle<()>)> = None;
+    let mut ticker = tokio::time::interval(TICK);
+
+    loop {
+        ticker.tick().await;
+
+        let Ok(config) = config_snapshot() else {
+            continue;
+        };
+        if !config.poller.enabled {
+            continue;
+        }
+
+        if config.cache.enabled {
+            refresh_due(&config, &mut refreshed);
+        }
+        refresh_media_due(&config, &mut refreshed);
+
+        let metrics_interval = Duration::from_secs(config.events.metrics_interval);
+        if config.events.enabled
+            && subscribers() > 0
+            && sampled.is_none_or(|last| last.elapsed() >= metrics_interval)
+        {
+            sampled = Some(Instant::now());
+            tokio::task::spawn_blocking(sample_metrics);
+        }
+
+        // Rules wait for the previous evaluation, which can take a while with slow channels
+        let alerts_interval = Duration::from_secs(config.alerts.interval);
+        if config.alerts.enabled
+            && alerts.as_ref().is_none_or(|(started, handle)| {
+                handle.is_finished() && started.elapsed() >= alerts_interval
+            })
+        {
+            alerts = Some((
+                Instant::now(),
+                tokio::spawn(evaluate_alerts(config.clone())),
+            ));
+        }
+
+        // Pings can outlast a tick, never run two at once
+        if ping.as_ref().is_none_or(|handle| handle.is_finished()) {
+            ping = Some(tokio::spawn(ping_if_due(config)));
+        }
+    }
+}
+
+fn refresh_due(config: &ConfigFields, refreshed: &mut HashMap<String, Instant>) {
+    let live = config.events.enabled && subscribers() > 0;
+
+    for service in SERVICES {
+        for instance in service.instances(config) {
+            if !instance.enabled() {
+                continue;
+            }
+
+            for endpoint in service.endpoints() {
+                let interval = if live && LIVE_ENDPOINTS.contains(&(service.id(), endpoint)) {
+                    config.events.live_interval
+                } else {
+                    config.poller.interval(service.id())
+                };
+
+                let key = format!("{}/{}/{}", service.id(), instance.name(), endpoint);
+                if refreshed
+                    .get(&key)
+                    .is_some_and(|last| last.elapsed() < Duration::from_secs(interval))
+                {
+                    continue;
+                }
+                refreshed.insert(key, Instant::now());
+
+                let fetch = service.fetch(config, instance.name(), endpoint);
+                let (id, name) = (service.id(), String::from(instance.name()));
+
+                tokio::spawn(async move { refresh(id, &name, endpoint, fetch).await });
+            }
+        }
+    }
+}
+
+// TMDB and TVDB have nothing to poll by themselves, the media of Overseerr requests is looked up ahead of the dashboard
+fn refresh_media_due(config: &Arc<ConfigFields>, refreshed: &mut HashMap<String, Instant>) {
+    if !config.tmdb.enabled && !config.tvdb.enabled {
+        return;
+    }
+
+    let key = String::from("overseerr/media");
+    let interval = Duration::from_secs(config.poller.interval("overseerr"));
+    if refreshed
+        .get(&key)
+        .is_some_and(|last| last.elapsed() < interval)
+    {
+        return;
+    }
+    refreshed.insert(key, Instant::now());
+
+    tokio::spawn(refresh_media(Arc::clone(config)));
+}
+
+// Posters are stored once per id, so only new requests cause TMDB or TVDB calls
+async fn refresh_media(config: Arc<ConfigFields>) {
+    let instances: Vec<String> = OVERSEERR
+        .instances(&config)
+        .into_iter()
+        .filter(|instance| instance.enabled())
+        .map(|instance| String::from(instance.name()))
+        .collect();
+
+    for instance in instances {
+        let requests = match cached_endpoint::<OverseerrV1Requests>(
+            &config, &OVERSEERR, &instance, "requests",
+        )
+        .await
+        {
+            Ok(requests) => requests,
+            Err(e) => {
+                warn!("Failed to read Overseerr requests of {}: {}", instance, e);
+                continue;
+            }
+        };
+
+        for (media_type, id) in requests.media_ids() {
+            let result = if media_type == "movie" {
+                if !config.tmdb.enabled {
+                    continue;
+                }
+                get_movie_entry(id, config.tmdb.api_key.clone())
+                    .await
+                    .map(|_| ())
+            } else {
+                if !config.tvdb.enabled {
+                    continue;
+                }
+                get_series_entry(id, config.tvdb.clone()).await.map(|_| ())
+            };
+
+            if let Err(e) = result {
+                warn!("Failed to look up {} {}: {}", media_type, id, e);
+            }
+        }
+    }
+}
+
+fn sample_metrics() {
+    match get_system_metrics() {
+        Ok(metrics) => publish(Event::Metrics(metrics)),
+        Err(e) => warn!("Failed to sample system metrics: {}", e),
+    }
+}
+
+async fn ping_if_due(config: Arc<ConfigFields>) {
+    let due = match connect_db() {
+        Ok(conn) => ping_due(&conn, config.poller.ping_interval),
+        Err(e) => {
+            error!("Failed to ping services: {}", e);
+            return;
+        }
+    };
+
+    if due {
+        if let Err(e) = ping_all_urls(&config).await {
+            error!("Failed to ping services: {}", e);
+        }
+    }
+}
@@ -0,0 +1,577 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::cache::cached_endpoint;
+use crate::config::{instance_display_name, ConfigFields};
+use crate::database::{connect_db, get_ping_data, Ping};
+use crate::services::adguard::AdGuardStats;
+use crate::services::dockwatch::DockwatchStatsResponse;
+use crate::services::proxmox::ProxmoxV2Data;
+use crate::services::qbittorrent::QBitV2Torrent;
+use crate::services::radarr::RadarrV3WantedMissing;
+use crate::services::registry::{ServiceClient, SERVICES};
+use crate::services::sonarr::SonarrV3WantedMissing;
+use crate::services::tautulli::TautulliV2Sessions;
+use crate::system_metrics::get_system_metrics;
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+// Widens through the shortest decimal, so 0.1f32 stays 0.1 instead of 0.10000000149011612
+fn widen(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(value as f64)
+}
+
+// Samples of one metric, rendered under a single HELP and TYPE
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    samples: Vec<(String, f64)>,
+}
+
+// Gauges in the Prometheus text exposition format, families keep the order they were first seen in
+#[derive(Default)]
+struct Metrics {
+    families: Vec<Family>,
+}
+
+// Backslashes, quotes and newlines are the only escapes in label values
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Metrics {
+    fn gauge(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&str, &str)],
+        value: f64,
+    ) {
+        // Prometheus spells infinity differently, and such values aren't useful here anyway
+        if !value.is_finite() {
+            return;
+        }
+
+        let labels = labels
+            .iter()
+            .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+            .collect::<Vec<String>>()
+            .join(",");
+
+        let index = match self.families.iter().position(|family| family.name == name) {
+            Some(index) => index,
+            None => {
+                self.families.push(Family {
+                    name,
+                    help,
+                    samples: Vec::new(),
+                });
+                self.families.len() - 1
+            }
+        };
+        self.families[index].samples.push((labels, value));
+    }
+
+    fn render(&self) -> String {
+        let mut output = String::new();
+
+        for family in &self.families {
+            output.push_str(&format!("# HELP {} {}\n", family.name, family.help));
+            output.push_str(&format!("# TYPE {} gauge\n", family.name));
+            for (labels, value) in &family.samples {
+                if labels.is_empty() {
+                    output.push_str(&format!("{} {}\n", family.name, value));
+                } else {
+                    output.push_str(&format!("{}{{{}}} {}\n", family.name, labels, value));
+                }
+            }
+        }
+
+        output
+    }
+}
+
+fn ping_metrics(metrics: &mut Metrics, config: &ConfigFields, pings: &[Ping]) {
+    // Services table names back to service and instance, other rows are plain URLs and monitors
+    let mut names: HashMap<String, (&str, String)> = HashMap::new();
+    for service in SERVICES {
+        for instance in service.instances(config) {
+            names.insert(
+                instance_display_name(service.name(), instance.name()),
+                (service.id(), String::from(instance.name())),
+            );
+        }
+    }
+
+    for ping in pings {
+        let (service, instance) = names
+            .get(&ping.name)
+            .map(|(service, instance)| (*service, instance.as_str()))
+            .unwrap_or((ping.name.as_str(), ""));
+        let labels = [("service", service), ("instance", instance)];
+
+        metrics.gauge(
+            "homedash_service_up",
+            "Whether the last check of the service passed",
+            &labels,
+            if ping.up { 1.0 } else { 0.0 },
+        );
+        metrics.gauge(
+            "homedash_service_state",
+            "Current state of the service, 1 for the state it is in",
+            &[
+                ("service", service),
+                ("instance", instance),
+                ("state", ping.state.as_str()),
+            ],
+            1.0,
+        );
+        if let Some(latency) = ping.latency {
+            metrics.gauge(
+                "homedash_service_latency_seconds",
+                "Response time of the last check",
+                &labels,
+                latency / 1000.0,
+            );
+        }
+        for (window, uptime) in [
+            ("24h", ping.uptime_24h),
+            ("7d", ping.uptime_7d),
+            ("30d", ping.uptime_30d),
+        ] {
+            if let Some(uptime) = uptime {
+                metrics.gauge(
+                    "homedash_service_uptime_percent",
+                    "Percent of passed checks over the window",
+                    &[
+                        ("service", service),
+                        ("instance", instance),
+                        ("window", window),
+                    ],
+                    uptime,
+                );
+            }
+        }
+        if let Some(expires) = ping.cert_expires {
+            metrics.gauge(
+                "homedash_service_certificate_expiry_timestamp_seconds",
+                "When the TLS certificate of the service expires",
+                &labels,
+                expires as f64,
+            );
+        }
+    }
+}
+
+async fn system_metrics(metrics: &mut Metrics) {
+    // The sample blocks for half a second of CPU measurement
+    let sample = tokio::task::spawn_blocking(get_system_metrics)
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|sample| sample.map_err(|e| e.to_string()));
+    let sample = match sample {
+        Ok(sample) => sample,
+        Err(e) => {
+            warn!("Failed to sample system metrics: {}", e);
+            return;
+        }
+    };
+
+    metrics.gauge(
+        "homedash_system_cpu_usage_percent",
+        "Average CPU usage across all cores",
+        &[],
+        widen(sample.cpu_usage),
+    );
+    metrics.gauge(
+        "homedash_system_memory_used_bytes",
+        "Memory in use",
+        &[],
+        (sample.mem_used * GB).round(),
+    );
+    metrics.gauge(
+        "homedash_system_memory_total_bytes",
+        "Total memory",
+        &[],
+        (sample.mem_total * GB).round(),
+    );
+    metrics.gauge(
+        "homedash_system_storage_used_bytes",
+        "Storage in use across mounted disks",
+        &[],
+        sample.storage_used as f64,
+    );
+    metrics.gauge(
+        "homedash_system_storage_total_bytes",
+        "Total storage across mounted disks",
+        &[],
+        sample.storage_total as f64,
+    );
+}
+
+// Endpoint the gauges of a service come from, None for services without any
+fn metrics_endpoint(service: &str) -> Option<&'static str> {
+    match service {
+        "proxmox" => Some("node"),
+        "qbittorrent" => Some("torrents"),
+        "tautulli" => Some("sessions"),
+        "adguard" => Some("stats"),
+        "dockwatch" => Some("overview"),
+        "sonarr" | "radarr" => Some("wanted_missing"),
+        _ => None,
+    }
+}
+
+// Gauges of one integration instance
+async fn service_metrics(
+    metrics: &mut Metrics,
+    config: &Arc<ConfigFields>,
+    service: &'static dyn ServiceClient,
+    instance: &str,
+) -> Result<(), String> {
+    let labels = [("service", service.id()), ("instance", instance)];
+
+    match service.id() {
+        "proxmox" => {
+            let nodes =
+                cached_endpoint::<Vec<ProxmoxV2Data>>(config, service, instance, "node").await?;
+            for node in nodes {
+                let node_labels = [
+                    ("service", service.id()),
+                    ("instance", instance),
+                    ("node", node.node.as_str()),
+                ];
+                let status = &node.status.data;
+                metrics.gauge(
+                    "homedash_proxmox_node_cpu_ratio",
+                    "CPU usage of the node between 0 and 1",
+                    &node_labels,
+                    widen(status.cpu),
+                );
+                metrics.gauge(
+                    "homedash_proxmox_node_memory_used_bytes",
+                    "Memory in use on the node",
+                    &node_labels,
+                    widen(status.memory.used),
+                );
+                metrics.gauge(
+                    "homedash_proxmox_node_memory_total_bytes",
+                    "Total memory of the node",
+                    &node_labels,
+                    widen(status.memory.total),
+                );
+
+                for storage in &node.storage.data {
+                    let storage_labels = [
+                        ("service", service.id()),
+                        ("instance", instance),
+                        ("node", node.node.as_str()),
+                        ("storage", storage.storage.as_str()),
+                    ];
+                    metrics.gauge(
+                        "homedash_proxmox_storage_used_bytes",
+                        "Used space of the storage",
+                        &storage_labels,
+                        storage.used as f64,
+                    );
+                    metrics.gauge(
+                        "homedash_proxmox_storage_total_bytes",
+                        "Size of the storage",
+                        &storage_labels,
+                        storage.total as f64,
+                    );
+                }
+            }
+        }
+        "qbittorrent" => {
+            let torrents =
+                cached_endpoint::<Vec<QBitV2Torrent>>(config, service, instance, "torrents")
+                    .await?;
+            metrics.gauge(
+                "homedash_qbittorrent_download_bytes_per_second",
+                "Download speed summed over all torrents",
+                &labels,
+                torrents.iter().map(|torrent| torrent.dlspeed).sum::<u64>() as f64,
+            );
+            metrics.gauge(
+                "homedash_qbittorrent_upload_bytes_per_second",
+                "Upload speed summed over all torrents",
+                &labels,
+                torrents.iter().map(|torrent| torrent.upspeed).sum::<u64>() as f64,
+            );
+
+            let mut states: Vec<(&str, u32)> = Vec::new();
+            for torrent in &torrents {
+                match states.iter_mut().find(|(state, _)| *state == torrent.state) {
+                    Some((_, count)) => *count += 1,
+                    None => states.push((torrent.state.as_str(), 1)),
+                }
+            }
+            for (state, count) in states {
+                metrics.gauge(
+                    "homedash_qbittorrent_torrents",
+                    "Torrents by qBittorrent state",
+                    &[
+                        ("service", service.id()),
+                        ("instance", instance),
+                        ("state", state),
+                    ],
+                    count as f64,
+                );
+            }
+        }
+        "tautulli" => {
+            let sessions =
+                cached_endpoint::<TautulliV2Sessions>(config, service, instance, "sessions")
+                    .await?;
+            let data = sessions
+                .response
+                .data
+                .ok_or_else(|| String::from("no session data"))?;
+
+            // stream_count is sent as a string
+            let total = data
+                .stream_count
+                .as_deref()
+                .and_then(|count| count.parse::<u32>().ok())
+                .unwrap_or_default();
+            for (kind, count) in [
+                ("total", Some(total)),
+                ("direct_play", data.stream_count_direct_play),
+                ("direct_stream", data.stream_count_direct_stream),
+                ("transcode", data.stream_count_transcode),
+            ] {
+                metrics.gauge(
+                    "homedash_tautulli_streams",
+                    "Active streams by playback type",
+                    &[
+                        ("service", service.id()),
+                        ("instance", instance),
+                        ("type", kind),
+                    ],
+                    count.unwrap_or_default() as f64,
+                );
+            }
+            for (network, bandwidth) in [
+                ("total", data.total_bandwidth),
+                ("lan", data.lan_bandwidth),
+                ("wan", data.wan_bandwidth),
+            ] {
+                metrics.gauge(
+                    "homedash_tautulli_bandwidth_kbps",
+                    "Streaming bandwidth in kilobits per second",
+                    &[
+                        ("service", service.id()),
+                        ("instance", instance),
+                        ("network", network),
+                    ],
+                    bandwidth.unwrap_or_default() as f64,
+                );
+            }
+        }
+        "adguard" => {
+            let stats = cached_endpoint::<AdGuardStats>(config, service, instance, "stats").await?;
+            for (name, help, value) in [
+                (
+                    "homedash_adguard_dns_queries",
+                    "DNS queries over the AdGuard statistics interval",
+                    stats.num_dns_queries,
+                ),
+                (
+                    "homedash_adguard_blocked_filtering",
+                    "Queries blocked by filters over the AdGuard statistics interval",
+                    stats.num_blocked_filtering,
+                ),
+                (
+                    "homedash_adguard_replaced_safebrowsing",
+                    "Queries blocked by safe browsing over the AdGuard statistics interval",
+                    stats.num_replaced_safebrowsing,
+                ),
+                (
+                    "homedash_adguard_replaced_safesearch",
+                    "Queries rewritten by safe search over the AdGuard statistics interval",
+                    stats.num_replaced_safesearch,
+                ),
+                (
+                    "homedash_adguard_replaced_parental",
+                    "Queries blocked by parental control over the AdGuard statistics interval",
+                    stats.num_replaced_parental,
+                ),
+            ] {
+                metrics.gauge(name, help, &labels, value as f64);
+            }
+            metrics.gauge(
+                "homedash_adguard_processing_time_seconds",
+                "Average time to process a DNS query",
+                &labels,
+                widen(stats.avg_processing_time),
+            );
+        }
+        "dockwatch" => {
+            let stats =
+                cached_endpoint::<DockwatchStatsResponse>(config, service, instance, "overview")
+                    .await?
+                    .response;
+            for (status, count) in [
+                ("running", stats.status.running),
+                ("stopped", stats.status.stopped),
+            ] {
+                metrics.gauge(
+                    "homedash_dockwatch_containers",
+                    "Containers by status",
+                    &[
+                        ("service", service.id()),
+                        ("instance", instance),
+                        ("status", status),
+                    ],
+                    count as f64,
+                );
+            }
+            for (health, count) in [
+                ("healthy", stats.health.healthy),
+                ("unhealthy", stats.health.unhealthy),
+                ("unknown", stats.health.unknown),
+            ] {
+                metrics.gauge(
+                    "homedash_dockwatch_container_health",
+                    "Containers by health check result",
+                    &[
+                        ("service", service.id()),
+                        ("instance", instance),
+                        ("health", health),
+                    ],
+                    count as f64,
+                );
+            }
+        }
+        "sonarr" => {
+            let missing = cached_endpoint::<SonarrV3WantedMissing>(
+                config,
+                service,
+                instance,
+                "wanted_missing",
+            )
+            .await?;
+            metrics.gauge(
+                "homedash_sonarr_missing_episodes",
+                "Monitored episodes that aren't downloaded",
+                &labels,
+                missing.totalRecords as f64,
+            );
+        }
+        "radarr" => {
+            let missing = cached_endpoint::<RadarrV3WantedMissing>(
+                config,
+                service,
+                instance,
+                "wanted_missing",
+            )
+            .await?;
+            metrics.gauge(
+                "homedash_radarr_missing_movies",
+                "Monitored movies that aren't downloaded",
+                &labels,
+                missing.totalRecords as f64,
+            );
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+// Renders every gauge, integration data comes through the response cache like on the dashboard
+pub async fn collect_metrics(config: Arc<ConfigFields>) -> String {
+    let mut metrics = Metrics::default();
+
+    let pings = connect_db().and_then(|conn| {
+        let pings = get_ping_data(&conn, 0)?;
+        conn.close().map_err(|(_, e)| e)?;
+        Ok(pings)
+    });
+    match pings {
+        Ok(pings) => ping_metrics(&mut metrics, &config, &pings),
+        Err(e) => warn!("Failed to read ping data for metrics: {}", e),
+    }
+
+    if config.prometheus.system_metrics {
+        system_metrics(&mut metrics).await;
+    }
+
+    for service in SERVICES {
+        let Some(endpoint) = metrics_endpoint(service.id()) else {
+            continue;
+        };
+
+        // Owned names, instances aren't Sync and can't be held across awaits
+        let instances: Vec<String> = service
+            .instances(&config)
+            .iter()
+            .filter(|instance| instance.enabled())
+            .map(|instance| String::from(instance.name()))
+            .collect();
+
+        for instance in instances {
+            let result = service_metrics(&mut metrics, &config, service, &instance).await;
+            if let Err(e) = &result {
+                warn!(
+                    "Failed to collect metrics from {}: {}",
+                    instance_display_name(service.name(), &instance),
+                    e
+                );
+            }
+
+            metrics.gauge(
+                "homedash_scrape_success",
+                "Whether the service endpoint could be read for this scrape",
+                &[
+                    ("service", service.id()),
+                    ("instance", &instance),
+                    ("endpoint", endpoint),
+                ],
+                if result.is_ok() { 1.0 } else { 0.0 },
+            );
+        }
+    }
+
+    metrics.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape(r#"a "quoted" name"#), r#"a \"quoted\" name"#);
+        assert_eq!(escape(r"C:\path"), r"C:\\path");
+        assert_eq!(escape("two\nlines"), r"two\nlines");
+    }
+
+    #[test]
+    fn renders_one_header_per_family() {
+        let mut metrics = Metrics::default();
+        metrics.gauge("homedash_up", "Up", &[("service", "sonarr")], 1.0);
+        metrics.gauge("homedash_cpu", "CPU", &[], 0.5);
+        metrics.gauge("homedash_up", "Up", &[("service", "a \"b\"")], 0.0);
+        metrics.gauge("homedash_cpu", "CPU", &[], f64::NAN);
+
+        assert_eq!(
+            metrics.render(),
+            "# HELP homedash_up Up\n\
+             # TYPE homedash_up gauge\n\
+             homedash_up{service=\"sonarr\"} 1\n\
+             homedash_up{service=\"a \\\"b\\\"\"} 0\n\
+             # HELP homedash_cpu CPU\n\
+             # TYPE homedash_cpu gauge\n\
+             homedash_cpu 0.5\n"
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Empty};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
+use std::thread;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{error, info, warn};
+
+use crate::config::{loaded_config, ProxyAuthConfig};
+
+// Set on requests from trusted proxies, auth only takes the user and groups headers along with it
+pub const TOKEN_HEADER: &str = "x-homedash-proxy-token";
+
+// Random per process, so only this listener can vouch for a request
+static TOKEN: OnceLock<String> = OnceLock::new();
+
+type ProxyBody = BoxBody<Bytes, hyper::Error>;
+
+pub fn proxy_token() -> &'static str {
+    TOKEN.get_or_init(|| {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    })
+}
+
+// "10.0.0.0/8" or a single address, None if it isn't valid
+pub fn parse_cidr(cidr: &str) -> Option<(IpAddr, u32)> {
+    let (address, prefix) = match cidr.split_once('/') {
+        Some((address, prefix)) => (address.parse::<IpAddr>().ok()?, Some(prefix)),
+        None => (cidr.parse::<IpAddr>().ok()?, None),
+    };
+    let bits = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.parse().ok().filter(|prefix| *prefix <= bits)?,
+        None => bits,
+    };
+
+    Some((address, prefix))
+}
+
+fn in_cidr(address: IpAddr, cidr: &str) -> bool {
+    let Some((network, prefix)) = parse_cidr(cidr) else {
+        return false;
+    };
+
+    // "::ffff:10.0.0.1" is the same client as "10.0.0.1"
+    match (address.to_canonical(), network) {
+        (IpAddr::V4(address), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(address) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(address), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(address) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+fn is_trusted(config: &ProxyAuthConfig, address: IpAddr) -> bool {
+    config
+        .trusted_proxies
+        .iter()
+        .any(|cidr| in_cidr(address, cidr))
+}
+
+// tuono doesn't tell routes the address of a connection, so the reverse proxy connects here instead
+// Requests are passed on to tuono, those from trusted_proxies with the token
+pub fn start_proxy_listener(config: &ProxyAuthConfig) {
+    if !config.enabled {
+        return;
+    }
+    let (listen, upstream) = (config.listen.clone(), config.upstream.clone());
+
+    thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                error!("Failed to start proxy listener: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = runtime.block_on(accept(&listen, upstream)) {
+            error!("Proxy listener on {} stopped: {}", listen, e);
+        }
+    });
+}
+
+async fn accept(listen: &str, upstream: String) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(listen).await?;
+    info!("Proxy listener on {}, forwarding to {}", listen, upstream);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Failed to accept proxy connection: {}", e);
+                continue;
+            }
+        };
+
+        let upstream = upstream.clone();
+        tokio::spawn(async move {
+            let service = service_fn(|req| forward(req, peer, upstream.clone()));
+            if let Err(e) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                warn!("Proxy connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+// Marks the request as trusted or takes its user and groups away
+fn vouch<B>(config: &ProxyAuthConfig, peer: SocketAddr, req: &mut Request<B>) {
+    let headers = req.headers_mut();
+    headers.remove(TOKEN_HEADER);
+
+    if is_trusted(config, peer.ip()) {
+        if let Ok(token) = proxy_token().parse() {
+            headers.insert(TOKEN_HEADER, token);
+        }
+        return;
+    }
+
+    if headers.contains_key(config.user_header.as_str()) {
+        warn!(
+            "Ignoring {} header from untrusted address {}",
+            config.user_header,
+            peer.ip()
+        );
+    }
+    headers.remove(config.user_header.as_str());
+    headers.remove(config.groups_header.as_str());
+}
+
+async fn forward(
+    mut req: Request<Incoming>,
+    peer: SocketAddr,
+    upstream: String,
+) -> Result<Response<ProxyBody>, hyper::Error> {
+    // Trust follows config reloads, the addresses only apply on restart
+    match loaded_config() {
+        Some(config) => vouch(&config.auth.proxy, peer, &mut req),
+        None => {
+            req.headers_mut().remove(TOKEN_HEADER);
+        }
+    }
+
+    match send(req, &upstream).await {
+        Ok(response) => Ok(response.map(|body| body.boxed())),
+        Err(e) => {
+            error!("Failed to forward request to {}: {}", upstream, e);
+            let mut response = Response::new(Empty::new().map_err(|e| match e {}).boxed());
+            *response.status_mut() = StatusCode::BAD_GATEWAY;
+            Ok(response)
+        }
+    }
+}
+
+async fn send(
+    req: Request<Incoming>,
+    upstream: &str,
+) -> Result<Response<Incoming>, Box<dyn Error>> {
+    let stream = TcpStream::connect(upstream).await?;
+    let (mut sender, connection) =
+        hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            warn!("Upstream connection failed: {}", e);
+        }
+    });
+
+    Ok(sender.send_request(req).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cidrs() {
+        assert_eq!(
+            parse_cidr("172.18.0.0/16"),
+            Some(("172.18.0.0".parse().unwrap(), 16))
+        );
+        assert_eq!(
+            parse_cidr("10.0.0.1"),
+            Some(("10.0.0.1".parse().unwrap(), 32))
+        );
+        assert_eq!(parse_cidr("fd00::/8"), Some(("fd00::".parse().unwrap(), 8)));
+        assert!(parse_cidr("10.0.0.0/33").is_none());
+        assert!(parse_cidr("proxy.lan").is_none());
+        assert!(parse_cidr("10.0.0.0/").is_none());
+    }
+
+    #[test]
+    fn matches_addresses() {
+        let address = |address: &str| address.parse::<IpAddr>().unwrap();
+
+        assert!(in_cidr(address("172.18.3.4"), "172.18.0.0/16"));
+        assert!(!in_cidr(address("172.19.0.1"), "172.18.0.0/16"));
+        assert!(in_cidr(address("::ffff:172.18.0.1"), "172.18.0.0/16"));
+        assert!(in_cidr(address("10.0.0.1"), "10.0.0.1"));
+        assert!(!in_cidr(address("10.0.0.2"), "10.0.0.1"));
+        assert!(in_cidr(address("192.168.1.1"), "0.0.0.0/0"));
+        assert!(in_cidr(address("fd00::1"), "fd00::/8"));
+        assert!(!in_cidr(address("fd00::1"), "10.0.0.0/8"));
+    }
+
+    #[test]
+    fn vouches_for_trusted_proxies() {
+        let config: ProxyAuthConfig =
+            toml::from_str("enabled = true\ntrusted_proxies = [\"172.18.0.0/16\"]").unwrap();
+        let request = || {
+            Request::builder()
+                .header("remote-user", "alice")
+                .header("remote-groups", "admins")
+                .header(TOKEN_HEADER, "forged")
+                .body(())
+                .unwrap()
+        };
+
+        let mut trusted = request();
+        vouch(&config, "172.18.0.2:40000".parse().unwrap(), &mut trusted);
+        assert_eq!(trusted.headers()[TOKEN_HEADER], proxy_token());
+        assert_eq!(trusted.headers()["remote-user"], "alice");
+
+        let mut untrusted = request();
+        vouch(
+            &config,
+            "192.168.1.5:40000".parse().unwrap(),
+            &mut untrusted,
+        );
+        for header in [TOKEN_HEADER, "remote-user", "remote-groups"] {
+            assert!(!untrusted.headers().contains_key(header), "{}", header);
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use homedash_rs::auth::{authenticate, check_visible, Access};
+use homedash_rs::database::{connect_db, get_alerts, Alert};
+use homedash_rs::logging::request_id;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::Json;
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/alerts", skip_all, fields(request = request_id()))]
+pub async fn alerts(req: Request) -> Result<Json<Vec<Alert>>, StatusCode> {
+    let login = authenticate(&req.headers, Access::Read)?;
+
+    let conn = connect_db().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut alerts = get_alerts(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    alerts.retain(|alert| check_visible(&login, &alert.service).is_ok());
+
+    // Close db connection
+    conn.close()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(alerts))
+}
@@ -0,0 +1,64 @@
+use homedash_rs::auth::{is_cross_site, session_cookie, start_session};
+use homedash_rs::config::config_snapshot;
+use homedash_rs::logging::request_id;
+use serde::Deserialize;
+use tracing::{error, instrument};
+use tuono_lib::axum::http::header::SET_COOKIE;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response};
+use tuono_lib::Request;
+
+#[derive(Deserialize)]
+struct Body {
+    username: String,
+    password: String,
+}
+
+#[tuono_lib::api(POST)]
+#[instrument(name = "POST /api/auth/login", skip_all, fields(request = request_id()))]
+pub async fn login(req: Request) -> Response {
+    let config = match config_snapshot() {
+        Ok(config) => config,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    if !config.auth.enabled {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    // Another site could otherwise log the browser in as someone else
+    if is_cross_site(&req.headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let body: Body = match req.body() {
+        Ok(body) => body,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    // Hashing the password takes a while, keep it off the async workers
+    let auth = config.auth.clone();
+    let session = tokio::task::spawn_blocking(move || {
+        start_session(&auth, &body.username, &body.password).map_err(|e| e.to_string())
+    })
+    .await;
+
+    match session {
+        Ok(Ok(Some(token))) => (
+            StatusCode::NO_CONTENT,
+            [(
+                SET_COOKIE,
+                session_cookie(&config.auth, &token, config.auth.session_days * 86400),
+            )],
+        )
+            .into_response(),
+        Ok(Ok(None)) => StatusCode::UNAUTHORIZED.into_response(),
+        Ok(Err(e)) => {
+            error!("Failed to log in: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(e) => {
+            error!("Failed to log in: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
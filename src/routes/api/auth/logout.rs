@@ -0,0 +1,34 @@
+use homedash_rs::auth::{end_session, is_cross_site, session_cookie};
+use homedash_rs::config::config_snapshot;
+use homedash_rs::logging::request_id;
+use tracing::{error, instrument};
+use tuono_lib::axum::http::header::SET_COOKIE;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response};
+use tuono_lib::Request;
+
+// Ends the session and tells the browser to drop its cookie
+#[tuono_lib::api(POST)]
+#[instrument(name = "POST /api/auth/logout", skip_all, fields(request = request_id()))]
+pub async fn logout(req: Request) -> Response {
+    let config = match config_snapshot() {
+        Ok(config) => config,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    // Another site could otherwise log the browser out
+    if is_cross_site(&req.headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    if let Err(e) = end_session(&req.headers) {
+        error!("Failed to log out: {}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    (
+        StatusCode::NO_CONTENT,
+        [(SET_COOKIE, session_cookie(&config.auth, "", 0))],
+    )
+        .into_response()
+}
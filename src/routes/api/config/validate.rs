@@ -0,0 +1,17 @@
+use homedash_rs::auth::{authenticate, check_sees_all, Access};
+use homedash_rs::config::get_config_path;
+use homedash_rs::logging::request_id;
+use homedash_rs::validation::{check_config_file, ConfigReport};
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::Json;
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/config/validate", skip_all, fields(request = request_id()))]
+pub async fn validate(req: Request) -> Result<Json<ConfigReport>, StatusCode> {
+    let login = authenticate(&req.headers, Access::Read)?;
+    check_sees_all(&login)?;
+
+    Ok(Json(check_config_file(&get_config_path())))
+}
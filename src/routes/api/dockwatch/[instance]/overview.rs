@@ -0,0 +1,36 @@
+use homedash_rs::auth::{authenticate, check_visible, Access};
+use homedash_rs::cache::{cached, Cached};
+use homedash_rs::config::{find_instance, get_config, instance_display_name};
+use homedash_rs::logging::request_id;
+use homedash_rs::services::dockwatch::{get_dockwatch_stats, DockwatchStatsResponse};
+use homedash_rs::services::ServiceError;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response, Result};
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/dockwatch/[instance]/overview", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn overview(req: Request) -> Result<Cached<DockwatchStatsResponse>, Response> {
+    let login = authenticate(&req.headers, Access::Read).map_err(IntoResponse::into_response)?;
+
+    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+
+    let instance = req.params.get("instance").cloned().unwrap_or_default();
+    let dockwatch = find_instance(&config.dockwatch, &instance)
+        .ok_or_else(|| {
+            ServiceError::Disabled.response(&instance_display_name("Dockwatch", &instance))
+        })?
+        .clone();
+    let name = instance_display_name("Dockwatch", &dockwatch.name);
+    let instance = dockwatch.name.clone();
+    check_visible(&login, &name).map_err(IntoResponse::into_response)?;
+
+    let dockwatch_stats = cached("dockwatch", &instance, "overview", move || {
+        get_dockwatch_stats(dockwatch.clone())
+    })
+    .await
+    .map_err(|e| e.response(&name))?;
+
+    Ok(dockwatch_stats)
+}
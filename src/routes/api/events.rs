@@ -0,0 +1,23 @@
+use homedash_rs::auth::{authenticate, check_visible, Access};
+use homedash_rs::config::config_snapshot;
+use homedash_rs::events::{event_stream, EventStream};
+use homedash_rs::logging::request_id;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/events", skip_all, fields(request = request_id()))]
+pub async fn events(req: Request) -> Result<EventStream, StatusCode> {
+    let login = authenticate(&req.headers, Access::Read)?;
+
+    let config = config_snapshot().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !config.events.enabled {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(event_stream(move |service| {
+        check_visible(&login, service).is_ok()
+    }))
+}
@@ -0,0 +1,36 @@
+use homedash_rs::auth::{authenticate, check_visible, Access};
+use homedash_rs::cache::{cached, Cached};
+use homedash_rs::config::{find_instance, get_config, instance_display_name};
+use homedash_rs::logging::request_id;
+use homedash_rs::services::gluetun::{get_vpn_public_ip, GluetunV1VPNPublicIP};
+use homedash_rs::services::ServiceError;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response, Result};
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/gluetun/[instance]/vpn_public_ip", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn vpn_public_ip(req: Request) -> Result<Cached<GluetunV1VPNPublicIP>, Response> {
+    let login = authenticate(&req.headers, Access::Read).map_err(IntoResponse::into_response)?;
+
+    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+
+    let instance = req.params.get("instance").cloned().unwrap_or_default();
+    let gluetun = find_instance(&config.gluetun, &instance)
+        .ok_or_else(|| {
+            ServiceError::Disabled.response(&instance_display_name("Gluetun", &instance))
+        })?
+        .clone();
+    let name = instance_display_name("Gluetun", &gluetun.name);
+    let instance = gluetun.name.clone();
+    check_visible(&login, &name).map_err(IntoResponse::into_response)?;
+
+    let public_ip = cached("gluetun", &instance, "vpn_public_ip", move || {
+        get_vpn_public_ip(gluetun.clone())
+    })
+    .await
+    .map_err(|e| e.response(&name))?;
+
+    Ok(public_ip)
+}
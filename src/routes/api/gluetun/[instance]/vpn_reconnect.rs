@@ -0,0 +1,31 @@
+use homedash_rs::auth::{authenticate, check_visible, Access};
+use homedash_rs::config::{find_instance, get_config, instance_display_name};
+use homedash_rs::logging::request_id;
+use homedash_rs::services::gluetun::{put_vpn_reconnect, GluetunV1VPNOutcome};
+use homedash_rs::services::ServiceError;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response, Result};
+use tuono_lib::axum::Json;
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/gluetun/[instance]/vpn_reconnect", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn vpn_reconnect(req: Request) -> Result<Json<Option<GluetunV1VPNOutcome>>, Response> {
+    let login = authenticate(&req.headers, Access::Write).map_err(IntoResponse::into_response)?;
+
+    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+
+    let instance = req.params.get("instance").cloned().unwrap_or_default();
+    let name = instance_display_name("Gluetun", &instance);
+    let gluetun = find_instance(&config.gluetun, &instance)
+        .ok_or_else(|| ServiceError::Disabled.response(&name))?;
+    check_visible(&login, &instance_display_name("Gluetun", &gluetun.name))
+        .map_err(IntoResponse::into_response)?;
+
+    let vpn_outcome = put_vpn_reconnect(gluetun.clone())
+        .await
+        .map_err(|e| e.response(&name))?;
+
+    Ok(Json(vpn_outcome))
+}
@@ -1,7 +1,10 @@
+use homedash_rs::logging::request_id;
+use tracing::instrument;
 use tuono_lib::axum::http::StatusCode;
 use tuono_lib::Request;
 
 #[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/health_check", skip_all, fields(request = request_id()))]
 pub async fn health_check(_req: Request) -> StatusCode {
     StatusCode::OK
 }
@@ -0,0 +1,33 @@
+use homedash_rs::auth::{authenticate, check_sees_all, Access};
+use homedash_rs::database::{connect_db, delete_maintenance_window};
+use homedash_rs::logging::request_id;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::Request;
+
+#[tuono_lib::api(DELETE)]
+#[instrument(name = "DELETE /api/maintenance/[id]", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn delete_window(req: Request) -> Result<StatusCode, StatusCode> {
+    let login = authenticate(&req.headers, Access::Write)?;
+    check_sees_all(&login)?;
+
+    let id: u32 = req
+        .params
+        .get("id")
+        .and_then(|id| id.parse().ok())
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let conn = connect_db().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let deleted =
+        delete_maintenance_window(&conn, id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Close db connection
+    conn.close()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
@@ -0,0 +1,67 @@
+use homedash_rs::auth::{authenticate, check_sees_all, Access};
+use homedash_rs::database::{
+    connect_db, get_maintenance_windows, insert_maintenance_window, MaintenanceWindow,
+};
+use homedash_rs::logging::request_id;
+use homedash_rs::maintenance::validate_window;
+use tracing::{error, instrument};
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response};
+use tuono_lib::axum::Json;
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/maintenance", skip_all, fields(request = request_id()))]
+pub async fn get_windows(req: Request) -> Result<Json<Vec<MaintenanceWindow>>, StatusCode> {
+    let login = authenticate(&req.headers, Access::Read)?;
+    check_sees_all(&login)?;
+
+    let conn = connect_db().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let windows = get_maintenance_windows(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Close db connection
+    conn.close()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(windows))
+}
+
+#[tuono_lib::api(POST)]
+#[instrument(name = "POST /api/maintenance", skip_all, fields(request = request_id()))]
+pub async fn add_window(req: Request) -> Response {
+    if let Err(status) =
+        authenticate(&req.headers, Access::Write).and_then(|login| check_sees_all(&login))
+    {
+        return status.into_response();
+    }
+
+    let mut window: MaintenanceWindow = match req.body() {
+        Ok(window) => window,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    if let Err(e) = validate_window(&window) {
+        return (StatusCode::UNPROCESSABLE_ENTITY, e).into_response();
+    }
+
+    let conn = match connect_db() {
+        Ok(conn) => conn,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let id = insert_maintenance_window(&conn, &window);
+    if conn.close().is_err() {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    match id {
+        Ok(Some(id)) => {
+            window.id = id as u32;
+            (StatusCode::CREATED, Json(window)).into_response()
+        }
+        // Unknown service_id
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!("Failed to save maintenance window: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -1,10 +1,16 @@
+use homedash_rs::auth::{authenticate, Access};
+use homedash_rs::logging::request_id;
 use homedash_rs::system_metrics::{get_system_metrics, SystemMetrics};
+use tracing::instrument;
 use tuono_lib::axum::http::StatusCode;
 use tuono_lib::axum::Json;
 use tuono_lib::Request;
 
 #[tuono_lib::api(GET)]
-pub async fn metrics(_: Request) -> Result<Json<SystemMetrics>, StatusCode> {
+#[instrument(name = "GET /api/metrics", skip_all, fields(request = request_id()))]
+pub async fn metrics(req: Request) -> Result<Json<SystemMetrics>, StatusCode> {
+    authenticate(&req.headers, Access::Read)?;
+
     get_system_metrics()
         .map(Json)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
@@ -1,18 +1,43 @@
+use homedash_rs::auth::{authenticate, check_visible, Access};
 use homedash_rs::config::get_config;
 use homedash_rs::database::{connect_db, get_ping_data, Ping};
-use homedash_rs::ping::ping_all_urls;
+use homedash_rs::logging::request_id;
+use homedash_rs::ping::{ping_all_urls, ping_due};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::instrument;
 use tuono_lib::axum::http::StatusCode;
 use tuono_lib::axum::Json;
 use tuono_lib::Request;
 
 #[tuono_lib::api(GET)]
-pub async fn ping(_req: Request) -> Result<Json<Vec<Ping>>, StatusCode> {
+#[instrument(name = "GET /api/ping", skip_all, fields(request = request_id()))]
+pub async fn ping(req: Request) -> Result<Json<Vec<Ping>>, StatusCode> {
+    let login = authenticate(&req.headers, Access::Read)?;
+
     let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // The poller pings in the background, without it the dashboard triggers the pings
+    let due = {
+        let conn = connect_db().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        !config.poller.enabled && ping_due(&conn, config.poller.ping_interval)
+    };
+    if due {
+        ping_all_urls(&config)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
     let conn = connect_db().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    ping_all_urls(&config, &conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let data = get_ping_data(&conn).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .as_secs();
+    let expiring_before = (now + config.ping.cert_warning_days * 86400) as i64;
+
+    let mut data =
+        get_ping_data(&conn, expiring_before).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    data.retain(|ping| check_visible(&login, &ping.name).is_ok());
 
     // Close db connection
     conn.close()
@@ -0,0 +1,37 @@
+use homedash_rs::auth::{authenticate, check_visible_id, Access};
+use homedash_rs::database::{connect_db, get_ping_history, PingHistory, MAX_PING_HISTORY};
+use homedash_rs::logging::request_id;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::Json;
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/ping/[id]/history/[since]", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn history(req: Request) -> Result<Json<Vec<PingHistory>>, StatusCode> {
+    let login = authenticate(&req.headers, Access::Read)?;
+
+    let id: u32 = req
+        .params
+        .get("id")
+        .and_then(|id| id.parse().ok())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    check_visible_id(&login, id)?;
+
+    // Unix timestamp of the oldest row to return
+    let since: i64 = req
+        .params
+        .get("since")
+        .and_then(|since| since.parse().ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let conn = connect_db().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let history = get_ping_history(&conn, id, since, MAX_PING_HISTORY)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Close db connection
+    conn.close()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    history.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
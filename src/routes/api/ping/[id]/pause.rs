@@ -0,0 +1,43 @@
+use homedash_rs::auth::{authenticate, check_visible_id, Access};
+use homedash_rs::database::{connect_db, set_paused};
+use homedash_rs::logging::request_id;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::Request;
+
+// Pauses or resumes monitoring, a paused service is in maintenance until resumed
+fn update(req: &Request, paused: bool) -> Result<StatusCode, StatusCode> {
+    let login = authenticate(&req.headers, Access::Write)?;
+
+    let id: u32 = req
+        .params
+        .get("id")
+        .and_then(|id| id.parse().ok())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    check_visible_id(&login, id)?;
+
+    let conn = connect_db().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let updated = set_paused(&conn, id, paused).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Close db connection
+    conn.close()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if updated {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+#[tuono_lib::api(POST)]
+#[instrument(name = "POST /api/ping/[id]/pause", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn pause(req: Request) -> Result<StatusCode, StatusCode> {
+    update(&req, true)
+}
+
+#[tuono_lib::api(DELETE)]
+#[instrument(name = "DELETE /api/ping/[id]/pause", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn resume(req: Request) -> Result<StatusCode, StatusCode> {
+    update(&req, false)
+}
@@ -0,0 +1,30 @@
+use homedash_rs::auth::{authenticate, check_visible_id, Access};
+use homedash_rs::database::{connect_db, get_state_transitions, StateTransition};
+use homedash_rs::logging::request_id;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::Json;
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/ping/[id]/transitions", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn transitions(req: Request) -> Result<Json<Vec<StateTransition>>, StatusCode> {
+    let login = authenticate(&req.headers, Access::Read)?;
+
+    let id: u32 = req
+        .params
+        .get("id")
+        .and_then(|id| id.parse().ok())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    check_visible_id(&login, id)?;
+
+    let conn = connect_db().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let transitions =
+        get_state_transitions(&conn, id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Close db connection
+    conn.close()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    transitions.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
@@ -0,0 +1,34 @@
+use homedash_rs::auth::{authenticate, check_visible, Access};
+use homedash_rs::cache::{cached, Cached};
+use homedash_rs::config::{find_instance, get_config, instance_display_name};
+use homedash_rs::logging::request_id;
+use homedash_rs::services::plex::{get_library_media_count, PlexLibraryCount};
+use homedash_rs::services::ServiceError;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response, Result};
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/plex/[instance]/library_count", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn library_count(req: Request) -> Result<Cached<PlexLibraryCount>, Response> {
+    let login = authenticate(&req.headers, Access::Read).map_err(IntoResponse::into_response)?;
+
+    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+
+    let instance = req.params.get("instance").cloned().unwrap_or_default();
+    let plex = find_instance(&config.plex, &instance)
+        .ok_or_else(|| ServiceError::Disabled.response(&instance_display_name("Plex", &instance)))?
+        .clone();
+    let name = instance_display_name("Plex", &plex.name);
+    let instance = plex.name.clone();
+    check_visible(&login, &name).map_err(IntoResponse::into_response)?;
+
+    let count = cached("plex", &instance, "library_count", move || {
+        get_library_media_count(plex.clone())
+    })
+    .await
+    .map_err(|e| e.response(&name))?;
+
+    Ok(count)
+}
@@ -0,0 +1,33 @@
+use homedash_rs::auth::{authenticate, check_sees_all, Access};
+use homedash_rs::config::config_snapshot;
+use homedash_rs::logging::request_id;
+use homedash_rs::prometheus::{collect_metrics, CONTENT_TYPE};
+use tracing::instrument;
+use tuono_lib::axum::http::header::CONTENT_TYPE as CONTENT_TYPE_HEADER;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response};
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/prometheus", skip_all, fields(request = request_id()))]
+pub async fn prometheus(req: Request) -> Response {
+    if let Err(status) =
+        authenticate(&req.headers, Access::Read).and_then(|login| check_sees_all(&login))
+    {
+        return status.into_response();
+    }
+
+    let config = match config_snapshot() {
+        Ok(config) => config,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    if !config.prometheus.enabled {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    (
+        [(CONTENT_TYPE_HEADER, CONTENT_TYPE)],
+        collect_metrics(config).await,
+    )
+        .into_response()
+}
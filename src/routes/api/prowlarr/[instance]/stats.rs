@@ -0,0 +1,36 @@
+use homedash_rs::auth::{authenticate, check_visible, Access};
+use homedash_rs::cache::{cached, Cached};
+use homedash_rs::config::{find_instance, get_config, instance_display_name};
+use homedash_rs::logging::request_id;
+use homedash_rs::services::prowlarr::{get_indexer_stats, ProwlarrV1IndexerStats};
+use homedash_rs::services::ServiceError;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response, Result};
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/prowlarr/[instance]/stats", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn indexer_stats(req: Request) -> Result<Cached<ProwlarrV1IndexerStats>, Response> {
+    let login = authenticate(&req.headers, Access::Read).map_err(IntoResponse::into_response)?;
+
+    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+
+    let instance = req.params.get("instance").cloned().unwrap_or_default();
+    let prowlarr = find_instance(&config.prowlarr, &instance)
+        .ok_or_else(|| {
+            ServiceError::Disabled.response(&instance_display_name("Prowlarr", &instance))
+        })?
+        .clone();
+    let name = instance_display_name("Prowlarr", &prowlarr.name);
+    let instance = prowlarr.name.clone();
+    check_visible(&login, &name).map_err(IntoResponse::into_response)?;
+
+    let stats = cached("prowlarr", &instance, "stats", move || {
+        get_indexer_stats(prowlarr.clone())
+    })
+    .await
+    .map_err(|e| e.response(&name))?;
+
+    Ok(stats)
+}
@@ -1,5 +1,8 @@
+use homedash_rs::auth::{authenticate, Access};
 use homedash_rs::database::{connect_db, update_app_preferences, Preference};
+use homedash_rs::logging::request_id;
 use serde::Deserialize;
+use tracing::instrument;
 use tuono_lib::axum::http::StatusCode;
 use tuono_lib::Request;
 
@@ -9,7 +12,10 @@ struct Body {
 }
 
 #[tuono_lib::api(POST)]
+#[instrument(name = "POST /api/save", skip_all, fields(request = request_id()))]
 pub async fn save(req: Request) -> Result<String, StatusCode> {
+    authenticate(&req.headers, Access::Write)?;
+
     let body: Body = req.body().map_err(|_| StatusCode::BAD_REQUEST)?;
 
     let mut conn = connect_db().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -0,0 +1,56 @@
+use homedash_rs::auth::{authenticate, check_visible, Access};
+use homedash_rs::cache::{cached, Cached};
+use homedash_rs::config::{config_snapshot, instance_display_name};
+use homedash_rs::logging::request_id;
+use homedash_rs::services::registry::find_service;
+use homedash_rs::services::ServiceError;
+use serde_json::Value;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response, Result};
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/services/[service]/[instance]/[endpoint]", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn service_data(req: Request) -> Result<Cached<Value>, Response> {
+    let login = authenticate(&req.headers, Access::Read).map_err(IntoResponse::into_response)?;
+
+    let config =
+        config_snapshot().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+
+    let service = req
+        .params
+        .get("service")
+        .and_then(|service| find_service(service))
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+
+    let endpoint = req
+        .params
+        .get("endpoint")
+        .and_then(|endpoint| {
+            service
+                .endpoints()
+                .into_iter()
+                .find(|known| known == endpoint)
+        })
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+
+    let selector = req.params.get("instance").cloned().unwrap_or_default();
+    let instance = service
+        .find(&config, &selector)
+        .map(|found| String::from(found.name()))
+        .ok_or_else(|| {
+            ServiceError::Disabled.response(&instance_display_name(service.name(), &selector))
+        })?;
+    let name = instance_display_name(service.name(), &instance);
+    check_visible(&login, &name).map_err(IntoResponse::into_response)?;
+
+    let fetched = instance.clone();
+    let data = cached(service.id(), &instance, endpoint, move || {
+        service.fetch(&config, &fetched, endpoint)
+    })
+    .await
+    .map_err(|e| e.response(&name))?;
+
+    Ok(data)
+}
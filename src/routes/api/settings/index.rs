@@ -0,0 +1,53 @@
+use homedash_rs::config::get_config;
+use homedash_rs::logging::request_id;
+use homedash_rs::settings::{authorize, redacted_config, save_settings, SettingsError};
+use serde_json::Value;
+use tracing::{error, instrument};
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response};
+use tuono_lib::axum::Json;
+use tuono_lib::Request;
+
+#[tuono_lib::api(GET)]
+#[instrument(name = "GET /api/settings", skip_all, fields(request = request_id()))]
+pub async fn get_settings(req: Request) -> Result<Json<Value>, StatusCode> {
+    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    authorize(&req.headers, &config)?;
+
+    match redacted_config() {
+        Ok(value) => Ok(Json(value)),
+        Err(e) => {
+            error!("Failed to read settings: {:?}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[tuono_lib::api(PUT)]
+#[instrument(name = "PUT /api/settings", skip_all, fields(request = request_id()))]
+pub async fn put_settings(req: Request) -> Response {
+    let config = match get_config() {
+        Ok(config) => config,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    if let Err(status) = authorize(&req.headers, &config) {
+        return status.into_response();
+    }
+
+    let body: Value = match req.body() {
+        Ok(body) => body,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    match save_settings(body).and_then(|_| redacted_config()) {
+        Ok(value) => Json(value).into_response(),
+        Err(SettingsError::BadRequest(e)) => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(SettingsError::Invalid(report)) => {
+            (StatusCode::UNPROCESSABLE_ENTITY, Json(report)).into_response()
+        }
+        Err(SettingsError::Failed(e)) => {
+            error!("Failed to save settings: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
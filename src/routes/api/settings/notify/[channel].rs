@@ -0,0 +1,30 @@
+use homedash_rs::config::get_config;
+use homedash_rs::logging::request_id;
+use homedash_rs::notifier::send_test;
+use homedash_rs::settings::authorize;
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response};
+use tuono_lib::Request;
+
+#[tuono_lib::api(POST)]
+#[instrument(name = "POST /api/settings/notify/[channel]", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn notify_channel(req: Request) -> Response {
+    let config = match get_config() {
+        Ok(config) => config,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    if let Err(status) = authorize(&req.headers, &config) {
+        return status.into_response();
+    }
+
+    let Some(channel) = req.params.get("channel") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match send_test(&config, channel).await {
+        Some(Ok(())) => StatusCode::NO_CONTENT.into_response(),
+        Some(Err(e)) => (StatusCode::BAD_GATEWAY, e).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
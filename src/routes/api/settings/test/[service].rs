@@ -0,0 +1,22 @@
+use homedash_rs::config::get_config;
+use homedash_rs::logging::request_id;
+use homedash_rs::settings::{authorize, test_connection, ConnectionTest};
+use tracing::instrument;
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::Result;
+use tuono_lib::axum::Json;
+use tuono_lib::Request;
+
+#[tuono_lib::api(POST)]
+#[instrument(name = "POST /api/settings/test/[service]", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn test_service(req: Request) -> Result<Json<Vec<ConnectionTest>>, StatusCode> {
+    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    authorize(&req.headers, &config)?;
+
+    let service = req.params.get("service").ok_or(StatusCode::NOT_FOUND)?;
+    let results = test_connection(&config, service)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(results))
+}
@@ -1,27 +1,34 @@
+use homedash_rs::auth::{authenticate, Access};
 use homedash_rs::config::get_config;
+use homedash_rs::logging::request_id;
 use homedash_rs::services::tmdb::{get_movie_entry, TMDB3Movie};
+use homedash_rs::services::ServiceError;
+use tracing::instrument;
 use tuono_lib::axum::http::StatusCode;
-use tuono_lib::axum::response::Result;
+use tuono_lib::axum::response::{IntoResponse, Response, Result};
 use tuono_lib::axum::Json;
 use tuono_lib::Request;
 
 #[tuono_lib::api(GET)]
-pub async fn get_tmdb_movie_entry(req: Request) -> Result<Json<TMDB3Movie>, StatusCode> {
-    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+#[instrument(name = "GET /api/tmdb/[id]", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn get_tmdb_movie_entry(req: Request) -> Result<Json<TMDB3Movie>, Response> {
+    authenticate(&req.headers, Access::Read).map_err(IntoResponse::into_response)?;
+
+    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
 
     if !config.tmdb.enabled {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(ServiceError::Disabled.response("TMDB"));
     }
 
     let tmdb_id = req
         .params
         .get("id")
         .and_then(|id| id.parse::<u32>().ok())
-        .ok_or(StatusCode::BAD_REQUEST)?;
+        .ok_or_else(|| StatusCode::BAD_REQUEST.into_response())?;
 
     let movie_entry = get_movie_entry(tmdb_id, config.tmdb.api_key)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|e| e.response("TMDB"))?;
 
     Ok(Json(movie_entry))
 }
@@ -1,27 +1,34 @@
+use homedash_rs::auth::{authenticate, Access};
 use homedash_rs::config::get_config;
+use homedash_rs::logging::request_id;
 use homedash_rs::services::tvdb::{get_series_entry, TVDBV4SeriesData};
+use homedash_rs::services::ServiceError;
+use tracing::instrument;
 use tuono_lib::axum::http::StatusCode;
-use tuono_lib::axum::response::Result;
+use tuono_lib::axum::response::{IntoResponse, Response, Result};
 use tuono_lib::axum::Json;
 use tuono_lib::Request;
 
 #[tuono_lib::api(GET)]
-pub async fn get_tvdb_series_entry(req: Request) -> Result<Json<TVDBV4SeriesData>, StatusCode> {
-    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+#[instrument(name = "GET /api/tvdb/[id]", skip_all, fields(request = request_id(), params = ?req.params))]
+pub async fn get_tvdb_series_entry(req: Request) -> Result<Json<TVDBV4SeriesData>, Response> {
+    authenticate(&req.headers, Access::Read).map_err(IntoResponse::into_response)?;
+
+    let config = get_config().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
 
     if !config.tvdb.enabled {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(ServiceError::Disabled.response("TVDB"));
     }
 
     let tvdb_id = req
         .params
         .get("id")
         .and_then(|id| id.parse::<u32>().ok())
-        .ok_or(StatusCode::BAD_REQUEST)?;
+        .ok_or_else(|| StatusCode::BAD_REQUEST.into_response())?;
 
     let series_entry = get_series_entry(tvdb_id, config.tvdb)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|e| e.response("TVDB"))?;
 
     Ok(Json(series_entry))
 }
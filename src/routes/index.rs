@@ -1,4 +1,6 @@
-use homedash_rs::config::get_config;
+use homedash_rs::auth::{current_login, Access};
+use homedash_rs::config::{get_config, instance_display_name, AuthMode};
+use homedash_rs::services::registry::SERVICES;
 use serde::Serialize;
 use tuono_lib::{Props, Request, Response, Type};
 
@@ -9,26 +11,77 @@ pub struct WeatherData {
     long: f64,
 }
 
+// Enabled instances of a service, by the name its routes take
+#[derive(Debug, Serialize, Type)]
+#[allow(unused, non_snake_case)]
+pub struct ServiceInstances {
+    service: String,
+    // Display name, the dashboard tells built-in services apart by it
+    name: String,
+    names: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct IndexData {
     username: String,
     weather: WeatherData,
     background: Option<String>,
+    // Has a session the logout button can end, proxy logins end at the proxy
+    logged_in: bool,
+    can_control: bool,
+    // The data cards show one card per instance
+    instances: Vec<ServiceInstances>,
 }
 
 #[tuono_lib::handler]
-async fn index_data(_req: Request) -> Response {
+async fn index_data(req: Request) -> Response {
     let config = get_config().unwrap();
 
+    let login = if config.auth.enabled {
+        current_login(&config.auth, &req.headers, Access::Read).unwrap_or(None)
+    } else {
+        None
+    };
+    if config.auth.enabled && config.auth.mode == AuthMode::All && login.is_none() {
+        return Response::Redirect(String::from("/login"));
+    }
+
     let weather_data = WeatherData {
         lat: config.clone().weather_location[0],
         long: config.clone().weather_location[1],
     };
+    let instances = SERVICES
+        .iter()
+        .map(|service| ServiceInstances {
+            service: String::from(service.id()),
+            name: String::from(service.name()),
+            names: service
+                .instances(&config)
+                .into_iter()
+                .filter(|instance| instance.enabled())
+                .filter(|instance| {
+                    login.as_ref().is_none_or(|login| {
+                        login.can_see(&instance_display_name(service.name(), instance.name()))
+                    })
+                })
+                .map(|instance| match instance.name() {
+                    "" => String::from("default"),
+                    name => String::from(name),
+                })
+                .collect(),
+        })
+        .collect();
+
     let index_data = IndexData {
-        username: config.clone().username,
+        username: login
+            .as_ref()
+            .map_or(config.clone().username, |login| login.username.clone()),
         weather: weather_data,
         background: config.clone().background_url,
+        logged_in: login.as_ref().is_some_and(|login| !login.proxied),
+        can_control: login.as_ref().is_none_or(|login| login.control),
+        instances,
     };
 
     Response::Props(Props::new(index_data))
@@ -7,7 +7,7 @@ import { IndexData } from "tuono/types";
 export default function IndexPage({ data }: TuonoRouteProps<IndexData>): JSX.Element {
     return (
         <TooltipProvider delayDuration={0}>
-            <Dashboard username={data?.username} weather={data?.weather} background={data?.background} />
+            <Dashboard username={data?.username} weather={data?.weather} background={data?.background} loggedIn={data?.logged_in ?? false} canControl={data?.can_control ?? true} instances={data?.instances ?? []} />
         </TooltipProvider>
     );
 }
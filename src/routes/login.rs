@@ -0,0 +1,28 @@
+use homedash_rs::auth::{current_login, Access};
+use homedash_rs::config::get_config;
+use serde::Serialize;
+use tuono_lib::{Props, Request, Response, Type};
+
+#[derive(Debug, Serialize, Type)]
+#[allow(unused, non_snake_case)]
+pub struct LoginData {
+    background: Option<String>,
+}
+
+#[tuono_lib::handler]
+async fn login_data(req: Request) -> Response {
+    let config = get_config().unwrap();
+
+    // Nothing to log in to, or already logged in
+    if !config.auth.enabled
+        || current_login(&config.auth, &req.headers, Access::Read)
+            .unwrap_or(None)
+            .is_some()
+    {
+        return Response::Redirect(String::from("/"));
+    }
+
+    Response::Props(Props::new(LoginData {
+        background: config.background_url,
+    }))
+}
@@ -0,0 +1,63 @@
+import { useState, type FormEvent, type JSX } from "react";
+import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
+import { Input } from "@/components/ui/input";
+import { Button } from "@/components/ui/button";
+import type { TuonoRouteProps } from "tuono";
+import { LoginData } from "tuono/types";
+
+export default function LoginPage({ data }: TuonoRouteProps<LoginData>): JSX.Element {
+    const [username, setUsername] = useState("");
+    const [password, setPassword] = useState("");
+    const [error, setError] = useState<string | null>(null);
+    const [isSubmitting, setIsSubmitting] = useState(false);
+
+    const onSubmit = async (event: FormEvent) => {
+        event.preventDefault();
+        setIsSubmitting(true);
+        setError(null);
+
+        try {
+            const res = await fetch("/api/auth/login", {
+                method: "POST",
+                headers: {
+                    "Content-Type": "application/json",
+                },
+                body: JSON.stringify({ username, password }),
+            });
+
+            if (res.ok) {
+                window.location.href = "/";
+                return;
+            }
+            setError(res.status === 401 ? "Wrong username or password" : "Login failed, try again later");
+        } catch (error) {
+            console.error("Error logging in:", error);
+            setError("Login failed, try again later");
+        }
+        setIsSubmitting(false);
+    };
+
+    return (
+        <>
+            <div className="fixed top-0 left-0 w-full h-full bg-cover bg-center z-0 blur-sm brightness-50" style={{ backgroundImage: data?.background ? `url(${data.background})` : "none" }}></div>
+            <div className="min-h-screen flex items-center justify-center text-zinc-100 p-6 font-sans z-10 relative">
+                <Card className="w-full max-w-sm bg-zinc-900/60 border border-white/10 shadow-lg">
+                    <CardHeader>
+                        <CardTitle className="text-lg font-medium text-zinc-100">homedash-rs</CardTitle>
+                        <CardDescription className="text-zinc-300">Log in to continue</CardDescription>
+                    </CardHeader>
+                    <CardContent>
+                        <form onSubmit={onSubmit} className="space-y-4">
+                            <Input type="text" placeholder="Username" autoComplete="username" value={username} onChange={(e) => setUsername(e.target.value)} className="bg-zinc-800/60 border-white/10 text-zinc-100" required autoFocus />
+                            <Input type="password" placeholder="Password" autoComplete="current-password" value={password} onChange={(e) => setPassword(e.target.value)} className="bg-zinc-800/60 border-white/10 text-zinc-100" required />
+                            {error && <p className="text-sm text-red-400">{error}</p>}
+                            <Button type="submit" className="w-full cursor-pointer" disabled={isSubmitting}>
+                                Log in
+                            </Button>
+                        </form>
+                    </CardContent>
+                </Card>
+            </div>
+        </>
+    );
+}
@@ -1,9 +1,11 @@
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::http_client;
 use crate::config::APICredsConfig;
+use crate::services::registry::{to_value, Service};
+use crate::services::{json, send, ServiceError};
 
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
@@ -17,11 +19,11 @@ pub struct AdGuardStats {
 }
 
 #[allow(deprecated)]
-async fn req(endpoint: String, credentials: APICredsConfig) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
+async fn req(endpoint: String, credentials: APICredsConfig) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
     let url = format!("{}/{}", &credentials.url, endpoint);
 
-    let response = client
+    let request = client
         .get(&url)
         .header("Content-Type", "application/json")
         .header(
@@ -33,18 +35,34 @@ async fn req(endpoint: String, credentials: APICredsConfig) -> Result<Response,
                     &credentials.username, &credentials.password
                 ))
             ),
-        )
-        .send()
-        .await?;
+        );
+    let response = send("adguard", &credentials.name, request)
+        .await?
+        .error_for_status()?;
 
     Ok(response)
 }
 
-pub async fn get_adguard_stats(
-    credentials: APICredsConfig,
-) -> Result<AdGuardStats, Box<dyn Error>> {
+pub async fn get_adguard_stats(credentials: APICredsConfig) -> Result<AdGuardStats, ServiceError> {
     let response = req(String::from("control/stats"), credentials).await?;
-    let data = response.json::<AdGuardStats>().await?;
+    let data = json::<AdGuardStats>(response).await?;
 
     Ok(data)
 }
+
+pub static ADGUARD: Service<APICredsConfig> = Service {
+    id: "adguard",
+    name: "AdGuard",
+    pinged: true,
+    instances: |config| &config.adguard,
+    instances_mut: |config| &mut config.adguard,
+    probe: |adguard| {
+        Box::pin(async move {
+            get_adguard_stats(adguard).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[("stats", |adguard| {
+        Box::pin(async move { to_value(get_adguard_stats(adguard).await?) })
+    })],
+};
@@ -1,8 +1,12 @@
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::http_client;
+use crate::config::APIKeyConfig;
+use crate::services::registry::{to_value, Service};
+use crate::services::{json, send, ServiceError};
+
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct Status {
@@ -51,24 +55,39 @@ pub struct DockwatchStatsResponse {
     pub response: DockwatchStats,
 }
 
-async fn req(
-    base_url: String,
-    endpoint: String,
-    api_key: String,
-) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!("{}/api/{}", base_url, endpoint);
-    let response = client.get(url).query(&[("apikey", api_key)]).send().await?;
+async fn req(endpoint: String, credentials: APIKeyConfig) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
+    let url = format!("{}/api/{}", credentials.url, endpoint);
+    let request = client.get(url).query(&[("apikey", credentials.api_key)]);
+    let response = send("dockwatch", &credentials.name, request)
+        .await?
+        .error_for_status()?;
 
     Ok(response)
 }
 
 pub async fn get_dockwatch_stats(
-    base_url: String,
-    api_key: String,
-) -> Result<DockwatchStatsResponse, Box<dyn Error>> {
-    let response = req(base_url, String::from("stats/overview"), api_key).await?;
-    let data = response.json::<DockwatchStatsResponse>().await?;
+    credentials: APIKeyConfig,
+) -> Result<DockwatchStatsResponse, ServiceError> {
+    let response = req(String::from("stats/overview"), credentials).await?;
+    let data = json::<DockwatchStatsResponse>(response).await?;
 
     Ok(data)
 }
+
+pub static DOCKWATCH: Service<APIKeyConfig> = Service {
+    id: "dockwatch",
+    name: "Dockwatch",
+    pinged: true,
+    instances: |config| &config.dockwatch,
+    instances_mut: |config| &mut config.dockwatch,
+    probe: |dockwatch| {
+        Box::pin(async move {
+            get_dockwatch_stats(dockwatch).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[("overview", |dockwatch| {
+        Box::pin(async move { to_value(get_dockwatch_stats(dockwatch).await?) })
+    })],
+};
@@ -1,8 +1,12 @@
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::http_client;
+use crate::config::APIKeyConfig;
+use crate::services::registry::{to_value, Service};
+use crate::services::{json, send, ServiceError};
+
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct GluetunV1VPNPublicIP {
@@ -25,95 +29,87 @@ pub struct GluetunV1VPNOutcome {
 }
 
 async fn req(
-    base_url: String,
     endpoint: String,
-    api_key: String,
+    credentials: APIKeyConfig,
     method: String,
     data: Option<String>,
-) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!("{}/v1/{}", base_url, endpoint);
+) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
+    let url = format!("{}/v1/{}", credentials.url, endpoint);
 
     if &method == "put" && !data.is_none() {
-        let response = client
+        let request = client
             .put(url)
             .header("Content-Type", "application/json")
-            .header("X-API-Key", api_key)
-            .body(data.unwrap())
-            .send()
-            .await?;
+            .header("X-API-Key", credentials.api_key)
+            .body(data.unwrap());
+        let response = send("gluetun", &credentials.name, request)
+            .await?
+            .error_for_status()?;
 
         return Ok(response);
     }
 
-    let response = client
+    let request = client
         .get(url)
         .header("Content-Type", "application/json")
-        .header("X-API-Key", api_key)
-        .send()
-        .await?;
+        .header("X-API-Key", credentials.api_key);
+    let response = send("gluetun", &credentials.name, request)
+        .await?
+        .error_for_status()?;
 
     Ok(response)
 }
 
 pub async fn get_vpn_public_ip(
-    base_url: String,
-    api_key: String,
-) -> Result<GluetunV1VPNPublicIP, Box<dyn Error>> {
+    credentials: APIKeyConfig,
+) -> Result<GluetunV1VPNPublicIP, ServiceError> {
     let response = req(
-        base_url,
         String::from("publicip/ip"),
-        api_key,
+        credentials,
         String::from("get"),
         None,
     )
     .await?;
-    let data = response.json::<GluetunV1VPNPublicIP>().await?;
+    let data = json::<GluetunV1VPNPublicIP>(response).await?;
 
     Ok(data)
 }
 
-pub async fn get_vpn_status(
-    base_url: String,
-    api_key: String,
-) -> Result<GluetunV1VPNStatus, Box<dyn Error>> {
+pub async fn get_vpn_status(credentials: APIKeyConfig) -> Result<GluetunV1VPNStatus, ServiceError> {
     let response = req(
-        base_url,
         String::from("vpn/status"),
-        api_key,
+        credentials,
         String::from("get"),
         None,
     )
     .await?;
-    let data = response.json::<GluetunV1VPNStatus>().await?;
+    let data = json::<GluetunV1VPNStatus>(response).await?;
 
     Ok(data)
 }
 
 pub async fn put_vpn_reconnect(
-    base_url: String,
-    api_key: String,
-) -> Result<Option<GluetunV1VPNOutcome>, Box<dyn Error>> {
+    credentials: APIKeyConfig,
+) -> Result<Option<GluetunV1VPNOutcome>, ServiceError> {
     let response = req(
-        base_url.clone(),
         String::from("vpn/status"),
-        api_key.clone(),
+        credentials.clone(),
         String::from("put"),
         Some(String::from("{\"status\": \"stopped\"}")),
     )
     .await?;
-    let data = response.json::<GluetunV1VPNOutcome>().await?;
+    let data = json::<GluetunV1VPNOutcome>(response).await?;
 
     if &data.outcome == "stopped" {
         let response = req(
-            base_url.clone(),
             String::from("vpn/status"),
-            api_key.clone(),
+            credentials.clone(),
             String::from("put"),
             Some(String::from("{\"status\": \"running\"}")),
         )
         .await?;
-        let data = response.json::<GluetunV1VPNOutcome>().await?;
+        let data = json::<GluetunV1VPNOutcome>(response).await?;
 
         return Ok(Some(data));
     }
@@ -121,3 +117,25 @@ pub async fn put_vpn_reconnect(
     // Failed to reconnect
     Ok(None)
 }
+
+pub static GLUETUN: Service<APIKeyConfig> = Service {
+    id: "gluetun",
+    name: "Gluetun",
+    pinged: false,
+    instances: |config| &config.gluetun,
+    instances_mut: |config| &mut config.gluetun,
+    probe: |gluetun| {
+        Box::pin(async move {
+            get_vpn_status(gluetun).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[
+        ("vpn_status", |gluetun| {
+            Box::pin(async move { to_value(get_vpn_status(gluetun).await?) })
+        }),
+        ("vpn_public_ip", |gluetun| {
+            Box::pin(async move { to_value(get_vpn_public_ip(gluetun).await?) })
+        }),
+    ],
+};
@@ -1,3 +1,15 @@
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::time::Instant;
+use tracing::{debug, debug_span, warn, Instrument};
+use tuono_lib::axum::http::StatusCode;
+use tuono_lib::axum::response::{IntoResponse, Response as HttpResponse};
+use tuono_lib::axum::Json;
+use tuono_lib::Type;
+
 pub mod adguard;
 pub mod dockwatch;
 pub mod gluetun;
@@ -7,7 +19,232 @@ pub mod prowlarr;
 pub mod proxmox;
 pub mod qbittorrent;
 pub mod radarr;
+pub mod registry;
 pub mod sonarr;
 pub mod tautulli;
 pub mod tmdb;
 pub mod tvdb;
+
+const BODY_EXCERPT_LENGTH: usize = 200;
+
+#[derive(Debug, Clone)]
+pub enum ServiceError {
+    // The upstream rejected the API key, token or credentials
+    Unauthorized,
+    // DNS, connection or TLS failure
+    Unreachable(String),
+    Timeout,
+    UpstreamStatus(u16),
+    // The response couldn't be parsed, body holds the start of it when available
+    Decode { message: String, body: String },
+    // The instance doesn't exist or isn't enabled
+    Disabled,
+    // Anything on our side, e.g. the database
+    Internal(String),
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ServiceErrorBody {
+    pub service: String,
+    pub error: String,
+    pub message: String,
+    pub status: Option<u16>,
+    pub body: Option<String>,
+}
+
+impl ServiceError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ServiceError::Unauthorized => "unauthorized",
+            ServiceError::Unreachable(_) => "unreachable",
+            ServiceError::Timeout => "timeout",
+            ServiceError::UpstreamStatus(_) => "upstream_status",
+            ServiceError::Decode { .. } => "decode",
+            ServiceError::Disabled => "disabled",
+            ServiceError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ServiceError::Unauthorized
+            | ServiceError::UpstreamStatus(_)
+            | ServiceError::Decode { .. } => StatusCode::BAD_GATEWAY,
+            ServiceError::Unreachable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ServiceError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            ServiceError::Disabled => StatusCode::NOT_FOUND,
+            ServiceError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // JSON error response for a route, service is the display name, e.g. "Radarr (4k)"
+    pub fn response(self, service: &str) -> HttpResponse {
+        let body = ServiceErrorBody {
+            service: String::from(service),
+            error: String::from(self.kind()),
+            message: self.to_string(),
+            status: match self {
+                ServiceError::UpstreamStatus(code) => Some(code),
+                _ => None,
+            },
+            body: match &self {
+                ServiceError::Decode { body, .. } if !body.is_empty() => Some(body.clone()),
+                _ => None,
+            },
+        };
+
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::Unauthorized => write!(f, "API key or credentials rejected"),
+            ServiceError::Unreachable(reason) => write!(f, "unreachable: {}", reason),
+            ServiceError::Timeout => write!(f, "request timed out"),
+            ServiceError::UpstreamStatus(code) => {
+                write!(f, "unexpected response status {}", code)
+            }
+            ServiceError::Decode { message, .. } => write!(f, "unexpected response: {}", message),
+            ServiceError::Disabled => write!(f, "not configured or not enabled"),
+            ServiceError::Internal(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ServiceError {}
+
+fn root_cause(e: &(dyn Error + 'static)) -> String {
+    let mut cause = e;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+
+    cause.to_string()
+}
+
+fn excerpt(body: &str) -> String {
+    body.trim().chars().take(BODY_EXCERPT_LENGTH).collect()
+}
+
+impl From<reqwest::Error> for ServiceError {
+    fn from(e: reqwest::Error) -> Self {
+        if let Some(status) = e.status() {
+            return match status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ServiceError::Unauthorized,
+                _ => ServiceError::UpstreamStatus(status.as_u16()),
+            };
+        }
+
+        if e.is_timeout() {
+            return ServiceError::Timeout;
+        }
+
+        if e.is_decode() {
+            return ServiceError::Decode {
+                message: root_cause(&e),
+                body: String::new(),
+            };
+        }
+
+        let cause = root_cause(&e);
+        let lowercase = cause.to_lowercase();
+        if ["certificate", "tls", "ssl", "handshake"]
+            .iter()
+            .any(|hint| lowercase.contains(hint))
+        {
+            return ServiceError::Unreachable(format!("TLS error: {}", cause));
+        }
+
+        ServiceError::Unreachable(cause)
+    }
+}
+
+impl From<serde_json::Error> for ServiceError {
+    fn from(e: serde_json::Error) -> Self {
+        ServiceError::Decode {
+            message: e.to_string(),
+            body: String::new(),
+        }
+    }
+}
+
+impl From<quick_xml::Error> for ServiceError {
+    fn from(e: quick_xml::Error) -> Self {
+        ServiceError::Decode {
+            message: e.to_string(),
+            body: String::new(),
+        }
+    }
+}
+
+impl From<std::num::ParseIntError> for ServiceError {
+    fn from(e: std::num::ParseIntError) -> Self {
+        ServiceError::Decode {
+            message: e.to_string(),
+            body: String::new(),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ServiceError {
+    fn from(e: rusqlite::Error) -> Self {
+        ServiceError::Internal(format!("database error: {}", e))
+    }
+}
+
+// Database helpers still return boxed errors
+impl From<Box<dyn Error>> for ServiceError {
+    fn from(e: Box<dyn Error>) -> Self {
+        ServiceError::Internal(e.to_string())
+    }
+}
+
+// Like Response::json, but keeps the start of the body when it doesn't parse
+pub async fn json<T: DeserializeOwned>(response: Response) -> Result<T, ServiceError> {
+    let body = response.text().await?;
+
+    serde_json::from_str(&body).map_err(|e| ServiceError::Decode {
+        message: e.to_string(),
+        body: excerpt(&body),
+    })
+}
+
+// Sends an integration request inside an "upstream" span with its latency, the URL is redacted when logged
+pub async fn send(
+    service: &str,
+    instance: &str,
+    request: RequestBuilder,
+) -> Result<Response, reqwest::Error> {
+    let (client, request) = request.build_split();
+    let request = request?;
+    let (method, url) = (request.method().clone(), request.url().clone());
+    let span = debug_span!("upstream", service, instance, %method, %url);
+
+    async move {
+        let started = Instant::now();
+        let result = client.execute(request).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        match &result {
+            Ok(response) => debug!(
+                status = response.status().as_u16(),
+                latency_ms, "Upstream response"
+            ),
+            Err(e) => warn!(
+                service,
+                instance,
+                %method,
+                %url,
+                latency_ms,
+                "Upstream request failed: {}",
+                e
+            ),
+        }
+
+        result
+    }
+    .instrument(span)
+    .await
+}
@@ -1,8 +1,12 @@
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::http_client;
+use crate::config::APIKeyConfig;
+use crate::services::registry::{to_value, Service};
+use crate::services::{json, send, ServiceError};
+
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct OverseerrV1Media {
@@ -37,34 +41,64 @@ pub struct OverseerrV1RequestsCount {
     processing: u32,
 }
 
-async fn req(
-    base_url: String,
-    endpoint: String,
-    api_key: String,
-) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!("{}/api/v1/{}", base_url, endpoint);
-    let response = client.get(url).header("X-Api-Key", api_key).send().await?;
+impl OverseerrV1Requests {
+    // Media type and id the dashboard looks the poster up by, e.g. ("movie", 603)
+    pub fn media_ids(&self) -> Vec<(String, u32)> {
+        self.results
+            .iter()
+            .filter_map(|request| {
+                let id = request.media.tvdbId.or(request.media.tmdbId)?;
+                Some((request.media.mediaType.clone(), id))
+            })
+            .collect()
+    }
+}
+
+async fn req(endpoint: String, credentials: APIKeyConfig) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
+    let url = format!("{}/api/v1/{}", credentials.url, endpoint);
+    let request = client.get(url).header("X-Api-Key", credentials.api_key);
+    let response = send("overseerr", &credentials.name, request)
+        .await?
+        .error_for_status()?;
 
     Ok(response)
 }
 
-pub async fn get_requests(
-    base_url: String,
-    api_key: String,
-) -> Result<OverseerrV1Requests, Box<dyn Error>> {
-    let response = req(base_url, String::from("request"), api_key).await?;
-    let data = response.json::<OverseerrV1Requests>().await?;
+pub async fn get_requests(credentials: APIKeyConfig) -> Result<OverseerrV1Requests, ServiceError> {
+    let response = req(String::from("request"), credentials).await?;
+    let data = json::<OverseerrV1Requests>(response).await?;
 
     Ok(data)
 }
 
 pub async fn get_requests_count(
-    base_url: String,
-    api_key: String,
-) -> Result<OverseerrV1RequestsCount, Box<dyn Error>> {
-    let response = req(base_url, String::from("request/count"), api_key).await?;
-    let data = response.json::<OverseerrV1RequestsCount>().await?;
+    credentials: APIKeyConfig,
+) -> Result<OverseerrV1RequestsCount, ServiceError> {
+    let response = req(String::from("request/count"), credentials).await?;
+    let data = json::<OverseerrV1RequestsCount>(response).await?;
 
     Ok(data)
 }
+
+pub static OVERSEERR: Service<APIKeyConfig> = Service {
+    id: "overseerr",
+    name: "Overseerr",
+    pinged: true,
+    instances: |config| &config.overseerr,
+    instances_mut: |config| &mut config.overseerr,
+    probe: |overseerr| {
+        Box::pin(async move {
+            get_requests_count(overseerr).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[
+        ("requests", |overseerr| {
+            Box::pin(async move { to_value(get_requests(overseerr).await?) })
+        }),
+        ("requests_count", |overseerr| {
+            Box::pin(async move { to_value(get_requests_count(overseerr).await?) })
+        }),
+    ],
+};
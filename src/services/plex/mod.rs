@@ -1,9 +1,13 @@
 use quick_xml::{events::Event, Reader};
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::http_client;
+use crate::config::APIKeyConfig;
+use crate::services::registry::{to_value, Service};
+use crate::services::{send, ServiceError};
+
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct PlexLibraryCount {
@@ -19,20 +23,17 @@ pub struct PlexLibrary {
     pub key: u32,
 }
 
-async fn req(
-    base_url: String,
-    endpoint: String,
-    api_key: String,
-) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!("{}/{}", base_url, endpoint);
+async fn req(endpoint: String, credentials: APIKeyConfig) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
+    let url = format!("{}/{}", credentials.url, endpoint);
 
-    let response = client
+    let request = client
         .get(url)
-        .query(&[("X-Plex-Token", &api_key)])
-        .header("X-Plex-Token", &api_key)
-        .send()
-        .await?;
+        .query(&[("X-Plex-Token", &credentials.api_key)])
+        .header("X-Plex-Token", &credentials.api_key);
+    let response = send("plex", &credentials.name, request)
+        .await?
+        .error_for_status()?;
 
     Ok(response)
 }
@@ -43,11 +44,10 @@ fn get_attribute(e: &quick_xml::events::BytesStart, attr_name: &[u8]) -> Option<
         .map(|a| a.unwrap().value.into_owned())
 }
 
-async fn get_libraries_xml(
-    base_url: String,
-    api_key: String,
-) -> Result<Vec<PlexLibrary>, Box<dyn Error>> {
-    let response = req(base_url, String::from("library/sections"), api_key).await?;
+pub async fn get_libraries_xml(
+    credentials: APIKeyConfig,
+) -> Result<Vec<PlexLibrary>, ServiceError> {
+    let response = req(String::from("library/sections"), credentials).await?;
     let xml = response.text().await?;
 
     let mut reader = Reader::from_str(&xml);
@@ -75,7 +75,7 @@ async fn get_libraries_xml(
                 _ => (),
             },
             Ok(Event::Eof) => break,
-            Err(e) => return Err(Box::new(e)),
+            Err(e) => return Err(e.into()),
             _ => (),
         }
     }
@@ -84,16 +84,10 @@ async fn get_libraries_xml(
 }
 
 async fn get_library_items(
-    base_url: String,
-    api_key: String,
+    credentials: APIKeyConfig,
     section_key: u32,
-) -> Result<u32, Box<dyn Error>> {
-    let response = req(
-        base_url,
-        format!("library/sections/{}/all", section_key),
-        api_key,
-    )
-    .await?;
+) -> Result<u32, ServiceError> {
+    let response = req(format!("library/sections/{}/all", section_key), credentials).await?;
     let xml = response.text().await?;
 
     let mut reader = Reader::from_str(&xml);
@@ -108,7 +102,7 @@ async fn get_library_items(
                 _ => (),
             },
             Ok(Event::Eof) => break,
-            Err(e) => return Err(Box::new(e)),
+            Err(e) => return Err(e.into()),
             _ => (),
         }
     }
@@ -117,16 +111,15 @@ async fn get_library_items(
 }
 
 pub async fn get_library_media_count(
-    base_url: String,
-    api_key: String,
-) -> Result<PlexLibraryCount, Box<dyn Error>> {
+    credentials: APIKeyConfig,
+) -> Result<PlexLibraryCount, ServiceError> {
     let mut data: PlexLibraryCount = PlexLibraryCount {
         movies: 0,
         shows: 0,
     };
-    let libraries = get_libraries_xml(base_url.clone(), api_key.clone()).await?;
+    let libraries = get_libraries_xml(credentials.clone()).await?;
     for library in libraries {
-        let count = get_library_items(base_url.clone(), api_key.clone(), library.key).await?;
+        let count = get_library_items(credentials.clone(), library.key).await?;
         match library.library_type.as_str() {
             "movie" => data.movies += count,
             "show" => data.shows += count,
@@ -136,3 +129,20 @@ pub async fn get_library_media_count(
 
     Ok(data)
 }
+
+pub static PLEX: Service<APIKeyConfig> = Service {
+    id: "plex",
+    name: "Plex",
+    pinged: true,
+    instances: |config| &config.plex,
+    instances_mut: |config| &mut config.plex,
+    probe: |plex| {
+        Box::pin(async move {
+            get_libraries_xml(plex).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[("library_count", |plex| {
+        Box::pin(async move { to_value(get_library_media_count(plex).await?) })
+    })],
+};
@@ -1,8 +1,12 @@
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::http_client;
+use crate::config::APIKeyConfig;
+use crate::services::registry::{to_value, Service};
+use crate::services::{json, send, ServiceError};
+
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct ProwlarrV1Hosts {
@@ -17,24 +21,39 @@ pub struct ProwlarrV1IndexerStats {
     pub hosts: Vec<ProwlarrV1Hosts>,
 }
 
-async fn req(
-    base_url: String,
-    endpoint: String,
-    api_key: String,
-) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!("{}/api/v1/{}", base_url, endpoint);
-    let response = client.get(url).query(&[("apikey", api_key)]).send().await?;
+async fn req(endpoint: String, credentials: APIKeyConfig) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
+    let url = format!("{}/api/v1/{}", credentials.url, endpoint);
+    let request = client.get(url).query(&[("apikey", credentials.api_key)]);
+    let response = send("prowlarr", &credentials.name, request)
+        .await?
+        .error_for_status()?;
 
     Ok(response)
 }
 
 pub async fn get_indexer_stats(
-    base_url: String,
-    api_key: String,
-) -> Result<ProwlarrV1IndexerStats, Box<dyn Error>> {
-    let response = req(base_url, String::from("indexerstats"), api_key).await?;
-    let data = response.json::<ProwlarrV1IndexerStats>().await?;
+    credentials: APIKeyConfig,
+) -> Result<ProwlarrV1IndexerStats, ServiceError> {
+    let response = req(String::from("indexerstats"), credentials).await?;
+    let data = json::<ProwlarrV1IndexerStats>(response).await?;
 
     Ok(data)
 }
+
+pub static PROWLARR: Service<APIKeyConfig> = Service {
+    id: "prowlarr",
+    name: "Prowlarr",
+    pinged: true,
+    instances: |config| &config.prowlarr,
+    instances_mut: |config| &mut config.prowlarr,
+    probe: |prowlarr| {
+        Box::pin(async move {
+            get_indexer_stats(prowlarr).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[("stats", |prowlarr| {
+        Box::pin(async move { to_value(get_indexer_stats(prowlarr).await?) })
+    })],
+};
@@ -1,9 +1,11 @@
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::http_client;
 use crate::config::APICredsConfig;
+use crate::services::registry::{to_value, Service};
+use crate::services::{json, send, ServiceError};
 
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
@@ -20,31 +22,31 @@ pub struct ProxmoxV2Nodes {
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct ProxmoxV2Data {
-    node: String,
-    status: ProxmoxV2NodeStatus,
+    pub node: String,
+    pub status: ProxmoxV2NodeStatus,
     lxc: ProxmoxV2NodeLXC,
     qemu: ProxmoxV2NodeQEMU,
-    storage: ProxmoxV2NodeStorage,
+    pub storage: ProxmoxV2NodeStorage,
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct ProxmoxV2NodeStatusMemory {
-    used: f32,
-    total: f32,
+    pub used: f32,
+    pub total: f32,
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct ProxmoxV2NodeStatusData {
-    cpu: f32,
-    memory: ProxmoxV2NodeStatusMemory,
+    pub cpu: f32,
+    pub memory: ProxmoxV2NodeStatusMemory,
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct ProxmoxV2NodeStatus {
-    data: ProxmoxV2NodeStatusData,
+    pub data: ProxmoxV2NodeStatusData,
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
@@ -76,24 +78,22 @@ pub struct ProxmoxV2NodeQEMU {
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct ProxmoxV2NodeStorageData {
-    total: u64,
-    used: u64,
-    storage: String,
+    pub total: u64,
+    pub used: u64,
+    pub storage: String,
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct ProxmoxV2NodeStorage {
-    data: Vec<ProxmoxV2NodeStorageData>,
+    pub data: Vec<ProxmoxV2NodeStorageData>,
 }
 
-async fn req(endpoint: String, credentials: APICredsConfig) -> Result<Response, Box<dyn Error>> {
-    let client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .build()?;
+async fn req(endpoint: String, credentials: APICredsConfig) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
     let url = format!("{}/api2/{}", &credentials.url, endpoint);
 
-    let response = client
+    let request = client
         .get(&url)
         .header("Content-Type", "application/json")
         .header(
@@ -102,18 +102,19 @@ async fn req(endpoint: String, credentials: APICredsConfig) -> Result<Response,
                 "Bearer PVEAPIToken={}",
                 format!("{}={}", &credentials.username, &credentials.password)
             ),
-        )
-        .send()
-        .await?;
+        );
+    let response = send("proxmox", &credentials.name, request)
+        .await?
+        .error_for_status()?;
 
     Ok(response)
 }
 
 async fn get_proxmox_nodes(
     credentials: APICredsConfig,
-) -> Result<ProxmoxV2NodesData, Box<dyn Error>> {
+) -> Result<ProxmoxV2NodesData, ServiceError> {
     let response = req(String::from("json/nodes"), credentials).await?;
-    let data = response.json::<ProxmoxV2NodesData>().await?;
+    let data = json::<ProxmoxV2NodesData>(response).await?;
 
     Ok(data)
 }
@@ -121,9 +122,9 @@ async fn get_proxmox_nodes(
 async fn get_proxmox_node_status(
     credentials: APICredsConfig,
     node: String,
-) -> Result<ProxmoxV2NodeStatus, Box<dyn Error>> {
+) -> Result<ProxmoxV2NodeStatus, ServiceError> {
     let response = req(format!("json/nodes/{}/status", &node), credentials).await?;
-    let data = response.json::<ProxmoxV2NodeStatus>().await?;
+    let data = json::<ProxmoxV2NodeStatus>(response).await?;
 
     Ok(data)
 }
@@ -131,9 +132,9 @@ async fn get_proxmox_node_status(
 async fn get_proxmox_node_lxc(
     credentials: APICredsConfig,
     node: String,
-) -> Result<ProxmoxV2NodeLXC, Box<dyn Error>> {
+) -> Result<ProxmoxV2NodeLXC, ServiceError> {
     let response = req(format!("json/nodes/{}/lxc", &node), credentials).await?;
-    let data = response.json::<ProxmoxV2NodeLXC>().await?;
+    let data = json::<ProxmoxV2NodeLXC>(response).await?;
 
     Ok(data)
 }
@@ -141,9 +142,9 @@ async fn get_proxmox_node_lxc(
 async fn get_proxmox_node_qemu(
     credentials: APICredsConfig,
     node: String,
-) -> Result<ProxmoxV2NodeQEMU, Box<dyn Error>> {
+) -> Result<ProxmoxV2NodeQEMU, ServiceError> {
     let response = req(format!("json/nodes/{}/qemu", &node), credentials).await?;
-    let data = response.json::<ProxmoxV2NodeQEMU>().await?;
+    let data = json::<ProxmoxV2NodeQEMU>(response).await?;
 
     Ok(data)
 }
@@ -151,16 +152,16 @@ async fn get_proxmox_node_qemu(
 async fn get_proxmox_node_storage(
     credentials: APICredsConfig,
     node: String,
-) -> Result<ProxmoxV2NodeStorage, Box<dyn Error>> {
+) -> Result<ProxmoxV2NodeStorage, ServiceError> {
     let response = req(format!("json/nodes/{}/storage", &node), credentials).await?;
-    let data = response.json::<ProxmoxV2NodeStorage>().await?;
+    let data = json::<ProxmoxV2NodeStorage>(response).await?;
 
     Ok(data)
 }
 
 pub async fn get_proxmox_data(
     credentials: APICredsConfig,
-) -> Result<Vec<ProxmoxV2Data>, Box<dyn Error>> {
+) -> Result<Vec<ProxmoxV2Data>, ServiceError> {
     let mut data: Vec<ProxmoxV2Data> = Vec::new();
 
     let nodes = get_proxmox_nodes(credentials.clone()).await?;
@@ -181,3 +182,20 @@ pub async fn get_proxmox_data(
 
     Ok(data)
 }
+
+pub static PROXMOX: Service<APICredsConfig> = Service {
+    id: "proxmox",
+    name: "Proxmox",
+    pinged: true,
+    instances: |config| &config.proxmox,
+    instances_mut: |config| &mut config.proxmox,
+    probe: |proxmox| {
+        Box::pin(async move {
+            get_proxmox_nodes(proxmox).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[("node", |proxmox| {
+        Box::pin(async move { to_value(get_proxmox_data(proxmox).await?) })
+    })],
+};
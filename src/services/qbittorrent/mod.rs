@@ -1,11 +1,13 @@
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
 use crate::{
+    client::http_client,
     config::APICredsConfig,
-    cookie::{retrieve_cookie, CookieFields},
+    cookie::{cached_cookie, retrieve_cookie, CookieFields},
+    services::registry::{to_value, Service},
+    services::{json, send, ServiceError},
 };
 
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
@@ -23,17 +25,19 @@ pub struct QBitV2Torrent {
     pub ratio: f32,
 }
 
-async fn req(endpoint: String, credentials: APICredsConfig) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
+async fn req(endpoint: String, credentials: APICredsConfig) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
     let url = format!("{}/api/v2/{}", &credentials.url, endpoint);
 
     // Try with cached cookie first
-    let response = client
+    let request = client
         .get(&url)
         .header("Content-Type", "application/json")
-        .header("Cookie", credentials.cookie.clone().unwrap_or_default())
-        .send()
-        .await?;
+        .header(
+            "Cookie",
+            cached_cookie("qbittorrent", &credentials.name).unwrap_or_default(),
+        );
+    let response = send("qbittorrent", &credentials.name, request).await?;
 
     // Only get new cookie if cached one fails
     if response.status() == 403 {
@@ -45,22 +49,38 @@ async fn req(endpoint: String, credentials: APICredsConfig) -> Result<Response,
         )
         .await?;
 
-        return Ok(client
+        let request = client
             .get(url)
             .header("Content-Type", "application/json")
-            .header("Cookie", cookie_result.cookie)
-            .send()
-            .await?);
+            .header("Cookie", cookie_result.cookie);
+        return Ok(send("qbittorrent", &credentials.name, request)
+            .await?
+            .error_for_status()?);
     }
 
-    Ok(response)
+    Ok(response.error_for_status()?)
 }
 
-pub async fn get_torrents(
-    credentials: APICredsConfig,
-) -> Result<Vec<QBitV2Torrent>, Box<dyn Error>> {
+pub async fn get_torrents(credentials: APICredsConfig) -> Result<Vec<QBitV2Torrent>, ServiceError> {
     let response = req(String::from("torrents/info"), credentials).await?;
-    let data = response.json::<Vec<QBitV2Torrent>>().await?;
+    let data = json::<Vec<QBitV2Torrent>>(response).await?;
 
     Ok(data)
 }
+
+pub static QBITTORRENT: Service<APICredsConfig> = Service {
+    id: "qbittorrent",
+    name: "qBittorrent",
+    pinged: true,
+    instances: |config| &config.qbittorrent,
+    instances_mut: |config| &mut config.qbittorrent,
+    probe: |qbittorrent| {
+        Box::pin(async move {
+            get_torrents(qbittorrent).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[("torrents", |qbittorrent| {
+        Box::pin(async move { to_value(get_torrents(qbittorrent).await?) })
+    })],
+};
@@ -1,8 +1,12 @@
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::http_client;
+use crate::config::APIKeyConfig;
+use crate::services::registry::{to_value, Service};
+use crate::services::{json, send, ServiceError};
+
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct RadarrV3Movies {
@@ -19,34 +23,51 @@ pub struct RadarrV3WantedMissing {
     pub totalRecords: u32,
 }
 
-async fn req(
-    base_url: String,
-    endpoint: String,
-    api_key: String,
-) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!("{}/api/v3/{}", base_url, endpoint);
-    let response = client.get(url).query(&[("apikey", api_key)]).send().await?;
+async fn req(endpoint: String, credentials: APIKeyConfig) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
+    let url = format!("{}/api/v3/{}", credentials.url, endpoint);
+    let request = client.get(url).query(&[("apikey", credentials.api_key)]);
+    let response = send("radarr", &credentials.name, request)
+        .await?
+        .error_for_status()?;
 
     Ok(response)
 }
 
-pub async fn get_movies(
-    base_url: String,
-    api_key: String,
-) -> Result<Vec<RadarrV3Movies>, Box<dyn Error>> {
-    let response = req(base_url, String::from("movie"), api_key).await?;
-    let data = response.json::<Vec<RadarrV3Movies>>().await?;
+pub async fn get_movies(credentials: APIKeyConfig) -> Result<Vec<RadarrV3Movies>, ServiceError> {
+    let response = req(String::from("movie"), credentials).await?;
+    let data = json::<Vec<RadarrV3Movies>>(response).await?;
 
     Ok(data)
 }
 
 pub async fn get_wanted_missing(
-    base_url: String,
-    api_key: String,
-) -> Result<RadarrV3WantedMissing, Box<dyn Error>> {
-    let response = req(base_url, String::from("wanted/missing"), api_key).await?;
-    let data = response.json::<RadarrV3WantedMissing>().await?;
+    credentials: APIKeyConfig,
+) -> Result<RadarrV3WantedMissing, ServiceError> {
+    let response = req(String::from("wanted/missing"), credentials).await?;
+    let data = json::<RadarrV3WantedMissing>(response).await?;
 
     Ok(data)
 }
+
+pub static RADARR: Service<APIKeyConfig> = Service {
+    id: "radarr",
+    name: "Radarr",
+    pinged: true,
+    instances: |config| &config.radarr,
+    instances_mut: |config| &mut config.radarr,
+    probe: |radarr| {
+        Box::pin(async move {
+            get_wanted_missing(radarr).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[
+        ("movies", |radarr| {
+            Box::pin(async move { to_value(get_movies(radarr).await?) })
+        }),
+        ("wanted_missing", |radarr| {
+            Box::pin(async move { to_value(get_wanted_missing(radarr).await?) })
+        }),
+    ],
+};
@@ -0,0 +1,154 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::config::{find_instance, APICredsConfig, APIKeyConfig, ConfigFields, ServiceInstance};
+use crate::services::{
+    adguard, dockwatch, gluetun, overseerr, plex, prowlarr, proxmox, qbittorrent, radarr, sonarr,
+    tautulli, ServiceError,
+};
+use crate::validation::{check_creds_instances, check_key_instances, ConfigIssue};
+
+pub type ServiceFuture<T> = Pin<Box<dyn Future<Output = Result<T, ServiceError>> + Send>>;
+
+// Everything the rest of the app needs to know about an integration
+pub trait ServiceClient: Sync {
+    // Config section and route segment, e.g. "qbittorrent"
+    fn id(&self) -> &'static str;
+    // Name shown on the dashboard, e.g. "qBittorrent"
+    fn name(&self) -> &'static str;
+    // Whether instances get a row in the services table and are pinged
+    fn pinged(&self) -> bool;
+    fn instances<'a>(&self, config: &'a ConfigFields) -> Vec<&'a dyn ServiceInstance>;
+    // Resolves an instance selector like find_instance does
+    fn find<'a>(&self, config: &'a ConfigFields, selector: &str)
+        -> Option<&'a dyn ServiceInstance>;
+    fn add_default_instance(&self, config: &mut ConfigFields);
+    fn validate(&self, config: &ConfigFields, issues: &mut Vec<ConfigIssue>);
+    // Cheapest authenticated call, used to check an instance works
+    fn probe(&self, config: &ConfigFields, instance: &str) -> ServiceFuture<()>;
+    // Cacheable API endpoints, named like the routes under /api/<id>/<instance>/
+    fn endpoints(&self) -> Vec<&'static str>;
+    fn fetch(&self, config: &ConfigFields, instance: &str, endpoint: &str) -> ServiceFuture<Value>;
+}
+
+// Config types a service instance can use
+pub trait InstanceConfig: ServiceInstance + Default + Clone + Send + 'static {
+    fn check_instances(issues: &mut Vec<ConfigIssue>, section: &str, instances: &[Self]);
+}
+
+impl InstanceConfig for APIKeyConfig {
+    fn check_instances(issues: &mut Vec<ConfigIssue>, section: &str, instances: &[Self]) {
+        check_key_instances(issues, section, instances)
+    }
+}
+
+impl InstanceConfig for APICredsConfig {
+    fn check_instances(issues: &mut Vec<ConfigIssue>, section: &str, instances: &[Self]) {
+        check_creds_instances(issues, section, instances)
+    }
+}
+
+// Endpoint name and the function fetching it for an instance
+pub type Endpoint<T> = (&'static str, fn(T) -> ServiceFuture<Value>);
+
+pub struct Service<T: InstanceConfig> {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub pinged: bool,
+    pub instances: fn(&ConfigFields) -> &Vec<T>,
+    pub instances_mut: fn(&mut ConfigFields) -> &mut Vec<T>,
+    pub probe: fn(T) -> ServiceFuture<()>,
+    pub endpoints: &'static [Endpoint<T>],
+}
+
+impl<T: InstanceConfig> ServiceClient for Service<T> {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn pinged(&self) -> bool {
+        self.pinged
+    }
+
+    fn instances<'a>(&self, config: &'a ConfigFields) -> Vec<&'a dyn ServiceInstance> {
+        (self.instances)(config)
+            .iter()
+            .map(|instance| instance as &dyn ServiceInstance)
+            .collect()
+    }
+
+    fn find<'a>(
+        &self,
+        config: &'a ConfigFields,
+        selector: &str,
+    ) -> Option<&'a dyn ServiceInstance> {
+        find_instance((self.instances)(config), selector)
+            .map(|instance| instance as &dyn ServiceInstance)
+    }
+
+    fn add_default_instance(&self, config: &mut ConfigFields) {
+        (self.instances_mut)(config).push(T::default());
+    }
+
+    fn validate(&self, config: &ConfigFields, issues: &mut Vec<ConfigIssue>) {
+        T::check_instances(issues, self.id, (self.instances)(config));
+    }
+
+    fn probe(&self, config: &ConfigFields, instance: &str) -> ServiceFuture<()> {
+        match find_instance((self.instances)(config), instance) {
+            Some(instance) => (self.probe)(instance.clone()),
+            None => Box::pin(async { Err(ServiceError::Disabled) }),
+        }
+    }
+
+    fn endpoints(&self) -> Vec<&'static str> {
+        self.endpoints
+            .iter()
+            .map(|(endpoint, _)| *endpoint)
+            .collect()
+    }
+
+    fn fetch(&self, config: &ConfigFields, instance: &str, endpoint: &str) -> ServiceFuture<Value> {
+        let fetch = self
+            .endpoints
+            .iter()
+            .find(|(name, _)| *name == endpoint)
+            .map(|(_, fetch)| fetch);
+
+        match (find_instance((self.instances)(config), instance), fetch) {
+            (Some(instance), Some(fetch)) => fetch(instance.clone()),
+            _ => Box::pin(async { Err(ServiceError::Disabled) }),
+        }
+    }
+}
+
+// Serializes endpoint data for ServiceClient::fetch
+pub fn to_value<T: Serialize>(data: T) -> Result<Value, ServiceError> {
+    Ok(serde_json::to_value(data)?)
+}
+
+// Every integration, in dashboard order
+// Besides an entry here a service needs its section in ConfigFields, the rest is derived from this list
+pub static SERVICES: [&dyn ServiceClient; 11] = [
+    &sonarr::SONARR,
+    &radarr::RADARR,
+    &prowlarr::PROWLARR,
+    &overseerr::OVERSEERR,
+    &plex::PLEX,
+    &tautulli::TAUTULLI,
+    &dockwatch::DOCKWATCH,
+    &qbittorrent::QBITTORRENT,
+    &proxmox::PROXMOX,
+    &adguard::ADGUARD,
+    &gluetun::GLUETUN,
+];
+
+pub fn find_service(id: &str) -> Option<&'static dyn ServiceClient> {
+    SERVICES.iter().copied().find(|service| service.id() == id)
+}
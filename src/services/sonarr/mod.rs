@@ -1,8 +1,12 @@
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::http_client;
+use crate::config::APIKeyConfig;
+use crate::services::registry::{to_value, Service};
+use crate::services::{json, send, ServiceError};
+
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct SonarrV3Series {
@@ -20,34 +24,51 @@ pub struct SonarrV3WantedMissing {
     pub totalRecords: u32,
 }
 
-async fn req(
-    base_url: String,
-    endpoint: String,
-    api_key: String,
-) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!("{}/api/v3/{}", base_url, endpoint);
-    let response = client.get(url).query(&[("apikey", api_key)]).send().await?;
+async fn req(endpoint: String, credentials: APIKeyConfig) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
+    let url = format!("{}/api/v3/{}", credentials.url, endpoint);
+    let request = client.get(url).query(&[("apikey", credentials.api_key)]);
+    let response = send("sonarr", &credentials.name, request)
+        .await?
+        .error_for_status()?;
 
     Ok(response)
 }
 
-pub async fn get_series(
-    base_url: String,
-    api_key: String,
-) -> Result<Vec<SonarrV3Series>, Box<dyn Error>> {
-    let response = req(base_url, String::from("series"), api_key).await?;
-    let data = response.json::<Vec<SonarrV3Series>>().await?;
+pub async fn get_series(credentials: APIKeyConfig) -> Result<Vec<SonarrV3Series>, ServiceError> {
+    let response = req(String::from("series"), credentials).await?;
+    let data = json::<Vec<SonarrV3Series>>(response).await?;
 
     Ok(data)
 }
 
 pub async fn get_wanted_missing(
-    base_url: String,
-    api_key: String,
-) -> Result<SonarrV3WantedMissing, Box<dyn Error>> {
-    let response = req(base_url, String::from("wanted/missing"), api_key).await?;
-    let data = response.json::<SonarrV3WantedMissing>().await?;
+    credentials: APIKeyConfig,
+) -> Result<SonarrV3WantedMissing, ServiceError> {
+    let response = req(String::from("wanted/missing"), credentials).await?;
+    let data = json::<SonarrV3WantedMissing>(response).await?;
 
     Ok(data)
 }
+
+pub static SONARR: Service<APIKeyConfig> = Service {
+    id: "sonarr",
+    name: "Sonarr",
+    pinged: true,
+    instances: |config| &config.sonarr,
+    instances_mut: |config| &mut config.sonarr,
+    probe: |sonarr| {
+        Box::pin(async move {
+            get_wanted_missing(sonarr).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[
+        ("series", |sonarr| {
+            Box::pin(async move { to_value(get_series(sonarr).await?) })
+        }),
+        ("wanted_missing", |sonarr| {
+            Box::pin(async move { to_value(get_wanted_missing(sonarr).await?) })
+        }),
+    ],
+};
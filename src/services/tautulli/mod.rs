@@ -1,8 +1,12 @@
-use reqwest::{Client, Response}; // Remove blocking
+use reqwest::Response; // Remove blocking
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::http_client;
+use crate::config::APIKeyConfig;
+use crate::services::registry::{to_value, Service};
+use crate::services::{json, send, ServiceError};
+
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
 pub struct TautulliV2Session {
@@ -47,28 +51,41 @@ pub struct TautulliV2Sessions {
     pub response: TautulliV2Response,
 }
 
-async fn req(
-    base_url: String,
-    endpoint: String,
-    api_key: String,
-) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!("{}/api/v2", base_url);
-    let response = client
+async fn req(endpoint: String, credentials: APIKeyConfig) -> Result<Response, ServiceError> {
+    let client = http_client(credentials.verify_tls, &credentials.ca_cert)?;
+    let url = format!("{}/api/v2", credentials.url);
+    let request = client
         .get(url)
-        .query(&[("apikey", api_key), ("cmd", endpoint)])
-        .send()
-        .await?;
+        .query(&[("apikey", credentials.api_key), ("cmd", endpoint)]);
+    let response = send("tautulli", &credentials.name, request)
+        .await?
+        .error_for_status()?;
 
     Ok(response)
 }
 
 pub async fn get_stream_sessions(
-    base_url: String,
-    api_key: String,
-) -> Result<TautulliV2Sessions, Box<dyn Error>> {
-    let response = req(base_url, String::from("get_activity"), api_key).await?;
-    let data = response.json::<TautulliV2Sessions>().await?;
+    credentials: APIKeyConfig,
+) -> Result<TautulliV2Sessions, ServiceError> {
+    let response = req(String::from("get_activity"), credentials).await?;
+    let data = json::<TautulliV2Sessions>(response).await?;
 
     Ok(data)
 }
+
+pub static TAUTULLI: Service<APIKeyConfig> = Service {
+    id: "tautulli",
+    name: "Tautulli",
+    pinged: true,
+    instances: |config| &config.tautulli,
+    instances_mut: |config| &mut config.tautulli,
+    probe: |tautulli| {
+        Box::pin(async move {
+            get_stream_sessions(tautulli).await?;
+            Ok(())
+        })
+    },
+    endpoints: &[("sessions", |tautulli| {
+        Box::pin(async move { to_value(get_stream_sessions(tautulli).await?) })
+    })],
+};
@@ -1,9 +1,10 @@
-use reqwest::{Client, Response}; // Remove blocking
+use reqwest::Response; // Remove blocking
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
+use crate::client::default_client;
 use crate::database::{connect_db, get_tmdb_id, insert_tmdb_id, Poster};
+use crate::services::{json, send, ServiceError};
 
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
 #[allow(unused, non_snake_case)]
@@ -13,19 +14,16 @@ pub struct TMDB3Movie {
     pub id: u32,
 }
 
-async fn req(endpoint: String, api_key: String) -> Result<Response, Box<dyn Error>> {
-    let client = Client::new();
+async fn req(endpoint: String, api_key: String) -> Result<Response, ServiceError> {
+    let client = default_client()?;
     let url = format!("https://api.themoviedb.org/3/{}", endpoint);
-    let response = client
-        .get(url)
-        .query(&[("api_key", api_key)])
-        .send()
-        .await?;
+    let request = client.get(url).query(&[("api_key", api_key)]);
+    let response = send("tmdb", "", request).await?.error_for_status()?;
 
     Ok(response)
 }
 
-pub async fn get_movie_entry(tmdb_id: u32, api_key: String) -> Result<TMDB3Movie, Box<dyn Error>> {
+pub async fn get_movie_entry(tmdb_id: u32, api_key: String) -> Result<TMDB3Movie, ServiceError> {
     let conn = connect_db()?;
 
     // Try to get from cache first
@@ -35,7 +33,7 @@ pub async fn get_movie_entry(tmdb_id: u32, api_key: String) -> Result<TMDB3Movie
 
     // Fetch from API if not in cache
     let response = req(format!("movie/{}", tmdb_id), api_key).await?;
-    let mut movie = response.json::<TMDB3Movie>().await?;
+    let mut movie = json::<TMDB3Movie>(response).await?;
 
     // Update the poster path to include the base URL
     movie.poster_path = format!("https://image.tmdb.org/t/p/original/{}", movie.poster_path);
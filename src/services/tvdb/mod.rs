@@ -1,12 +1,13 @@
-use reqwest::{Client, Response};
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use tuono_lib::Type;
 
 use crate::{
+    client::default_client,
     config::APIOauthConfig,
     database::{connect_db, get_tvdb_id, insert_tvdb_id, Poster},
     oauth::{retrieve_oauth, OAuthFields},
+    services::{json, send, ServiceError},
 };
 
 #[derive(Debug, Deserialize, Clone, Serialize, Type)]
@@ -23,7 +24,7 @@ pub struct TVDBV4SeriesData {
     pub data: TVDBV4Series,
 }
 
-async fn req(endpoint: String, credentials: APIOauthConfig) -> Result<Response, Box<dyn Error>> {
+async fn req(endpoint: String, credentials: APIOauthConfig) -> Result<Response, ServiceError> {
     let oauth = retrieve_oauth(
         &credentials,
         OAuthFields {
@@ -32,13 +33,12 @@ async fn req(endpoint: String, credentials: APIOauthConfig) -> Result<Response,
     )
     .await?;
 
-    let client = Client::new();
+    let client = default_client()?;
     let url = format!("https://api4.thetvdb.com/v4/{}", endpoint);
-    let response = client
+    let request = client
         .get(url)
-        .header("Authorization", format!("Bearer {}", oauth.token))
-        .send()
-        .await?;
+        .header("Authorization", format!("Bearer {}", oauth.token));
+    let response = send("tvdb", "", request).await?.error_for_status()?;
 
     Ok(response)
 }
@@ -46,7 +46,7 @@ async fn req(endpoint: String, credentials: APIOauthConfig) -> Result<Response,
 pub async fn get_series_entry(
     tvdb_id: u32,
     credentials: APIOauthConfig,
-) -> Result<TVDBV4SeriesData, Box<dyn Error>> {
+) -> Result<TVDBV4SeriesData, ServiceError> {
     let conn = connect_db()?;
 
     // Try to get from cache first
@@ -56,7 +56,7 @@ pub async fn get_series_entry(
 
     // Fetch from API if not in cache
     let response = req(format!("series/{}", tvdb_id), credentials).await?;
-    let data = response.json::<TVDBV4SeriesData>().await?;
+    let data = json::<TVDBV4SeriesData>(response).await?;
     let series = data.data.clone();
 
     // Cache the result
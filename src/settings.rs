@@ -0,0 +1,291 @@
+use serde::Serialize;
+use serde_json::Value;
+use subtle::ConstantTimeEq;
+use tuono_lib::axum::http::{header::AUTHORIZATION, HeaderMap, StatusCode};
+use tuono_lib::Type;
+
+use crate::config::{preview_config, read_config_table, stored_config, write_config, ConfigFields};
+use crate::database::{connect_db, populate_tables};
+use crate::logging::is_sensitive_key;
+use crate::services::registry::find_service;
+use crate::validation::{validate_config, ConfigReport};
+
+const REDACTED: &str = "********";
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ConnectionTest {
+    pub instance: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    BadRequest(String),
+    Invalid(ConfigReport),
+    Failed(String),
+}
+
+// Settings are only reachable when enabled and called with "Authorization: Bearer <token>"
+pub fn authorize(headers: &HeaderMap, config: &ConfigFields) -> Result<(), StatusCode> {
+    if !config.settings.enabled || config.settings.token.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    // Compared in constant time so response times don't give the token away
+    match token {
+        Some(token) if bool::from(token.as_bytes().ct_eq(config.settings.token.as_bytes())) => {
+            Ok(())
+        }
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+// The same keys logging redacts, key is the field value sits in
+// Every value of a headers map counts as a secret, e.g. Authorization
+fn redact(value: &mut Value, key: &str) {
+    match value {
+        Value::Object(map) => {
+            for (name, field) in map.iter_mut() {
+                let is_set = field.as_str().is_some_and(|secret| !secret.is_empty());
+                if (key == "headers" || is_sensitive_key(name)) && is_set {
+                    *field = Value::String(String::from(REDACTED));
+                } else {
+                    redact(field, name);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact(item, key)),
+        _ => (),
+    }
+}
+
+// Webhook urls are secrets themselves, e.g. Discord's carry the token
+fn redact_channel_urls(value: &mut Value, config: &ConfigFields) {
+    let Some(channels) = value["alerts"]["channels"].as_array_mut() else {
+        return;
+    };
+
+    for (channel, config) in channels.iter_mut().zip(&config.alerts.channels) {
+        if config.kind.url_is_secret() && !config.url.is_empty() {
+            channel["url"] = Value::String(String::from(REDACTED));
+        }
+    }
+}
+
+// Puts back any value the client sent as "********", taken from config.toml (never from env overrides)
+// List entries are matched by instance name so reordering instances keeps their secrets
+fn restore_secrets(value: &mut Value, current: Option<&Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let current = current.and_then(|current| current.get(key));
+
+                if value.as_str() == Some(REDACTED) {
+                    *value = current.cloned().unwrap_or(Value::String(String::new()));
+                } else {
+                    restore_secrets(value, current);
+                }
+            }
+        }
+        Value::Array(items) => {
+            let current_items = current.and_then(|current| current.as_array());
+
+            for (index, item) in items.iter_mut().enumerate() {
+                let by_name = item.get("name").and_then(|name| {
+                    current_items?
+                        .iter()
+                        .find(|current| current.get("name") == Some(name))
+                });
+                let current = by_name.or(current_items.and_then(|items| items.get(index)));
+
+                restore_secrets(item, current);
+            }
+        }
+        _ => (),
+    }
+}
+
+// Settings as stored in config.toml, environment overrides are left out so they never get saved
+pub fn redacted_config() -> Result<Value, SettingsError> {
+    let config = stored_config().map_err(|e| SettingsError::Failed(e.to_string()))?;
+
+    let mut value = serde_json::to_value(&config).unwrap_or(Value::Null);
+    redact(&mut value, "");
+    redact_channel_urls(&mut value, &config);
+
+    Ok(value)
+}
+
+// Validates and writes new settings, then brings the services table in line with them
+pub fn save_settings(mut value: Value) -> Result<(), SettingsError> {
+    let current = read_config_table()
+        .ok()
+        .and_then(|table| serde_json::to_value(table).ok());
+    restore_secrets(&mut value, current.as_ref());
+
+    let config: ConfigFields =
+        serde_json::from_value(value).map_err(|e| SettingsError::BadRequest(e.to_string()))?;
+
+    let effective =
+        preview_config(&config).map_err(|e| SettingsError::BadRequest(e.to_string()))?;
+    let issues = validate_config(&effective);
+    if !issues.is_empty() {
+        return Err(SettingsError::Invalid(ConfigReport {
+            valid: false,
+            issues,
+        }));
+    }
+
+    let config = write_config(&config).map_err(|e| SettingsError::Failed(e.to_string()))?;
+
+    let conn = connect_db().map_err(|e| SettingsError::Failed(e.to_string()))?;
+    populate_tables(&conn, &config).map_err(|e| SettingsError::Failed(e.to_string()))?;
+    conn.close()
+        .map_err(|(_, e)| SettingsError::Failed(e.to_string()))?;
+
+    Ok(())
+}
+
+// Probes every enabled instance of a service, None for unknown services
+pub async fn test_connection(config: &ConfigFields, service: &str) -> Option<Vec<ConnectionTest>> {
+    let service = find_service(service)?;
+    let mut results = Vec::new();
+
+    for instance in service.instances(config) {
+        if !instance.enabled() {
+            continue;
+        }
+
+        let result = service.probe(config, instance.name()).await;
+        results.push(ConnectionTest {
+            instance: String::from(instance.name()),
+            ok: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        });
+    }
+
+    Some(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_default_config;
+
+    fn config() -> Value {
+        let mut config = create_default_config();
+        config.radarr[0].api_key = String::from("radarr-key");
+        config.radarr[0]
+            .check
+            .headers
+            .insert(String::from("Authorization"), String::from("Bearer abc"));
+        config.radarr[0]
+            .check
+            .headers
+            .insert(String::from("X-Trace"), String::new());
+        config.http.urls = vec![toml::from_str(
+            "name = \"nas\"\nurl = \"http://nas.lan\"\n[check]\nheaders = { X-Api-Key = \"nas-key\" }",
+        )
+        .unwrap()];
+
+        serde_json::to_value(config).unwrap()
+    }
+
+    #[test]
+    fn redacts_health_check_headers() {
+        let mut value = config();
+        redact(&mut value, "");
+
+        let radarr = &value["radarr"][0];
+        assert_eq!(radarr["api_key"], REDACTED);
+        assert_eq!(radarr["check"]["headers"]["Authorization"], REDACTED);
+        // Empty values stay empty, so the UI can tell they aren't set
+        assert_eq!(radarr["check"]["headers"]["X-Trace"], "");
+        assert_eq!(
+            value["http"]["urls"][0]["check"]["headers"]["X-Api-Key"],
+            REDACTED
+        );
+        assert_eq!(radarr["url"], config()["radarr"][0]["url"]);
+    }
+
+    #[test]
+    fn redacts_alert_channels() {
+        let mut config = create_default_config();
+        config.alerts.channels = [
+            "name = \"discord\"\ntype = \"discord\"\nurl = \"https://discord.com/api/webhooks/1/abc\"",
+            "name = \"hook\"\ntype = \"webhook\"\nurl = \"https://hooks.lan/x\"\nheaders = { Authorization = \"Bearer abc\" }",
+            "name = \"gotify\"\ntype = \"gotify\"\nurl = \"https://gotify.lan\"\ntoken = \"app\"",
+            "name = \"mail\"\ntype = \"smtp\"\nhost = \"mail.lan\"\npassword = \"pw\"",
+        ]
+        .iter()
+        .map(|channel| toml::from_str(channel).unwrap())
+        .collect();
+        let current = serde_json::to_value(&config).unwrap();
+
+        let mut value = current.clone();
+        redact(&mut value, "");
+        redact_channel_urls(&mut value, &config);
+
+        let channels = &value["alerts"]["channels"];
+        assert_eq!(channels[0]["url"], REDACTED);
+        assert_eq!(channels[1]["url"], REDACTED);
+        assert_eq!(channels[1]["headers"]["Authorization"], REDACTED);
+        assert_eq!(channels[2]["url"], "https://gotify.lan");
+        assert_eq!(channels[2]["token"], REDACTED);
+        assert_eq!(channels[3]["host"], "mail.lan");
+        assert_eq!(channels[3]["password"], REDACTED);
+        assert!(!value.to_string().contains("abc"));
+
+        // Matched by name, so reordering channels keeps their urls
+        value["alerts"]["channels"]
+            .as_array_mut()
+            .unwrap()
+            .reverse();
+        restore_secrets(&mut value, Some(&current));
+        value["alerts"]["channels"]
+            .as_array_mut()
+            .unwrap()
+            .reverse();
+        assert_eq!(value, current);
+    }
+
+    #[test]
+    fn restores_redacted_values() {
+        let current = config();
+        let mut value = current.clone();
+        redact(&mut value, "");
+
+        restore_secrets(&mut value, Some(&current));
+        assert_eq!(value, current);
+    }
+
+    #[test]
+    fn keeps_changed_and_removed_values() {
+        let current = config();
+        let mut value = current.clone();
+        redact(&mut value, "");
+
+        let headers = value["radarr"][0]["check"]["headers"]
+            .as_object_mut()
+            .unwrap();
+        headers.remove("Authorization");
+        headers.insert(String::from("X-New"), Value::from("new"));
+        value["http"]["urls"][0]["check"]["headers"]["X-Api-Key"] = Value::from("changed");
+
+        restore_secrets(&mut value, Some(&current));
+        let headers = &value["radarr"][0]["check"]["headers"];
+        assert!(headers.get("Authorization").is_none());
+        assert_eq!(headers["X-New"], "new");
+        assert_eq!(
+            value["http"]["urls"][0]["check"]["headers"]["X-Api-Key"],
+            "changed"
+        );
+        assert_eq!(value["radarr"][0]["api_key"], "radarr-key");
+    }
+}
@@ -0,0 +1,853 @@
+use regex::Regex;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Method, Url};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use tracing_subscriber::EnvFilter;
+use tuono_lib::Type;
+
+use crate::alerts::rule_service;
+use crate::config::{
+    instance_display_name, load_config, APICredsConfig, APIKeyConfig, ChannelConfig, ChannelKind,
+    ConfigFields, HealthCheck, MonitorConfig, MonitorKind, ProxyAuthConfig, RuleKind, SmtpSecurity,
+    DEFAULT_INSTANCE,
+};
+use crate::database::configured_apps;
+use crate::monitor::{dns_labels, record_type};
+use crate::ping::status_ranges;
+use crate::proxy_listener::parse_cidr;
+use crate::services::registry::{find_service, SERVICES};
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ConfigIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            return write!(f, "{}", self.message);
+        }
+
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ConfigReport {
+    pub valid: bool,
+    pub issues: Vec<ConfigIssue>,
+}
+
+fn issue(issues: &mut Vec<ConfigIssue>, path: String, message: String) {
+    issues.push(ConfigIssue { path, message });
+}
+
+fn check_url(issues: &mut Vec<ConfigIssue>, path: String, url: &str) {
+    if url.is_empty() {
+        return issue(issues, path, String::from("is empty"));
+    }
+
+    match Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => (),
+        Ok(parsed) => issue(
+            issues,
+            path,
+            format!(
+                "\"{}\" uses unsupported scheme \"{}\", expected http or https",
+                url,
+                parsed.scheme()
+            ),
+        ),
+        Err(e) => issue(
+            issues,
+            path,
+            format!("\"{}\" is not a valid URL: {}", url, e),
+        ),
+    }
+}
+
+fn check_not_empty(issues: &mut Vec<ConfigIssue>, path: String, value: &str) {
+    if value.trim().is_empty() {
+        issue(issues, path, String::from("must not be empty when enabled"));
+    }
+}
+
+fn check_ca_cert(issues: &mut Vec<ConfigIssue>, path: String, ca_cert: &str) {
+    if !ca_cert.is_empty() && !Path::new(ca_cert).is_file() {
+        issue(issues, path, format!("file \"{}\" does not exist", ca_cert));
+    }
+}
+
+fn check_health(issues: &mut Vec<ConfigIssue>, path: String, check: &HealthCheck) {
+    if Method::from_bytes(check.method.to_uppercase().as_bytes()).is_err() {
+        issue(
+            issues,
+            format!("{}.method", path),
+            format!("\"{}\" is not an HTTP method", check.method),
+        );
+    }
+    if check.expected_status.is_empty() {
+        issue(
+            issues,
+            format!("{}.expected_status", path),
+            String::from("must not be empty"),
+        );
+    }
+    if let Err(e) = status_ranges(&check.expected_status) {
+        issue(issues, format!("{}.expected_status", path), e);
+    }
+    if let Err(e) = Regex::new(&check.body_regex) {
+        issue(
+            issues,
+            format!("{}.body_regex", path),
+            format!("invalid regex: {}", e),
+        );
+    }
+    if !check.json_value.is_empty() && check.json_path.is_empty() {
+        issue(
+            issues,
+            format!("{}.json_path", path),
+            String::from("must not be empty when json_value is set"),
+        );
+    }
+    for (name, value) in &check.headers {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() || HeaderValue::from_str(value).is_err()
+        {
+            issue(
+                issues,
+                format!("{}.headers.{}", path, name),
+                String::from("is not a valid header"),
+            );
+        }
+    }
+}
+
+fn check_monitor(issues: &mut Vec<ConfigIssue>, path: String, monitor: &MonitorConfig) {
+    if monitor.host.trim().is_empty() {
+        issue(issues, format!("{}.host", path), String::from("is empty"));
+    }
+
+    match monitor.kind {
+        MonitorKind::Tcp | MonitorKind::Tls if monitor.port == 0 => issue(
+            issues,
+            format!("{}.port", path),
+            String::from("must be set"),
+        ),
+        MonitorKind::Dns => {
+            // Empty hosts are reported above
+            match dns_labels(&monitor.host) {
+                Err(e) if !monitor.host.trim().is_empty() => {
+                    issue(issues, format!("{}.host", path), e)
+                }
+                _ => (),
+            }
+            if monitor.resolver.trim().is_empty() {
+                issue(
+                    issues,
+                    format!("{}.resolver", path),
+                    String::from("is empty"),
+                );
+            }
+            if record_type(&monitor.record).is_none() {
+                issue(
+                    issues,
+                    format!("{}.record", path),
+                    format!(
+                        "\"{}\" is not supported, expected A or AAAA",
+                        monitor.record
+                    ),
+                );
+            }
+            for (index, expected) in monitor.expected.iter().enumerate() {
+                if expected.parse::<IpAddr>().is_err() {
+                    issue(
+                        issues,
+                        format!("{}.expected[{}]", path, index),
+                        format!("\"{}\" is not an IP address", expected),
+                    );
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn check_channel(issues: &mut Vec<ConfigIssue>, path: String, channel: &ChannelConfig) {
+    match channel.kind {
+        ChannelKind::Smtp => {
+            check_not_empty(issues, format!("{}.host", path), &channel.host);
+            check_not_empty(issues, format!("{}.from", path), &channel.from);
+            if channel.port == 0 {
+                issue(
+                    issues,
+                    format!("{}.port", path),
+                    String::from("must be set"),
+                );
+            }
+            if channel.to.is_empty() {
+                issue(
+                    issues,
+                    format!("{}.to", path),
+                    String::from("needs at least one address"),
+                );
+            }
+            if channel.security == SmtpSecurity::None && !channel.username.is_empty() {
+                issue(
+                    issues,
+                    format!("{}.security", path),
+                    String::from("must be starttls or tls when a username is set"),
+                );
+            }
+        }
+        kind => {
+            check_url(issues, format!("{}.url", path), &channel.url);
+            if kind == ChannelKind::Gotify {
+                check_not_empty(issues, format!("{}.token", path), &channel.token);
+            }
+        }
+    }
+
+    for (name, value) in &channel.headers {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() || HeaderValue::from_str(value).is_err()
+        {
+            issue(
+                issues,
+                format!("{}.headers.{}", path, name),
+                String::from("is not a valid header"),
+            );
+        }
+    }
+}
+
+// names are the services table names, which service_down rules can watch
+fn check_alerts(issues: &mut Vec<ConfigIssue>, config: &ConfigFields, names: &HashSet<String>) {
+    let alerts = &config.alerts;
+
+    if alerts.interval == 0 {
+        issue(
+            issues,
+            String::from("alerts.interval"),
+            String::from("must be at least 1 second"),
+        );
+    }
+
+    let mut channels = HashSet::new();
+    for (index, channel) in alerts.channels.iter().enumerate() {
+        let path = format!("alerts.channels[{}]", index);
+
+        if channel.name.trim().is_empty() {
+            issue(issues, format!("{}.name", path), String::from("is empty"));
+        } else if !channels.insert(channel.name.as_str()) {
+            issue(
+                issues,
+                format!("{}.name", path),
+                format!("duplicate name \"{}\"", channel.name),
+            );
+        }
+
+        check_channel(issues, path, channel);
+    }
+
+    let mut rules = HashSet::new();
+    for (index, rule) in alerts.rules.iter().enumerate() {
+        let path = format!("alerts.rules[{}]", index);
+
+        if rule.name.trim().is_empty() {
+            issue(issues, format!("{}.name", path), String::from("is empty"));
+        } else if !rules.insert(rule.name.as_str()) {
+            issue(
+                issues,
+                format!("{}.name", path),
+                format!("duplicate name \"{}\"", rule.name),
+            );
+        }
+
+        if alerts.channels.is_empty() {
+            issue(
+                issues,
+                format!("{}.channels", path),
+                String::from("no channels are configured"),
+            );
+        }
+        for channel in &rule.channels {
+            if !channels.contains(channel.as_str()) {
+                issue(
+                    issues,
+                    format!("{}.channels", path),
+                    format!("unknown channel \"{}\"", channel),
+                );
+            }
+        }
+
+        // Instance names of the watched integration, or every pinged service
+        let watchable: HashSet<String> = match rule_service(rule.kind).and_then(find_service) {
+            Some(service) => service
+                .instances(config)
+                .iter()
+                .map(|instance| instance_display_name(service.name(), instance.name()))
+                .collect(),
+            None => names.clone(),
+        };
+        for service in &rule.services {
+            if !watchable.contains(service) {
+                issue(
+                    issues,
+                    format!("{}.services", path),
+                    format!("unknown service \"{}\"", service),
+                );
+            }
+        }
+
+        let threshold = rule.threshold();
+        match rule.kind {
+            RuleKind::StorageUsage if !(0.0..100.0).contains(&threshold) => issue(
+                issues,
+                format!("{}.threshold", path),
+                String::from("must be a percentage below 100"),
+            ),
+            RuleKind::ContainerUnhealthy if threshold < 0.0 => issue(
+                issues,
+                format!("{}.threshold", path),
+                String::from("must not be negative"),
+            ),
+            _ => (),
+        }
+    }
+}
+
+// names are the services table names groups can see
+fn check_proxy_auth(
+    issues: &mut Vec<ConfigIssue>,
+    proxy: &ProxyAuthConfig,
+    names: &HashSet<String>,
+) {
+    for (path, header) in [
+        ("auth.proxy.user_header", &proxy.user_header),
+        ("auth.proxy.groups_header", &proxy.groups_header),
+    ] {
+        if HeaderName::from_bytes(header.as_bytes()).is_err() {
+            issue(
+                issues,
+                String::from(path),
+                format!("\"{}\" is not a valid header name", header),
+            );
+        }
+    }
+
+    for (path, address) in [
+        ("auth.proxy.listen", &proxy.listen),
+        ("auth.proxy.upstream", &proxy.upstream),
+    ] {
+        if address.parse::<SocketAddr>().is_err() {
+            issue(
+                issues,
+                String::from(path),
+                format!("\"{}\" is not an address and port", address),
+            );
+        }
+    }
+
+    if proxy.trusted_proxies.is_empty() {
+        issue(
+            issues,
+            String::from("auth.proxy.trusted_proxies"),
+            String::from("is empty, no proxy would be trusted"),
+        );
+    }
+    for (index, cidr) in proxy.trusted_proxies.iter().enumerate() {
+        if parse_cidr(cidr).is_none() {
+            issue(
+                issues,
+                format!("auth.proxy.trusted_proxies[{}]", index),
+                format!("\"{}\" is not an address or CIDR", cidr),
+            );
+        }
+    }
+
+    let mut groups = HashSet::new();
+    for (index, group) in proxy.groups.iter().enumerate() {
+        let path = format!("auth.proxy.groups[{}]", index);
+
+        if group.name.trim().is_empty() {
+            issue(issues, format!("{}.name", path), String::from("is empty"));
+        } else if !groups.insert(group.name.as_str()) {
+            issue(
+                issues,
+                format!("{}.name", path),
+                format!("duplicate name \"{}\"", group.name),
+            );
+        }
+
+        for service in &group.services {
+            if !names.contains(service) {
+                issue(
+                    issues,
+                    format!("{}.services", path),
+                    format!("unknown service \"{}\"", service),
+                );
+            }
+        }
+    }
+}
+
+fn check_instance_names<'a>(
+    issues: &mut Vec<ConfigIssue>,
+    section: &str,
+    names: impl Iterator<Item = &'a str>,
+) {
+    let mut seen = HashSet::new();
+
+    for (index, name) in names.enumerate() {
+        let path = format!("{}[{}].name", section, index);
+
+        // Names are route segments, e.g. /api/radarr/4k/movies
+        if name.is_empty() {
+            issue(issues, path, String::from("is empty"));
+        } else if !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            issue(
+                issues,
+                path,
+                format!(
+                    "\"{}\" may only contain letters, digits, \"-\", \"_\" and \".\"",
+                    name
+                ),
+            );
+        } else if name == DEFAULT_INSTANCE && index > 0 {
+            // "default" also selects the first instance, so only the first may be called that
+            issue(
+                issues,
+                path,
+                format!(
+                    "only the first instance may be named \"{}\"",
+                    DEFAULT_INSTANCE
+                ),
+            );
+        } else if !seen.insert(name) {
+            issue(
+                issues,
+                path,
+                format!("duplicate instance name \"{}\"", name),
+            );
+        }
+    }
+}
+
+pub fn check_key_instances(
+    issues: &mut Vec<ConfigIssue>,
+    section: &str,
+    instances: &[APIKeyConfig],
+) {
+    check_instance_names(issues, section, instances.iter().map(|i| i.name.as_str()));
+
+    for (index, instance) in instances.iter().enumerate() {
+        if !instance.enabled {
+            continue;
+        }
+
+        check_url(issues, format!("{}[{}].url", section, index), &instance.url);
+        check_not_empty(
+            issues,
+            format!("{}[{}].api_key", section, index),
+            &instance.api_key,
+        );
+        check_ca_cert(
+            issues,
+            format!("{}[{}].ca_cert", section, index),
+            &instance.ca_cert,
+        );
+        check_health(
+            issues,
+            format!("{}[{}].check", section, index),
+            &instance.check,
+        );
+    }
+}
+
+pub fn check_creds_instances(
+    issues: &mut Vec<ConfigIssue>,
+    section: &str,
+    instances: &[APICredsConfig],
+) {
+    check_instance_names(issues, section, instances.iter().map(|i| i.name.as_str()));
+
+    for (index, instance) in instances.iter().enumerate() {
+        if !instance.enabled {
+            continue;
+        }
+
+        check_url(issues, format!("{}[{}].url", section, index), &instance.url);
+        check_not_empty(
+            issues,
+            format!("{}[{}].username", section, index),
+            &instance.username,
+        );
+        check_not_empty(
+            issues,
+            format!("{}[{}].password", section, index),
+            &instance.password,
+        );
+        check_ca_cert(
+            issues,
+            format!("{}[{}].ca_cert", section, index),
+            &instance.ca_cert,
+        );
+        check_health(
+            issues,
+            format!("{}[{}].check", section, index),
+            &instance.check,
+        );
+    }
+}
+
+// Collects every problem in the config at once, paths match the TOML layout
+pub fn validate_config(config: &ConfigFields) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+
+    check_url(&mut issues, String::from("base_url"), &config.base_url);
+
+    if config.weather_location.len() != 2 {
+        issue(
+            &mut issues,
+            String::from("weather_location"),
+            format!(
+                "expected exactly two values [latitude, longitude], got {}",
+                config.weather_location.len()
+            ),
+        );
+    } else {
+        let (lat, long) = (config.weather_location[0], config.weather_location[1]);
+        if !(-90.0..=90.0).contains(&lat) {
+            issue(
+                &mut issues,
+                String::from("weather_location[0]"),
+                format!("latitude {} is out of range (-90 to 90)", lat),
+            );
+        }
+        if !(-180.0..=180.0).contains(&long) {
+            issue(
+                &mut issues,
+                String::from("weather_location[1]"),
+                format!("longitude {} is out of range (-180 to 180)", long),
+            );
+        }
+    }
+
+    if config.tvdb.enabled {
+        check_not_empty(
+            &mut issues,
+            String::from("tvdb.api_key"),
+            &config.tvdb.api_key,
+        );
+    }
+    if config.tmdb.enabled {
+        check_not_empty(
+            &mut issues,
+            String::from("tmdb.api_key"),
+            &config.tmdb.api_key,
+        );
+    }
+
+    for service in SERVICES {
+        service.validate(config, &mut issues);
+    }
+
+    if config.http_client.connect_timeout == 0 {
+        issue(
+            &mut issues,
+            String::from("http_client.connect_timeout"),
+            String::from("must be at least 1 second"),
+        );
+    }
+    if config.http_client.read_timeout == 0 {
+        issue(
+            &mut issues,
+            String::from("http_client.read_timeout"),
+            String::from("must be at least 1 second"),
+        );
+    }
+    if !config.http_client.proxy.is_empty() {
+        if let Err(e) = reqwest::Proxy::all(&config.http_client.proxy) {
+            issue(
+                &mut issues,
+                String::from("http_client.proxy"),
+                format!(
+                    "\"{}\" is not a valid proxy: {}",
+                    config.http_client.proxy, e
+                ),
+            );
+        }
+    }
+
+    if let Err(e) = EnvFilter::try_new(&config.logging.level) {
+        issue(
+            &mut issues,
+            String::from("logging.level"),
+            format!("\"{}\" is not a valid level: {}", config.logging.level, e),
+        );
+    }
+
+    if config.auth.enabled && config.auth.session_days == 0 {
+        issue(
+            &mut issues,
+            String::from("auth.session_days"),
+            String::from("must be at least 1 day"),
+        );
+    }
+
+    if config.poller.interval == 0 {
+        issue(
+            &mut issues,
+            String::from("poller.interval"),
+            String::from("must be at least 1 second"),
+        );
+    }
+    if config.poller.ping_interval == 0 {
+        issue(
+            &mut issues,
+            String::from("poller.ping_interval"),
+            String::from("must be at least 1 second"),
+        );
+    }
+    if config.ping.concurrency == 0 {
+        issue(
+            &mut issues,
+            String::from("ping.concurrency"),
+            String::from("must be at least 1"),
+        );
+    }
+    if config.ping.timeout == 0 {
+        issue(
+            &mut issues,
+            String::from("ping.timeout"),
+            String::from("must be at least 1 second"),
+        );
+    }
+    if config.ping.history_raw_hours == 0 {
+        issue(
+            &mut issues,
+            String::from("ping.history_raw_hours"),
+            String::from("must be at least 1 hour"),
+        );
+    }
+    if config.ping.history_days * 24 < config.ping.history_raw_hours {
+        issue(
+            &mut issues,
+            String::from("ping.history_days"),
+            String::from("must cover at least history_raw_hours"),
+        );
+    }
+    if config.ping.failure_threshold == 0 {
+        issue(
+            &mut issues,
+            String::from("ping.failure_threshold"),
+            String::from("must be at least 1"),
+        );
+    }
+    if config.ping.recovery_threshold == 0 {
+        issue(
+            &mut issues,
+            String::from("ping.recovery_threshold"),
+            String::from("must be at least 1"),
+        );
+    }
+    if config.events.live_interval == 0 {
+        issue(
+            &mut issues,
+            String::from("events.live_interval"),
+            String::from("must be at least 1 second"),
+        );
+    }
+    if config.events.metrics_interval == 0 {
+        issue(
+            &mut issues,
+            String::from("events.metrics_interval"),
+            String::from("must be at least 1 second"),
+        );
+    }
+    for (service, interval) in &config.poller.intervals {
+        let path = format!("poller.intervals.{}", service);
+
+        if find_service(service).is_none() {
+            issue(
+                &mut issues,
+                path,
+                format!("unknown service \"{}\"", service),
+            );
+        } else if *interval == 0 {
+            issue(&mut issues, path, String::from("must be at least 1 second"));
+        }
+    }
+
+    // HTTP and monitor names share the services table with the built-in apps
+    let mut names: HashSet<String> = configured_apps(config)
+        .into_iter()
+        .map(|(name, _url)| name)
+        .collect();
+
+    if config.http.enabled {
+        for (index, url) in config.http.urls.iter().enumerate() {
+            if url.name.trim().is_empty() {
+                issue(
+                    &mut issues,
+                    format!("http.urls[{}].name", index),
+                    String::from("is empty"),
+                );
+            } else if !names.insert(url.name.clone()) {
+                issue(
+                    &mut issues,
+                    format!("http.urls[{}].name", index),
+                    format!("duplicate name \"{}\"", url.name),
+                );
+            }
+
+            check_url(&mut issues, format!("http.urls[{}].url", index), &url.url);
+            check_health(
+                &mut issues,
+                format!("http.urls[{}].check", index),
+                &url.check,
+            );
+        }
+    }
+
+    for (index, monitor) in config.monitors.iter().enumerate() {
+        let path = format!("monitors[{}]", index);
+
+        if monitor.name.trim().is_empty() {
+            issue(
+                &mut issues,
+                format!("{}.name", path),
+                String::from("is empty"),
+            );
+        } else if !names.insert(monitor.name.clone()) {
+            issue(
+                &mut issues,
+                format!("{}.name", path),
+                format!("duplicate name \"{}\"", monitor.name),
+            );
+        }
+
+        check_monitor(&mut issues, path, monitor);
+    }
+
+    check_alerts(&mut issues, config, &names);
+    if config.auth.enabled && config.auth.proxy.enabled {
+        check_proxy_auth(&mut issues, &config.auth.proxy, &names);
+    }
+
+    issues
+}
+
+// Loads and validates a config file without touching it, parse errors are reported as issues
+pub fn check_config_file(path: &str) -> ConfigReport {
+    let issues = match load_config(path) {
+        Ok(config) => validate_config(&config),
+        Err(e) => vec![ConfigIssue {
+            path: String::new(),
+            message: e.to_string(),
+        }],
+    };
+
+    ConfigReport {
+        valid: issues.is_empty(),
+        issues,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::create_default_config;
+
+    fn paths(issues: &[ConfigIssue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.path.as_str()).collect()
+    }
+
+    fn name_issues(names: &[&str]) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        check_instance_names(&mut issues, "radarr", names.iter().copied());
+        issues
+    }
+
+    #[test]
+    fn accepts_the_default_config() {
+        let issues = validate_config(&create_default_config());
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+
+    #[test]
+    fn checks_instance_names() {
+        assert!(name_issues(&["default", "4k", "anime_1", "v2.0"]).is_empty());
+
+        for name in ["", "4k movies", "a/b", "4k\t", "ümlaut", "a?b"] {
+            assert_eq!(
+                paths(&name_issues(&["default", name])),
+                ["radarr[1].name"],
+                "{:?}",
+                name
+            );
+        }
+
+        // "default" would be ambiguous anywhere but first
+        assert_eq!(paths(&name_issues(&["4k", "default"])), ["radarr[1].name"]);
+        assert_eq!(paths(&name_issues(&["4k", "hd", "4k"])), ["radarr[2].name"]);
+    }
+
+    #[test]
+    fn reports_instance_paths() {
+        let mut config = create_default_config();
+        config.radarr[0].enabled = true;
+        config.radarr[0].url = String::from("ftp://nas.lan");
+        config.radarr.push(config.radarr[0].clone());
+
+        let issues = validate_config(&config);
+        assert_eq!(
+            paths(&issues),
+            [
+                "radarr[1].name",
+                "radarr[0].url",
+                "radarr[0].api_key",
+                "radarr[1].url",
+                "radarr[1].api_key"
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_smtp_channels() {
+        let mut config = create_default_config();
+        config.alerts.channels = vec![toml::from_str(
+            "name = \"mail\"\ntype = \"smtp\"\nhost = \"mail.lan\"\nfrom = \"a@lan\"\nto = [\"b@lan\"]\nsecurity = \"none\"",
+        )
+        .unwrap()];
+        assert!(validate_config(&config).is_empty());
+
+        config.alerts.channels[0].username = String::from("homedash");
+        assert_eq!(
+            paths(&validate_config(&config)),
+            ["alerts.channels[0].security"]
+        );
+    }
+
+    #[test]
+    fn checks_health_check_headers() {
+        let mut config = create_default_config();
+        config.radarr[0].enabled = true;
+        config.radarr[0].url = String::from("http://nas.lan:7878");
+        config.radarr[0].api_key = String::from("key");
+        config.radarr[0]
+            .check
+            .headers
+            .insert(String::from("Bad Header"), String::from("x"));
+
+        let issues = validate_config(&config);
+        assert_eq!(paths(&issues), ["radarr[0].check.headers.Bad Header"]);
+    }
+}